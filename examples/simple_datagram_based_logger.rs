@@ -10,97 +10,23 @@ fn main() -> io::Result<()> {
 #[cfg(unix)]
 mod unix {
     use std::{
-        io::{self, IsTerminal, Write},
+        io::{self, IsTerminal},
         os::unix::net::UnixDatagram,
     };
 
-    use arrayvec::ArrayVec;
-    use parking_lot::Mutex;
-    use syslog_fmt::{
-        v5424::{self, Timestamp},
-        Facility, Severity,
-    };
-
-    const SYSLOG_MSG_BUFFER_LEN: usize = 1024;
+    use syslog_fmt::{log::SyslogLogger, v5424, Facility};
 
-    struct DatagramLogger {
-        socket: UnixDatagram,
-        formatter: v5424::Formatter,
-        buf: Mutex<ArrayVec<u8, SYSLOG_MSG_BUFFER_LEN>>,
-        log_level: log::LevelFilter,
-    }
+    /// Send each message written as a datagram
+    struct DatagramSink(UnixDatagram);
 
-    impl log::Log for DatagramLogger {
-        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
-            metadata.level() <= self.log_level
+    impl io::Write for DatagramSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.send(buf)
         }
 
-        fn log(&self, record: &log::Record<'_>) {
-            if self.enabled(record.metadata()) {
-                let mut buf = self.buf.lock();
-
-                let res = self.formatter.write_without_data(
-                    &mut *buf,
-                    Severity::Info,
-                    Timestamp::CreateChronoLocal,
-                    record.args(),
-                    None,
-                );
-
-                if let Err(e) = res {
-                    // ignore when the buffer runs over capcity
-                    // write as much as you can and drop the rest
-                    if e.kind() != io::ErrorKind::WriteZero {
-                        eprintln!("{e}");
-                    }
-                }
-
-                if let Err(e) = self.socket.send(&buf) {
-                    eprintln!("{e}");
-                }
-            }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
-
-        fn flush(&self) {}
-    }
-
-    struct StdErrLogger {
-        formatter: v5424::Formatter,
-        buf: Mutex<ArrayVec<u8, SYSLOG_MSG_BUFFER_LEN>>,
-        log_level: log::LevelFilter,
-    }
-
-    impl log::Log for StdErrLogger {
-        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
-            metadata.level() <= self.log_level
-        }
-
-        fn log(&self, record: &log::Record<'_>) {
-            if self.enabled(record.metadata()) {
-                let mut buf = self.buf.lock();
-
-                let res = self.formatter.write_without_data(
-                    &mut *buf,
-                    Severity::Info,
-                    Timestamp::CreateChronoLocal,
-                    record.args(),
-                    None,
-                );
-
-                if let Err(e) = res {
-                    // ignore when the buffer runs over capcity
-                    // write as much as you can and drop the rest
-                    if e.kind() != io::ErrorKind::WriteZero {
-                        eprintln!("{e}");
-                    }
-                }
-
-                let bytes_written = std::io::stderr().write(&buf).unwrap();
-                debug_assert!(bytes_written > 0);
-            }
-        }
-
-        fn flush(&self) {}
     }
 
     pub fn run() -> io::Result<()> {
@@ -113,7 +39,7 @@ mod unix {
     }
 
     fn setup_logger() -> io::Result<()> {
-        if std::io::stderr().is_terminal() {
+        if io::stderr().is_terminal() {
             setup_stderr_logger()
         } else {
             setup_datagram_logger()
@@ -122,13 +48,9 @@ mod unix {
 
     fn setup_stderr_logger() -> io::Result<()> {
         let formatter = setup_syslog_formatter();
-        let logger = StdErrLogger {
-            formatter,
-            buf: Mutex::new(ArrayVec::new()),
-            log_level: log::LevelFilter::Info,
-        };
+        let logger = SyslogLogger::new(formatter, io::stderr(), log::LevelFilter::Info);
 
-        log::set_max_level(logger.log_level);
+        log::set_max_level(logger.level());
         log::set_boxed_logger(Box::new(logger))
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
 
@@ -140,15 +62,10 @@ mod unix {
 
         let socket = any_datagram_socket(&UNIX_SOCK_PATHS)?;
         let formatter = setup_syslog_formatter();
+        let logger = SyslogLogger::new(formatter, DatagramSink(socket), log::LevelFilter::Info)
+            .with_buffer_len::<1024>();
 
-        let logger = DatagramLogger {
-            socket,
-            formatter,
-            buf: Mutex::new(ArrayVec::new()),
-            log_level: log::LevelFilter::Info,
-        };
-
-        log::set_max_level(logger.log_level);
+        log::set_max_level(logger.level());
         log::set_boxed_logger(Box::new(logger))
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
 
@@ -160,7 +77,8 @@ mod unix {
             facility: Facility::Local0,
             hostname: Some("localhost"),
             app_name: Some("simple_datagram_based_logger"),
-            proc_id: std::process::id().to_string().as_str().into(),
+            process_proc_id: true,
+            ..Default::default()
         }
         .into_formatter()
     }
@@ -12,6 +12,7 @@ mod unix {
     use std::{io, os::unix::net::UnixDatagram};
 
     use syslog_fmt::{
+        transport::RetryPolicy,
         v5424::{self, Timestamp},
         Facility, Severity,
     };
@@ -25,7 +26,8 @@ mod unix {
             facility: Facility::Local0,
             hostname: Some("localhost"),
             app_name: Some("unix_datagram_example"),
-            proc_id: std::process::id().to_string().as_str().into(),
+            process_proc_id: true,
+            ..Default::default()
         }
         .into_formatter();
 
@@ -38,7 +40,8 @@ mod unix {
             None,
         )?;
 
-        socket.send(&buf)?;
+        // the send can fail transiently when the socket buffer is full
+        RetryPolicy::default().send(|| socket.send(&buf))?;
 
         Ok(())
     }
@@ -0,0 +1,105 @@
+//! The parts of `std::io` the formatter writes through, for a build without the `std` feature.
+//!
+//! This lets the formatter share a single implementation with and without `std`. Without `std` a message
+//! is written into a `core::fmt::Write` with [Formatter::format_into](crate::v5424::Formatter::format_into),
+//! which reports any error as a `fmt::Error`, so an error carries no information.
+use alloc::vec::Vec;
+use core::fmt;
+
+pub(crate) type Result<T> = core::result::Result<T, Error>;
+
+/// The kinds of error the formatter returns, kept so an error is created the same way with and without `std`
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum ErrorKind {
+    InvalidInput,
+    InvalidData,
+    WriteZero,
+    Other,
+}
+
+/// An error of the formatter, the kind and description are dropped as there is no way to report them
+#[derive(Debug)]
+pub(crate) struct Error;
+
+impl Error {
+    pub(crate) fn new<E>(_kind: ErrorKind, _description: E) -> Self {
+        Self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(_kind: ErrorKind) -> Self {
+        Self
+    }
+}
+
+/// A sink of bytes like `std::io::Write`
+pub(crate) trait Write {
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Kept so a writer implements the same methods with and without `std`
+    #[allow(dead_code)]
+    fn flush(&mut self) -> Result<()>;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(ErrorKind::WriteZero.into()),
+                n => buf = &buf[n..],
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the formatted arguments, the target of the `write!` macro
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        /// Keeps the error of the writer, as `fmt::Error` carries no information
+        struct Adapter<'w, W: ?Sized> {
+            w: &'w mut W,
+            result: Result<()>,
+        }
+
+        impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.w.write_all(s.as_bytes()).map_err(|e| {
+                    self.result = Err(e);
+                    fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            w: self,
+            result: Ok(()),
+        };
+
+        match fmt::write(&mut adapter, args) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => adapter
+                .result
+                .and(Err(Error::new(ErrorKind::Other, "formatter error"))),
+        }
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
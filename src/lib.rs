@@ -2,8 +2,27 @@
 //!
 //! This crate does not provide a transport method to get the message to the syslog daemon.
 //! The focus is to correctly format a message ready for transport.
+//!
+//! The `std` feature is enabled by default and provides the `io::Write` based writers of [v5424]
+//! and the [transport] helpers. Without it the crate is `no_std` and requires only `alloc`,
+//! a message is then written into a `core::fmt::Write` with [v5424::Formatter::format_into].
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 use core::{fmt, marker::PhantomData};
+#[cfg(not(feature = "std"))]
+mod io;
+#[cfg(feature = "log")]
+pub mod log;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+#[cfg(feature = "std")]
+pub mod transport;
 pub mod v5424;
 
 /// The Priority value is calculated by first multiplying the Facility
@@ -15,11 +34,89 @@ pub mod v5424;
 /// have a Priority value of 165.
 ///
 /// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.1)
-type Priority = u8;
+///
+/// A Priority is always in the valid range `0..=191`, it's written as the number, e.g. `165`.
+///
+/// ```rust
+/// use syslog_fmt::{Facility, Priority, Severity};
+///
+/// let priority = Priority::new(Facility::Local4, Severity::Notice);
+/// assert_eq!(priority.value(), 165);
+/// assert_eq!(priority.facility().unwrap(), Facility::Local4);
+/// assert_eq!(priority.severity(), Severity::Notice);
+/// assert_eq!(Priority::try_from(165).unwrap(), priority);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Priority(u8);
+
+impl Priority {
+    /// The highest valid Priority, the `Local7` facility with the `Debug` severity
+    const MAX: u8 = 191;
+
+    pub fn new(facility: Facility, severity: Severity) -> Self {
+        Self(facility.code() | severity.code())
+    }
+
+    /// The numerical value of the Priority
+    pub fn value(self) -> u8 {
+        self.0
+    }
+
+    /// The facility of the Priority.
+    ///
+    /// A relayed Priority can have a facility that isn't known to this crate, e.g. 12 to 15,
+    /// in which case an error is returned.
+    pub fn facility(self) -> Result<Facility, IntToEnumError<Facility>> {
+        Facility::try_from(i32::from(self.0 >> 3))
+    }
+
+    /// The severity of the Priority
+    pub fn severity(self) -> Severity {
+        match self.0 & 0b111 {
+            0 => Severity::Emerg,
+            1 => Severity::Alert,
+            2 => Severity::Crit,
+            3 => Severity::Err,
+            4 => Severity::Warning,
+            5 => Severity::Notice,
+            6 => Severity::Info,
+            _ => Severity::Debug,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<Priority> for u8 {
+    fn from(priority: Priority) -> Self {
+        priority.0
+    }
+}
+
+/// Try convert a raw PRI value into a Priority, the value must be in the range `0..=191`
+impl TryFrom<u8> for Priority {
+    type Error = IntToEnumError<Self>;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if value > Self::MAX {
+            return Err(IntToEnumError {
+                value: value.into(),
+                target: PhantomData,
+            });
+        }
+
+        Ok(Self(value))
+    }
+}
 
 /// The facility argument is used to specify what type of program is logging the message.
 /// This lets the configuration file specify that messages from different facilities will be handled differently.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Facility {
     /// kernel messages
@@ -47,6 +144,7 @@ pub enum Facility {
     /// FTP daemon
     Ftp = 11 << 3,
     /// local use 0  (local0)
+    #[default]
     Local0 = 16 << 3,
     /// local use 1  (local1)
     Local1 = 17 << 3,
@@ -64,15 +162,81 @@ pub enum Facility {
     Local7 = 23 << 3,
 }
 
-impl Default for Facility {
-    fn default() -> Self {
-        Self::Local0
+impl Facility {
+    /// All the facilities in numeric order
+    const ALL: [Facility; 20] = [
+        Facility::Kern,
+        Facility::User,
+        Facility::Mail,
+        Facility::Daemon,
+        Facility::Auth,
+        Facility::Syslog,
+        Facility::Lpr,
+        Facility::News,
+        Facility::Uucp,
+        Facility::Cron,
+        Facility::Authpriv,
+        Facility::Ftp,
+        Facility::Local0,
+        Facility::Local1,
+        Facility::Local2,
+        Facility::Local3,
+        Facility::Local4,
+        Facility::Local5,
+        Facility::Local6,
+        Facility::Local7,
+    ];
+
+    /// Iterate over all the facilities in numeric order, e.g. to list the valid values in a help text
+    pub fn all() -> impl Iterator<Item = Facility> {
+        Self::ALL.into_iter()
+    }
+
+    /// The facility number shifted into place of the PRI, e.g. 160 for `Local4`.
+    ///
+    /// The PRI is the code of the facility combined with the code of the severity.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Facility, Priority, Severity};
+    ///
+    /// let pri = Facility::Local4.code() | Severity::Notice.code();
+    /// assert_eq!(pri, Priority::new(Facility::Local4, Severity::Notice).value());
+    /// ```
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// The facility number in the range `0..=23`, e.g. 20 for `Local4`
+    pub fn number(self) -> u8 {
+        self.code() >> 3
+    }
+
+    /// Whether the facility is one of the local use facilities `Local0` to `Local7`
+    pub fn is_local(self) -> bool {
+        matches!(
+            self,
+            Facility::Local0
+                | Facility::Local1
+                | Facility::Local2
+                | Facility::Local3
+                | Facility::Local4
+                | Facility::Local5
+                | Facility::Local6
+                | Facility::Local7
+        )
     }
 }
 
 impl fmt::Display for Facility {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
+        f.write_str(self.name())
+    }
+}
+
+impl Facility {
+    /// The name of the facility as written by `Display`
+    pub(crate) fn name(self) -> &'static str {
+        match self {
             Facility::Kern => "Kern",
             Facility::User => "User",
             Facility::Mail => "Mail",
@@ -93,15 +257,13 @@ impl fmt::Display for Facility {
             Facility::Local5 => "Local5",
             Facility::Local6 => "Local6",
             Facility::Local7 => "Local7",
-        };
-
-        f.write_str(s)
+        }
     }
 }
 
 impl<T> fmt::Display for IntToEnumError<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let enum_name: &'static str = std::any::type_name::<T>();
+        let enum_name: &'static str = core::any::type_name::<T>();
         write!(f, "Failed to convert {} to {}", self.value, enum_name)
     }
 }
@@ -153,7 +315,11 @@ impl TryFrom<i32> for Facility {
 }
 
 /// The severity of the message
-#[derive(Copy, Clone, Debug)]
+///
+/// The severities are ordered by their numerical value, so the most severe `Emerg` is the smallest
+/// and the least severe `Debug` is the largest, e.g. `Severity::Emerg < Severity::Debug`.
+/// Use [Severity::is_at_least] to compare a severity to a threshold without having to keep the inverted order in mind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum Severity {
     /// System is unusable.
@@ -180,9 +346,45 @@ pub enum Severity {
     Debug,
 }
 
-impl fmt::Display for Severity {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
+impl Severity {
+    /// All the severities in numeric order
+    const ALL: [Severity; 8] = [
+        Severity::Emerg,
+        Severity::Alert,
+        Severity::Crit,
+        Severity::Err,
+        Severity::Warning,
+        Severity::Notice,
+        Severity::Info,
+        Severity::Debug,
+    ];
+
+    /// Iterate over all the severities in numeric order, from the most to the least severe
+    pub fn all() -> impl Iterator<Item = Severity> {
+        Self::ALL.into_iter()
+    }
+
+    /// The numerical value of the severity in the range `0..=7`, e.g. 5 for `Notice`
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether this severity is at least as severe as the threshold.
+    ///
+    /// ```rust
+    /// use syslog_fmt::Severity;
+    ///
+    /// assert!(Severity::Err.is_at_least(Severity::Warning));
+    /// assert!(Severity::Warning.is_at_least(Severity::Warning));
+    /// assert!(!Severity::Info.is_at_least(Severity::Warning));
+    /// ```
+    pub fn is_at_least(self, threshold: Severity) -> bool {
+        self <= threshold
+    }
+
+    /// The name of the severity as written by `Display`
+    pub(crate) fn name(self) -> &'static str {
+        match self {
             Severity::Emerg => "Emerg",
             Severity::Alert => "Alert",
             Severity::Crit => "Crit",
@@ -191,9 +393,13 @@ impl fmt::Display for Severity {
             Severity::Notice => "Notice",
             Severity::Info => "Info",
             Severity::Debug => "Debug",
-        };
+        }
+    }
+}
 
-        f.write_str(s)
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
     }
 }
 
@@ -241,7 +447,112 @@ impl<T> fmt::Debug for IntToEnumError<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("IntToEnumError")
             .field("value", &self.value)
-            .field("target", &std::any::type_name::<T>())
+            .field("target", &core::any::type_name::<T>())
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_order_severity_from_most_to_least_severe() {
+        let severities = [
+            Severity::Emerg,
+            Severity::Alert,
+            Severity::Crit,
+            Severity::Err,
+            Severity::Warning,
+            Severity::Notice,
+            Severity::Info,
+            Severity::Debug,
+        ];
+
+        assert!(Severity::Emerg < Severity::Debug);
+        assert_eq!(severities.iter().max(), Some(&Severity::Debug));
+        assert_eq!(severities.iter().min(), Some(&Severity::Emerg));
+
+        for pair in severities.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].is_at_least(pair[1]));
+            assert!(!pair[1].is_at_least(pair[0]));
+        }
+
+        for severity in severities {
+            assert!(severity.is_at_least(severity));
+            assert!(severity.is_at_least(Severity::Debug));
+        }
+
+        assert!(!Severity::Debug.is_at_least(Severity::Emerg));
+    }
+
+    #[test]
+    fn should_iterate_over_all_variants_in_numeric_order() {
+        assert_eq!(Facility::all().count(), 20);
+        assert_eq!(Severity::all().count(), 8);
+
+        let facilities: Vec<_> = Facility::all().collect();
+        assert!(facilities
+            .windows(2)
+            .all(|pair| pair[0] as u8 <= pair[1] as u8));
+        let severities: Vec<_> = Severity::all().collect();
+        assert!(severities.windows(2).all(|pair| pair[0] < pair[1]));
+
+        for facility in Facility::all() {
+            let number = i32::from(facility as u8 >> 3);
+            assert_eq!(Facility::try_from(number).unwrap(), facility);
+            assert_eq!(facility.to_string(), format!("{facility:?}"));
+        }
+
+        for severity in Severity::all() {
+            assert_eq!(Severity::try_from(severity as u8).unwrap(), severity);
+            assert_eq!(severity.to_string(), format!("{severity:?}"));
+        }
+    }
+
+    #[test]
+    fn should_convert_priority_from_and_to_parts() {
+        // the PRI values of the RFC examples
+        let priority = Priority::new(Facility::Auth, Severity::Crit);
+        assert_eq!(u8::from(priority), 34);
+        assert_eq!(priority.to_string(), "34");
+        assert_eq!(priority.facility().unwrap(), Facility::Auth);
+        assert_eq!(priority.severity(), Severity::Crit);
+
+        let priority = Priority::try_from(165).unwrap();
+        assert_eq!(priority, Priority::new(Facility::Local4, Severity::Notice));
+
+        // a relayed PRI value can have a facility that isn't known
+        let priority = Priority::try_from(12 * 8 + 3).unwrap();
+        assert!(priority.facility().is_err());
+        assert_eq!(priority.severity(), Severity::Err);
+
+        assert_eq!(Priority::try_from(191).unwrap().value(), 191);
+        assert!(Priority::try_from(192).is_err());
+    }
+
+    #[test]
+    fn should_give_numerical_codes() {
+        assert_eq!(Facility::Local4.number(), 20);
+        assert_eq!(Facility::Local4.code(), 160);
+        assert_eq!(Facility::Kern.number(), 0);
+        assert_eq!(Severity::Notice.code(), 5);
+        assert_eq!(Severity::Emerg.code(), 0);
+
+        for facility in Facility::all() {
+            assert_eq!(facility.code(), facility.number() << 3);
+            assert_eq!(
+                Facility::try_from(i32::from(facility.number())).unwrap(),
+                facility
+            );
+
+            for severity in Severity::all() {
+                assert_eq!(
+                    facility.code() | severity.code(),
+                    Priority::new(facility, severity).value()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! A [log::Log] implementation that writes each record as a syslog message, behind the `log` feature.
+//!
+//! ```rust
+//! use syslog_fmt::{log::SyslogLogger, v5424::Config};
+//!
+//! let logger = SyslogLogger::new(
+//!     Config::default().into_formatter(),
+//!     std::io::stderr(),
+//!     log::LevelFilter::Info,
+//! );
+//! log::set_max_level(logger.level());
+//! log::set_boxed_logger(Box::new(logger)).unwrap();
+//!
+//! log::info!("'su root' failed for lonvick on /dev/pts/8");
+//! ```
+use std::{
+    io,
+    sync::{Mutex, PoisonError},
+};
+
+use arrayvec::ArrayVec;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::{
+    v5424::{sanitize_msg_id, Formatter, Timestamp},
+    Facility, Priority, Severity,
+};
+
+/// The default capacity of the buffer a message is formatted into,
+/// the message size a receiver SHOULD be able to handle.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.1)
+pub const DEFAULT_BUFFER_LEN: usize = 2048;
+
+/// Write each log record as a syslog message to the sink.
+///
+/// A record is written with the facility of the formatter, unless its target is routed to another
+/// facility with [SyslogLogger::with_target_facility]. A record is logged if its level is within
+/// the level of its facility, set with [SyslogLogger::with_facility_level], or the level of the logger.
+///
+/// A message is formatted into a buffer of `N` bytes that's reused for each record,
+/// and written to the sink with a single `write_all`. So each message is a single datagram
+/// when the sink writes to a datagram socket. A message that doesn't fit the buffer is cut off,
+/// set [Config::max_len](crate::v5424::Config::max_len) to truncate the MSG instead.
+///
+/// A message that fails to format or write is dropped, as is an error flushing the sink.
+/// Set an error handler with [SyslogLogger::with_error_handler] to report these errors.
+#[derive(Debug)]
+pub struct SyslogLogger<W, const N: usize = DEFAULT_BUFFER_LEN> {
+    formatter: Formatter,
+    sink: Mutex<W>,
+    buf: Mutex<ArrayVec<u8, N>>,
+    level: LevelFilter,
+    target_as_msg_id: bool,
+    /// The facility of the records whose target starts with the prefix, the first match is used
+    target_facilities: Vec<(&'static str, Facility)>,
+    /// The level of the records of a facility, instead of the level of the logger
+    facility_levels: Vec<(Facility, LevelFilter)>,
+    error_handler: fn(&io::Error),
+}
+
+impl<W: io::Write + Send> SyslogLogger<W> {
+    pub fn new(formatter: Formatter, sink: W, level: LevelFilter) -> Self {
+        Self {
+            formatter,
+            sink: Mutex::new(sink),
+            buf: Mutex::new(ArrayVec::new()),
+            level,
+            target_as_msg_id: false,
+            target_facilities: vec![],
+            facility_levels: vec![],
+            error_handler: drop_error,
+        }
+    }
+}
+
+impl<W: io::Write + Send, const N: usize> SyslogLogger<W, N> {
+    /// Format the messages into a buffer of `M` bytes
+    pub fn with_buffer_len<const M: usize>(self) -> SyslogLogger<W, M> {
+        let Self {
+            formatter,
+            sink,
+            buf: _,
+            level,
+            target_as_msg_id,
+            target_facilities,
+            facility_levels,
+            error_handler,
+        } = self;
+
+        SyslogLogger {
+            formatter,
+            sink,
+            buf: Mutex::new(ArrayVec::new()),
+            level,
+            target_as_msg_id,
+            target_facilities,
+            facility_levels,
+            error_handler,
+        }
+    }
+
+    /// Write the target of a record, usually the module path, as the MSG-ID.
+    ///
+    /// The target is made a valid MSG-ID with [sanitize_msg_id].
+    pub fn with_target_as_msg_id(mut self) -> Self {
+        self.target_as_msg_id = true;
+        self
+    }
+
+    /// Write the records whose target starts with the prefix with the facility.
+    ///
+    /// The prefixes are matched in the order they're added, the first match is used.
+    pub fn with_target_facility(mut self, target_prefix: &'static str, facility: Facility) -> Self {
+        self.target_facilities.push((target_prefix, facility));
+        self
+    }
+
+    /// Log the records of the facility up to the level, instead of the level of the logger.
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use syslog_fmt::{log::SyslogLogger, v5424::Config, Facility};
+    ///
+    /// // the records of `sshd` at `Info` and above, the others at `Warn` and above
+    /// let logger = SyslogLogger::new(
+    ///     Config::default().into_formatter(),
+    ///     std::io::sink(),
+    ///     LevelFilter::Warn,
+    /// )
+    /// .with_target_facility("sshd", Facility::Auth)
+    /// .with_facility_level(Facility::Auth, LevelFilter::Info);
+    /// assert_eq!(logger.level(), LevelFilter::Info);
+    /// ```
+    pub fn with_facility_level(mut self, facility: Facility, level: LevelFilter) -> Self {
+        match self
+            .facility_levels
+            .iter_mut()
+            .find(|(f, _)| *f == facility)
+        {
+            Some((_, facility_level)) => *facility_level = level,
+            None => self.facility_levels.push((facility, level)),
+        }
+        self
+    }
+
+    /// Call the handler with the error of a message that fails to format or write,
+    /// or of a failed flush, instead of dropping the error.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{log::SyslogLogger, v5424::Config};
+    ///
+    /// let logger = SyslogLogger::new(
+    ///     Config::default().into_formatter(),
+    ///     std::io::sink(),
+    ///     log::LevelFilter::Info,
+    /// )
+    /// .with_error_handler(|err| eprintln!("failed to log: {err}"));
+    /// ```
+    pub fn with_error_handler(mut self, error_handler: fn(&io::Error)) -> Self {
+        self.error_handler = error_handler;
+        self
+    }
+
+    /// The most verbose level that's logged for any facility, pass it to [log::set_max_level]
+    pub fn level(&self) -> LevelFilter {
+        self.facility_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, Ord::max)
+    }
+
+    fn target_facility(&self, target: &str) -> Facility {
+        self.target_facilities
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix))
+            .map_or_else(|| self.formatter.facility(), |(_, facility)| *facility)
+    }
+
+    fn facility_level(&self, facility: Facility) -> LevelFilter {
+        self.facility_levels
+            .iter()
+            .find(|(f, _)| *f == facility)
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    fn write_record(&self, record: &Record<'_>) -> io::Result<()> {
+        let mut buf = self.buf.lock().unwrap_or_else(PoisonError::into_inner);
+        buf.clear();
+
+        let msg_id = self
+            .target_as_msg_id
+            .then(|| sanitize_msg_id(record.target()));
+
+        let priority = Priority::new(self.target_facility(record.target()), record.level().into());
+        let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+        let res = self.formatter.write_with_priority(
+            &mut *buf,
+            priority,
+            Timestamp::CreateSystemNow,
+            record.args(),
+            msg_id.as_deref(),
+            no_data,
+        );
+
+        match res {
+            // the buffer is full, write as much as fits
+            Err(err) if err.kind() == io::ErrorKind::WriteZero => (),
+            res => res?,
+        }
+
+        let mut sink = self.sink.lock().unwrap_or_else(PoisonError::into_inner);
+        sink.write_all(&buf)
+    }
+}
+
+impl<W: io::Write + Send, const N: usize> Log for SyslogLogger<W, N> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let facility = self.target_facility(metadata.target());
+        metadata.level() <= self.facility_level(facility)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if let Err(err) = self.write_record(record) {
+            (self.error_handler)(&err);
+        }
+    }
+
+    fn flush(&self) {
+        let mut sink = self.sink.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Err(err) = sink.flush() {
+            (self.error_handler)(&err);
+        }
+    }
+}
+
+/// The default error handler, which drops the error
+fn drop_error(_err: &io::Error) {}
+
+/// `Error` maps to [Severity::Err], `Warn` to [Severity::Warning], `Info` to [Severity::Info]
+/// and both `Debug` and `Trace` to [Severity::Debug].
+impl From<Level> for Severity {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Error => Severity::Err,
+            Level::Warn => Severity::Warning,
+            Level::Info => Severity::Info,
+            Level::Debug | Level::Trace => Severity::Debug,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{v5424::Config, Facility};
+
+    fn log_into_vec<const N: usize>(logger: &SyslogLogger<Vec<u8>, N>, level: Level, msg: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("app::db")
+                .args(format_args!("{msg}"))
+                .build(),
+        );
+    }
+
+    /// Log the target as the message
+    fn log_into_vec_with_target(logger: &SyslogLogger<Vec<u8>>, level: Level, target: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{target}"))
+                .build(),
+        );
+    }
+
+    fn formatter() -> Formatter {
+        Config {
+            facility: Facility::Local4,
+            hostname: Some("mymachine"),
+            app_name: Some("app"),
+            ..Default::default()
+        }
+        .into_formatter()
+    }
+
+    #[test]
+    fn should_map_level_to_severity() {
+        let severities = Level::iter().map(Severity::from).collect::<Vec<_>>();
+        assert_eq!(
+            severities,
+            [
+                Severity::Err,
+                Severity::Warning,
+                Severity::Info,
+                Severity::Debug,
+                Severity::Debug
+            ]
+        );
+    }
+
+    #[test]
+    fn should_log_record_into_sink() {
+        let logger = SyslogLogger::new(formatter(), vec![], LevelFilter::Info);
+
+        log_into_vec(&logger, Level::Warn, "disk almost full");
+        log_into_vec(&logger, Level::Debug, "filtered out");
+
+        let sink = logger.sink.lock().unwrap();
+        let msg = crate::v5424::parse(&sink).unwrap();
+        assert_eq!(msg.priority.value(), 164);
+        assert_eq!(msg.hostname, Some("mymachine"));
+        assert_eq!(msg.app_name, Some("app"));
+        assert_eq!(msg.msg_id, None);
+        assert_eq!(msg.msg_str(), Some("disk almost full"));
+    }
+
+    #[test]
+    fn should_log_target_as_msg_id() {
+        let logger =
+            SyslogLogger::new(formatter(), vec![], LevelFilter::Trace).with_target_as_msg_id();
+
+        log_into_vec(&logger, Level::Trace, "query");
+
+        let sink = logger.sink.lock().unwrap();
+        let msg = crate::v5424::parse(&sink).unwrap();
+        assert_eq!(msg.priority.value(), 167);
+        assert_eq!(msg.msg_id, Some("app::db"));
+    }
+
+    #[test]
+    fn should_filter_per_facility_level() {
+        let logger = SyslogLogger::new(formatter(), vec![], LevelFilter::Warn)
+            .with_target_facility("sshd", Facility::Auth)
+            .with_facility_level(Facility::Auth, LevelFilter::Info);
+        assert_eq!(logger.level(), LevelFilter::Info);
+
+        let mut written = vec![];
+        for (target, level) in [
+            ("sshd", Level::Info),
+            ("sshd::session", Level::Debug),
+            ("app::db", Level::Info),
+            ("app::db", Level::Warn),
+        ] {
+            log_into_vec_with_target(&logger, level, target);
+
+            let msg = std::mem::take(&mut *logger.sink.lock().unwrap());
+            if !msg.is_empty() {
+                let msg = crate::v5424::parse(&msg).unwrap();
+                written.push((msg.priority, msg.msg_str().unwrap().to_owned()));
+            }
+        }
+
+        assert_eq!(
+            written,
+            [
+                (
+                    Priority::new(Facility::Auth, Severity::Info),
+                    "sshd".to_owned()
+                ),
+                (
+                    Priority::new(Facility::Local4, Severity::Warning),
+                    "app::db".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_pass_errors_to_error_handler() {
+        static ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+        struct FailingSink;
+
+        impl io::Write for FailingSink {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+
+        let logger = SyslogLogger::new(formatter(), FailingSink, LevelFilter::Info);
+        logger.log(&Record::builder().level(Level::Info).build());
+        logger.flush();
+        assert_eq!(ERRORS.load(Ordering::Relaxed), 0);
+
+        let logger = logger.with_error_handler(|err| {
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+            ERRORS.fetch_add(1, Ordering::Relaxed);
+        });
+        logger.log(&Record::builder().level(Level::Info).build());
+        logger.flush();
+        assert_eq!(ERRORS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn should_cut_off_message_at_buffer_len() {
+        let logger =
+            SyslogLogger::new(formatter(), vec![], LevelFilter::Info).with_buffer_len::<64>();
+
+        log_into_vec(&logger, Level::Info, &"x".repeat(100));
+
+        let sink = logger.sink.lock().unwrap();
+        assert_eq!(sink.len(), 64);
+        assert!(sink.starts_with(b"<166>1 "));
+    }
+}
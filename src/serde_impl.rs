@@ -0,0 +1,218 @@
+//! `Serialize` and `Deserialize` for [Facility] and [Severity], behind the `serde` feature.
+//!
+//! The enums are serialized by their lowercase name, e.g. `"local0"` and `"info"`.
+//! A name is deserialized case insensitive, the number is accepted as well, e.g. `16` and `6`.
+//! The number of a facility is [Facility::number], not the [Facility::code] shifted into place of the PRI.
+//! A format that isn't human readable, e.g. `bincode`, only has the name, as such a format
+//! can't tell a string from a number.
+use core::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, Unexpected},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{Facility, IntToEnumError, Severity};
+
+/// An enum that is serialized by its name
+trait Named: Copy + TryFrom<i32, Error = IntToEnumError<Self>> + 'static {
+    /// What is expected when deserializing fails
+    const EXPECTING: &'static str;
+    const ALL: &'static [Self];
+
+    fn name(self) -> &'static str;
+}
+
+impl Named for Facility {
+    const EXPECTING: &'static str = r#"a facility name like "local0" or its number"#;
+    const ALL: &'static [Self] = &Facility::ALL;
+
+    fn name(self) -> &'static str {
+        Facility::name(self)
+    }
+}
+
+impl Named for Severity {
+    const EXPECTING: &'static str = r#"a severity name like "info" or its number"#;
+    const ALL: &'static [Self] = &Severity::ALL;
+
+    fn name(self) -> &'static str {
+        Severity::name(self)
+    }
+}
+
+impl Serialize for Facility {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_lowercase(self.name(), serializer)
+    }
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_lowercase(self.name(), serializer)
+    }
+}
+
+/// The longest name of a [Facility] or [Severity]
+const MAX_NAME_LEN: usize = 8;
+
+/// Serialize the name in lowercase without allocating
+fn serialize_lowercase<S: Serializer>(name: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut buf = [0u8; MAX_NAME_LEN];
+    let buf = &mut buf[..name.len()];
+    buf.copy_from_slice(name.as_bytes());
+    buf.make_ascii_lowercase();
+    // The names are ASCII, lowercasing keeps them valid UTF-8.
+    serializer.serialize_str(core::str::from_utf8(buf).unwrap_or_default())
+}
+
+impl<'de> Deserialize<'de> for Facility {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_name_or_number(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Severity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_name_or_number(deserializer)
+    }
+}
+
+/// Deserialize the name or the number, a format that isn't self describing only has the name
+fn deserialize_name_or_number<'de, T: Named, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(NameOrNumber(PhantomData))
+    } else {
+        deserializer.deserialize_str(NameOrNumber(PhantomData))
+    }
+}
+
+/// Visits the name or the number of an enum
+struct NameOrNumber<T>(PhantomData<T>);
+
+impl<'de, T: Named> de::Visitor<'de> for NameOrNumber<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(T::EXPECTING)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        T::ALL
+            .iter()
+            .copied()
+            .find(|variant| variant.name().eq_ignore_ascii_case(v))
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        i32::try_from(v)
+            .ok()
+            .and_then(|number| T::try_from(number).ok())
+            .ok_or_else(|| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        i32::try_from(v)
+            .ok()
+            .and_then(|number| T::try_from(number).ok())
+            .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_by_lowercase_name() {
+        for facility in Facility::all() {
+            let json = serde_json::to_string(&facility).unwrap();
+            assert_eq!(
+                json,
+                format!("\"{}\"", facility.name().to_ascii_lowercase())
+            );
+            assert_eq!(serde_json::from_str::<Facility>(&json).unwrap(), facility);
+        }
+
+        for severity in Severity::all() {
+            let json = serde_json::to_string(&severity).unwrap();
+            assert_eq!(
+                json,
+                format!("\"{}\"", severity.name().to_ascii_lowercase())
+            );
+            assert_eq!(serde_json::from_str::<Severity>(&json).unwrap(), severity);
+        }
+
+        assert_eq!(
+            serde_json::to_string(&Facility::Local0).unwrap(),
+            r#""local0""#
+        );
+        assert_eq!(serde_json::to_string(&Severity::Info).unwrap(), r#""info""#);
+        assert_eq!(
+            serde_json::from_str::<Facility>(r#""Local4""#).unwrap(),
+            Facility::Local4
+        );
+    }
+
+    #[test]
+    fn should_deserialize_number() {
+        assert_eq!(
+            serde_json::from_str::<Facility>("16").unwrap(),
+            Facility::Local0
+        );
+        assert_eq!(
+            serde_json::from_str::<Facility>("0").unwrap(),
+            Facility::Kern
+        );
+        assert_eq!(
+            serde_json::from_str::<Severity>("6").unwrap(),
+            Severity::Info
+        );
+
+        // the number, not the code shifted into place of the PRI
+        let number = Facility::Local4.number().to_string();
+        assert_eq!(
+            serde_json::from_str::<Facility>(&number).unwrap(),
+            Facility::Local4
+        );
+        let code = Facility::Local4.code().to_string();
+        assert!(serde_json::from_str::<Facility>(&code).is_err());
+
+        assert!(serde_json::from_str::<Facility>("12").is_err());
+        assert!(serde_json::from_str::<Severity>("8").is_err());
+        assert!(serde_json::from_str::<Severity>("-1").is_err());
+    }
+
+    #[test]
+    fn should_round_trip_in_binary_format() {
+        for facility in Facility::all() {
+            let bytes = bincode::serialize(&facility).unwrap();
+            assert_eq!(bincode::deserialize::<Facility>(&bytes).unwrap(), facility);
+        }
+
+        for severity in Severity::all() {
+            let bytes = bincode::serialize(&severity).unwrap();
+            assert_eq!(bincode::deserialize::<Severity>(&bytes).unwrap(), severity);
+        }
+
+        let bytes = bincode::serialize("verbose").unwrap();
+        assert!(bincode::deserialize::<Severity>(&bytes).is_err());
+    }
+
+    #[test]
+    fn should_reject_unknown_name() {
+        let err = serde_json::from_str::<Facility>(r#""local8""#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"invalid value: string "local8", expected a facility name like "local0" or its number at line 1 column 8"#
+        );
+
+        let err = serde_json::from_str::<Severity>(r#""verbose""#).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(r#"expected a severity name like "info""#));
+    }
+}
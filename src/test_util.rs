@@ -0,0 +1,268 @@
+//! Helpers for testing the logging of an application without a syslog daemon.
+//!
+//! These helpers are available with the `test-util` feature, which is meant for dev-dependencies.
+use std::io;
+
+use crate::{
+    v5424::{self, NILVALUE},
+    Priority,
+};
+
+/// The framing that separates the messages written to a [CaptureSink]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Each message is prefixed with its length, see [crate::v5424::frame_octet_counting]
+    #[default]
+    OctetCounting,
+    /// Each message is followed by a LF, see [crate::v5424::frame_lf]
+    Lf,
+}
+
+/// A writer that captures framed messages in memory so they can be asserted on in a test.
+///
+/// ```rust
+/// use std::io::Write;
+///
+/// use syslog_fmt::{test_util::{CaptureSink, Framing}, v5424::Formatter, Severity};
+///
+/// let mut sink = CaptureSink::new(Framing::OctetCounting);
+/// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+/// Formatter::default()
+///     .write_framed(&mut sink, Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None, no_data)
+///     .unwrap();
+///
+/// let messages = sink.messages();
+/// assert_eq!(messages.len(), 1);
+/// assert_eq!(messages[0].msg, "a message");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CaptureSink {
+    framing: Framing,
+    buf: Vec<u8>,
+}
+
+impl CaptureSink {
+    pub fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            buf: vec![],
+        }
+    }
+
+    /// The complete frames written so far, without the framing.
+    ///
+    /// An incomplete frame at the end, e.g. a message without a trailing LF, isn't returned.
+    pub fn frames(&self) -> Vec<&[u8]> {
+        match self.framing {
+            Framing::OctetCounting => octet_counted_frames(&self.buf),
+            Framing::Lf => {
+                let mut frames: Vec<&[u8]> = self.buf.split(|b| *b == b'\n').collect();
+                // the part after the last LF is either empty or incomplete
+                frames.pop();
+                frames
+            }
+        }
+    }
+
+    /// The complete messages written so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a frame isn't a syslog 5424 message, the sink is meant for tests.
+    pub fn messages(&self) -> Vec<ParsedMessage> {
+        self.frames()
+            .into_iter()
+            .map(|frame| {
+                ParsedMessage::parse(frame).unwrap_or_else(|| {
+                    panic!(
+                        "The frame is not a syslog 5424 message: {}",
+                        String::from_utf8_lossy(frame)
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Remove all the captured messages
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+impl io::Write for CaptureSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Split the octet counted frames, stopping at the first incomplete or malformed frame
+fn octet_counted_frames(mut buf: &[u8]) -> Vec<&[u8]> {
+    let mut frames = vec![];
+
+    while let Some(space) = buf.iter().position(|b| *b == b' ') {
+        let Some(len) = std::str::from_utf8(&buf[..space])
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+        else {
+            break;
+        };
+
+        let Some(frame) = buf.get(space + 1..space + 1 + len) else {
+            break;
+        };
+
+        frames.push(frame);
+        buf = &buf[space + 1 + len..];
+    }
+
+    frames
+}
+
+/// A syslog 5424 message split into its fields.
+///
+/// A field with the NILVALUE is `-`, the MSG is converted lossy to UTF-8 and stripped of the BOM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedMessage {
+    pub priority: Priority,
+    pub timestamp: String,
+    pub hostname: String,
+    pub app_name: String,
+    pub proc_id: String,
+    pub msg_id: String,
+    /// Each SD-ELEMENT including the brackets, empty if the STRUCTURED-DATA is the NILVALUE
+    pub structured_data: Vec<String>,
+    pub msg: String,
+}
+
+impl ParsedMessage {
+    /// Parse a single unframed syslog 5424 message, `None` is returned if the message is malformed.
+    ///
+    /// The message is parsed with [v5424::parse], so a message is valid here if it's valid there.
+    /// Only VERSION 1 is accepted.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        const UTF8_BOM: char = '\u{feff}';
+
+        let message = v5424::parse(bytes)
+            .ok()
+            .filter(|message| message.version == 1)?;
+        let or_nil = |field: Option<&str>| field.unwrap_or(NILVALUE).to_owned();
+
+        let msg = String::from_utf8_lossy(message.msg.unwrap_or_default());
+        let msg = msg.strip_prefix(UTF8_BOM).unwrap_or(&msg).to_owned();
+
+        Some(Self {
+            priority: message.priority,
+            timestamp: or_nil(message.timestamp),
+            hostname: or_nil(message.hostname),
+            app_name: or_nil(message.app_name),
+            proc_id: or_nil(message.proc_id),
+            msg_id: or_nil(message.msg_id),
+            structured_data: message.sd_elements().map(str::to_owned).collect(),
+            msg,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::{v5424::Config, Facility, Severity};
+
+    #[test]
+    fn should_capture_framed_messages() {
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        let mut sink = CaptureSink::new(Framing::OctetCounting);
+        fmt.write_framed(
+            &mut sink,
+            Severity::Notice,
+            "2003-10-11T22:14:15.003Z",
+            "first",
+            Some("ID47"),
+            data,
+        )
+        .unwrap();
+        fmt.write_framed(&mut sink, Severity::Err, "-", "", None, data)
+            .unwrap();
+        // an incomplete frame isn't captured
+        sink.write_all(b"42 <134>1").unwrap();
+
+        let messages = sink.messages();
+        assert_eq!(
+            messages,
+            [
+                ParsedMessage {
+                    priority: Priority::try_from(165).unwrap(),
+                    timestamp: "2003-10-11T22:14:15.003Z".to_owned(),
+                    hostname: "mymachine.example.com".to_owned(),
+                    app_name: "evntslog".to_owned(),
+                    proc_id: "-".to_owned(),
+                    msg_id: "ID47".to_owned(),
+                    structured_data: vec![r#"[exampleSDID@32473 iut="3"]"#.to_owned()],
+                    msg: "first".to_owned(),
+                },
+                ParsedMessage {
+                    priority: Priority::try_from(163).unwrap(),
+                    timestamp: "-".to_owned(),
+                    hostname: "mymachine.example.com".to_owned(),
+                    app_name: "evntslog".to_owned(),
+                    proc_id: "-".to_owned(),
+                    msg_id: "-".to_owned(),
+                    structured_data: vec![r#"[exampleSDID@32473 iut="3"]"#.to_owned()],
+                    msg: String::new(),
+                },
+            ]
+        );
+
+        let mut sink = CaptureSink::new(Framing::Lf);
+        for msg in ["first", "second"] {
+            let buf = fmt.format_to_vec(Severity::Info, "-", msg, None).unwrap();
+            v5424::frame_lf(&mut sink, &buf, v5424::EmbeddedLf::Reject).unwrap();
+        }
+
+        let messages = sink.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].msg, "first");
+        assert_eq!(messages[1].msg, "second");
+        assert!(messages[1].structured_data.is_empty());
+
+        sink.clear();
+        assert!(sink.messages().is_empty());
+    }
+
+    #[test]
+    fn should_not_parse_malformed_message() {
+        assert_eq!(ParsedMessage::parse(b""), None);
+        assert_eq!(ParsedMessage::parse(b"<192>1 - - - - - -"), None);
+        assert_eq!(ParsedMessage::parse(b"<134>2 - - - - - -"), None);
+        assert_eq!(ParsedMessage::parse(b"<134>1 - - - - -"), None);
+        assert_eq!(ParsedMessage::parse(b"<134>1 - - - - - [id"), None);
+        assert!(ParsedMessage::parse(b"<134>1 - - - - - -").is_some());
+    }
+
+    #[test]
+    fn should_parse_like_v5424_parse() {
+        let msg = ParsedMessage::parse(br#"<134>1 - - - - - [a@32473 x="b\]c"] msg"#).unwrap();
+        assert_eq!(msg.structured_data, [r#"[a@32473 x="b\]c"]"#]);
+        assert_eq!(msg.msg, "msg");
+
+        // an SD-ELEMENT must be followed by another SD-ELEMENT, a space or the end
+        let malformed = b"<134>1 - - - - - [a@32473]msg";
+        assert!(v5424::parse(malformed).is_err());
+        assert_eq!(ParsedMessage::parse(malformed), None);
+    }
+}
@@ -0,0 +1,294 @@
+//! A [Layer] that writes each `tracing` event as a syslog message, behind the `tracing` feature.
+//!
+//! ```rust
+//! use syslog_fmt::{tracing::SyslogLayer, v5424::Config};
+//! use tracing_subscriber::{layer::SubscriberExt, Registry};
+//!
+//! let layer = SyslogLayer::new(
+//!     Config::default().into_formatter(),
+//!     std::io::sink(),
+//!     "fields@32473",
+//!     "span@32473",
+//! );
+//! let subscriber = Registry::default().with(layer);
+//! ```
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::{self, Write as _},
+    io,
+    ops::Range,
+    sync::{Mutex, PoisonError},
+};
+
+use tracing_core::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{
+    v5424::{sanitize_param_name, Formatter, Timestamp},
+    Severity,
+};
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::default();
+}
+
+/// Write each event as a syslog message with structured data.
+///
+/// The MSG is the `message` field of the event, the other fields of the event are written
+/// as SD-PARAMs under the event SD-ID. The fields of the spans the event occurs in are written
+/// under the span SD-ID, from the root span to the current span. A field name is made a valid
+/// PARAM-NAME with [sanitize_param_name].
+///
+/// The level of an event is converted to a [Severity] with its `From<Level>` impl.
+///
+/// A message is formatted into a per-thread scratch buffer that's reused for the next event.
+/// It's written to the writer with a single `write_all`, so each message is a single datagram
+/// when writing to a datagram socket.
+///
+/// A message that fails to format or write is dropped.
+/// Set an error handler with [SyslogLayer::with_error_handler] to report these errors.
+pub struct SyslogLayer<W> {
+    formatter: Formatter,
+    writer: Mutex<W>,
+    event_sd_id: &'static str,
+    span_sd_id: &'static str,
+    error_handler: fn(&io::Error),
+}
+
+impl<W: io::Write> SyslogLayer<W> {
+    pub fn new(
+        formatter: Formatter,
+        writer: W,
+        event_sd_id: &'static str,
+        span_sd_id: &'static str,
+    ) -> Self {
+        Self {
+            formatter,
+            writer: Mutex::new(writer),
+            event_sd_id,
+            span_sd_id,
+            error_handler: drop_error,
+        }
+    }
+
+    /// Call the handler with the error of a message that fails to format or write,
+    /// instead of dropping the error.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{tracing::SyslogLayer, v5424::Config};
+    ///
+    /// let layer = SyslogLayer::new(
+    ///     Config::default().into_formatter(),
+    ///     std::io::sink(),
+    ///     "fields@32473",
+    ///     "span@32473",
+    /// )
+    /// .with_error_handler(|err| eprintln!("failed to log: {err}"));
+    /// ```
+    pub fn with_error_handler(mut self, error_handler: fn(&io::Error)) -> Self {
+        self.error_handler = error_handler;
+        self
+    }
+
+    fn write_event<S>(&self, event: &Event<'_>, ctx: &Context<'_, S>, scratch: &mut Scratch)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        scratch.clear();
+        event.record(&mut FieldVisitor {
+            msg: Some(&mut scratch.msg),
+            fields: &mut scratch.event,
+        });
+
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<Fields>() {
+                    scratch.spans.extend(fields);
+                }
+            }
+        }
+
+        let Scratch {
+            msg,
+            event: event_fields,
+            spans,
+            buf,
+        } = scratch;
+        let data = [
+            (self.event_sd_id, &*event_fields),
+            (self.span_sd_id, &*spans),
+        ]
+        .into_iter()
+        .filter(|(_, fields)| !fields.params.is_empty())
+        .map(|(id, fields)| (id, fields.params()));
+
+        let res = self.formatter.write_with_data(
+            buf,
+            Severity::from(*event.metadata().level()),
+            Timestamp::CreateSystemNow,
+            msg.as_str(),
+            None,
+            data,
+        );
+
+        let res = res.and_then(|()| {
+            let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+            writer.write_all(buf)
+        });
+
+        if let Err(err) = res {
+            (self.error_handler)(&err);
+        }
+    }
+}
+
+impl<W> fmt::Debug for SyslogLayer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyslogLayer")
+            .field("formatter", &self.formatter)
+            .field("event_sd_id", &self.event_sd_id)
+            .field("span_sd_id", &self.span_sd_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, W> Layer<S> for SyslogLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: io::Write + Send + 'static,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut fields = Fields::default();
+        attrs.record(&mut FieldVisitor {
+            msg: None,
+            fields: &mut fields,
+        });
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<Fields>() {
+            values.record(&mut FieldVisitor { msg: None, fields });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+            Ok(mut scratch) => self.write_event(event, &ctx, &mut scratch),
+            // the event is emitted while writing another event, e.g. by the writer
+            Err(_err) => self.write_event(event, &ctx, &mut Scratch::default()),
+        });
+    }
+}
+
+/// The default error handler, which drops the error
+fn drop_error(_err: &io::Error) {}
+
+/// `ERROR` maps to [Severity::Err], `WARN` to [Severity::Warning], `INFO` to [Severity::Info]
+/// and both `DEBUG` and `TRACE` to [Severity::Debug].
+impl From<Level> for Severity {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::ERROR => Severity::Err,
+            Level::WARN => Severity::Warning,
+            Level::INFO => Severity::Info,
+            // DEBUG and TRACE
+            _ => Severity::Debug,
+        }
+    }
+}
+
+/// The buffers used to format an event, these are kept to reuse their allocations
+#[derive(Default)]
+struct Scratch {
+    msg: String,
+    event: Fields,
+    spans: Fields,
+    buf: Vec<u8>,
+}
+
+impl Scratch {
+    fn clear(&mut self) {
+        self.msg.clear();
+        self.event.clear();
+        self.spans.clear();
+        self.buf.clear();
+    }
+}
+
+/// The field values formatted into a single string, with the name and range of each value
+#[derive(Default)]
+struct Fields {
+    values: String,
+    params: Vec<(Cow<'static, str>, Range<usize>)>,
+}
+
+impl Fields {
+    fn clear(&mut self) {
+        self.values.clear();
+        self.params.clear();
+    }
+
+    fn push_with(&mut self, name: Cow<'static, str>, write: impl FnOnce(&mut String)) {
+        let start = self.values.len();
+        write(&mut self.values);
+        self.params.push((name, start..self.values.len()));
+    }
+
+    fn extend(&mut self, other: &Fields) {
+        for (name, range) in &other.params {
+            self.push_with(name.clone(), |values| {
+                values.push_str(&other.values[range.clone()]);
+            });
+        }
+    }
+
+    fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, range)| (&**name, &self.values[range.clone()]))
+    }
+}
+
+/// Record the fields, the `message` field is written to the MSG if given
+struct FieldVisitor<'a> {
+    msg: Option<&'a mut String>,
+    fields: &'a mut Fields,
+}
+
+impl FieldVisitor<'_> {
+    fn record(&mut self, field: &Field, write: impl FnOnce(&mut String)) {
+        match &mut self.msg {
+            Some(msg) if field.name() == "message" => write(msg),
+            _ => self
+                .fields
+                .push_with(sanitize_param_name(field.name()), write),
+        }
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, |s| s.push_str(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        // writing to a `String` never fails
+        self.record(field, |s| {
+            let _ = write!(s, "{value:?}");
+        });
+    }
+}
@@ -0,0 +1,711 @@
+//! Helpers for getting formatted messages to a syslog daemon.
+//!
+//! The crate does not provide a transport itself.
+//! These helpers are meant to be combined with the socket or writer of your choosing.
+use std::{
+    io, mem,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::v5424::frame_octet_counting;
+
+/// The error code a socket returns when no buffer space is available (`ENOBUFS`).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const ENOBUFS: Option<i32> = Some(105);
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+const ENOBUFS: Option<i32> = Some(55);
+#[cfg(windows)]
+const ENOBUFS: Option<i32> = Some(10055);
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+    windows
+)))]
+const ENOBUFS: Option<i32> = None;
+
+/// The policy for retrying a send that failed transiently.
+///
+/// Sending a datagram over a UDP or Unix socket can fail when the system is under pressure,
+/// for example with `ENOBUFS` when the socket buffer is full.
+/// These failures are transient and the send will likely succeed after a short wait.
+///
+/// The policy is standalone, the sinks of this module don't retry. Apply it to the send of the
+/// transport, e.g. on the thread that receives the messages of a [ChannelSink].
+///
+/// ```rust,no_run
+/// use std::{net::UdpSocket, time::Duration};
+///
+/// use syslog_fmt::transport::RetryPolicy;
+///
+/// let policy = RetryPolicy {
+///     max_retries: 5,
+///     backoff: Duration::from_millis(1),
+/// };
+///
+/// let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let buf = b"<134>1 - - - - - -";
+/// policy.send(|| socket.send_to(buf, "127.0.0.1:514")).unwrap();
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    /// The time to wait before each retry.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub const NONE: Self = Self {
+        max_retries: 0,
+        backoff: Duration::ZERO,
+    };
+
+    /// Call the given send fn until it succeeds, fails with a non transient error
+    /// or the retry budget is exhausted.
+    ///
+    /// The error of the last attempt is returned if all attempts fail.
+    pub fn send<F, T>(&self, send: F) -> io::Result<T>
+    where
+        F: FnMut() -> io::Result<T>,
+    {
+        self.send_with_sleep(send, thread::sleep)
+    }
+
+    /// Like [RetryPolicy::send], but the backoff before each retry is waited for by the given sleep fn
+    /// instead of `thread::sleep`, e.g. to record the backoff in a test.
+    ///
+    /// ```rust
+    /// use std::{io, time::Duration};
+    ///
+    /// use syslog_fmt::transport::RetryPolicy;
+    ///
+    /// let mut backoffs = vec![];
+    /// let res = RetryPolicy::default().send_with_sleep(
+    ///     || Err::<(), _>(io::Error::from(io::ErrorKind::WouldBlock)),
+    ///     |backoff| backoffs.push(backoff),
+    /// );
+    ///
+    /// assert!(res.is_err());
+    /// assert_eq!(backoffs, [Duration::from_millis(10); 3]);
+    /// ```
+    pub fn send_with_sleep<F, S, T>(&self, mut send: F, mut sleep: S) -> io::Result<T>
+    where
+        F: FnMut() -> io::Result<T>,
+        S: FnMut(Duration),
+    {
+        let mut retries = 0;
+
+        loop {
+            match send() {
+                Err(e) if retries < self.max_retries && is_transient(&e) => {
+                    retries += 1;
+                    sleep(self.backoff);
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// A writer that sends each message over a channel, for example to a transport thread.
+///
+/// The bytes written are accumulated until `flush` is called, which sends them as a single message.
+/// This decouples formatting a message from the, possibly slow, I/O of sending it.
+/// Any bytes that have not been flushed are sent when the sink is dropped.
+///
+/// ```rust
+/// use std::{io::Write, sync::mpsc, thread};
+///
+/// use syslog_fmt::{transport::ChannelSink, v5424::Formatter, Severity};
+///
+/// let (tx, rx) = mpsc::channel::<Vec<u8>>();
+///
+/// let transport = thread::spawn(move || {
+///     for msg in rx {
+///         // send the message to the syslog daemon
+///     }
+/// });
+///
+/// let mut sink = ChannelSink::new(tx);
+/// Formatter::default()
+///     .write_without_data(&mut sink, Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None)
+///     .unwrap();
+/// sink.flush().unwrap();
+///
+/// drop(sink);
+/// transport.join().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChannelSink {
+    tx: mpsc::Sender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelSink {
+    pub fn new(tx: mpsc::Sender<Vec<u8>>) -> Self {
+        Self { tx, buf: vec![] }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let msg = mem::take(&mut self.buf);
+
+        self.tx.send(msg).map_err(|_err| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The receiving end of the channel is disconnected",
+            )
+        })
+    }
+}
+
+impl io::Write for ChannelSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Send the accumulated bytes as a single message
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+impl Drop for ChannelSink {
+    fn drop(&mut self) {
+        // there is no way to report the error
+        let _ = self.send();
+    }
+}
+
+/// A writer that formats a message once and writes it to two writers with a different framing.
+///
+/// The bytes written are accumulated until `flush` is called. The message is then written as is to
+/// the `raw` writer, e.g. a UDP socket, and with octet counting to the `octet_counted` writer, e.g. a TCP stream.
+/// Octet counting prefixes the message with its length in bytes and a space, see
+/// [RFC 6587](https://datatracker.ietf.org/doc/html/rfc6587#section-3.4.1).
+/// Any bytes that have not been flushed are written when the sink is dropped.
+///
+/// ```rust
+/// use std::io::Write;
+///
+/// use syslog_fmt::{transport::DualSink, v5424::Formatter, Severity};
+///
+/// let mut udp = Vec::<u8>::new();
+/// let mut tcp = Vec::<u8>::new();
+///
+/// let mut sink = DualSink::new(&mut udp, &mut tcp);
+/// Formatter::default()
+///     .write_without_data(&mut sink, Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None)
+///     .unwrap();
+/// sink.flush().unwrap();
+/// drop(sink);
+///
+/// assert_eq!(tcp, [format!("{} ", udp.len()).as_bytes(), &udp].concat());
+/// ```
+#[derive(Debug)]
+pub struct DualSink<R: io::Write, O: io::Write> {
+    raw: R,
+    octet_counted: O,
+    buf: Vec<u8>,
+}
+
+impl<R: io::Write, O: io::Write> DualSink<R, O> {
+    pub fn new(raw: R, octet_counted: O) -> Self {
+        Self {
+            raw,
+            octet_counted,
+            buf: vec![],
+        }
+    }
+
+    /// The raw and octet counted writers
+    pub fn get_ref(&self) -> (&R, &O) {
+        (&self.raw, &self.octet_counted)
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let msg = mem::take(&mut self.buf);
+
+        self.raw.write_all(&msg)?;
+        frame_octet_counting(&mut self.octet_counted, &msg)?;
+
+        self.raw.flush()?;
+        self.octet_counted.flush()
+    }
+}
+
+impl<R: io::Write, O: io::Write> io::Write for DualSink<R, O> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Write the accumulated bytes as a single message to both writers
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+impl<R: io::Write, O: io::Write> Drop for DualSink<R, O> {
+    fn drop(&mut self) {
+        // there is no way to report the error
+        let _ = self.send();
+    }
+}
+
+/// A writer that writes each message on a separate thread and gives up waiting after a timeout.
+///
+/// A slow or blocking collector, e.g. a TCP stream with a full send buffer, then doesn't block
+/// the logging thread indefinitely. The bytes written are accumulated until `flush` is called,
+/// which writes them as a single message and returns an error of kind `TimedOut` if the write
+/// doesn't complete within the timeout.
+///
+/// The timed out message is still written once the writer unblocks, the next messages are queued behind it.
+/// Prefer the write timeout of the socket itself if it supports one, e.g. `TcpStream::set_write_timeout`.
+///
+/// Each sink spawns its own thread that owns the writer. Dropping the sink doesn't wait for the thread:
+/// any bytes that have not been flushed are queued without waiting for the result, the thread writes
+/// the queued messages and then stops, dropping the writer. A writer that never unblocks keeps its thread alive.
+///
+/// ```rust
+/// use std::{io::Write, time::Duration};
+///
+/// use syslog_fmt::{transport::TimeoutSink, v5424::Formatter, Severity};
+///
+/// let mut sink = TimeoutSink::new(std::io::sink(), Duration::from_millis(100));
+/// Formatter::default()
+///     .write_without_data(&mut sink, Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None)
+///     .unwrap();
+/// sink.flush().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TimeoutSink {
+    tx: mpsc::Sender<Vec<u8>>,
+    results: mpsc::Receiver<io::Result<()>>,
+    timeout: Duration,
+    /// The number of messages sent to the writer thread of which the result hasn't been received
+    pending: usize,
+    buf: Vec<u8>,
+}
+
+impl TimeoutSink {
+    pub fn new<W>(mut writer: W, timeout: Duration) -> Self
+    where
+        W: io::Write + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        let (results_tx, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for msg in rx {
+                let res = writer.write_all(&msg).and_then(|()| writer.flush());
+
+                if results_tx.send(res).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            tx,
+            results,
+            timeout,
+            pending: 0,
+            buf: vec![],
+        }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let msg = mem::take(&mut self.buf);
+
+        self.tx.send(msg).map_err(|_err| writer_thread_stopped())?;
+        self.pending += 1;
+
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            match self.results.recv_timeout(timeout) {
+                // the result of an earlier message that timed out has already been reported
+                Ok(res) => {
+                    self.pending -= 1;
+
+                    if self.pending == 0 {
+                        return res;
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "The message wasn't written within the timeout of {:?}",
+                            self.timeout
+                        ),
+                    ))
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(writer_thread_stopped()),
+            }
+        }
+    }
+}
+
+impl io::Write for TimeoutSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Write the accumulated bytes as a single message, waiting at most the timeout
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+impl Drop for TimeoutSink {
+    fn drop(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+
+        // queue the message without waiting for the result, there is no way to report the error.
+        // Sending never blocks as the channel is unbounded
+        let _ = self.tx.send(mem::take(&mut self.buf));
+    }
+}
+
+fn writer_thread_stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "The writer thread has stopped")
+}
+
+/// A transient error is likely to be resolved by trying again
+fn is_transient(e: &io::Error) -> bool {
+    use io::ErrorKind;
+
+    matches!(
+        e.kind(),
+        ErrorKind::WouldBlock | ErrorKind::Interrupted | ErrorKind::TimedOut
+    ) || (ENOBUFS.is_some() && e.raw_os_error() == ENOBUFS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+
+    /// A sink that fails the given number of times before accepting a message
+    struct FlakySink {
+        failures: u32,
+        attempts: u32,
+        sent: Vec<u8>,
+    }
+
+    impl FlakySink {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures,
+                attempts: 0,
+                sent: vec![],
+            }
+        }
+
+        fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.attempts += 1;
+
+            if self.attempts <= self.failures {
+                return Err(io::Error::from(ErrorKind::WouldBlock));
+            }
+
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn should_send_complete_messages_over_channel() {
+        use std::io::Write;
+
+        use crate::{v5424::Formatter, Severity};
+
+        let formatter = Formatter::default();
+        let (tx, rx) = mpsc::channel();
+        let mut sink = ChannelSink::new(tx);
+
+        let receiver = thread::spawn(move || rx.into_iter().collect::<Vec<Vec<u8>>>());
+
+        formatter
+            .write_without_data(&mut sink, Severity::Info, "-", "first", None)
+            .unwrap();
+        sink.flush().unwrap();
+        // flushing without writing doesn't send an empty message
+        sink.flush().unwrap();
+        formatter
+            .write_with_data(
+                &mut sink,
+                Severity::Info,
+                "-",
+                "second",
+                None,
+                [("exampleSDID@32473", [("iut", "3")])],
+            )
+            .unwrap();
+        drop(sink);
+
+        let msgs = receiver.join().unwrap();
+
+        let mut expected = vec![];
+        formatter
+            .write_without_data(&mut expected, Severity::Info, "-", "first", None)
+            .unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0], expected);
+        assert!(msgs[1].ends_with("[exampleSDID@32473 iut=\"3\"] \u{feff}second".as_bytes()));
+    }
+
+    #[test]
+    fn should_write_raw_and_octet_counted_frames() {
+        use std::io::Write;
+
+        use crate::{v5424::Formatter, Severity};
+
+        let formatter = Formatter::default();
+        let mut udp = vec![];
+        let mut tcp = vec![];
+        let mut sink = DualSink::new(&mut udp, &mut tcp);
+
+        formatter
+            .write_without_data(&mut sink, Severity::Info, "-", "first", None)
+            .unwrap();
+        sink.flush().unwrap();
+        formatter
+            .write_without_data(&mut sink, Severity::Info, "-", "second ünicode", None)
+            .unwrap();
+        drop(sink);
+
+        let mut first = vec![];
+        formatter
+            .write_without_data(&mut first, Severity::Info, "-", "first", None)
+            .unwrap();
+        let mut second = vec![];
+        formatter
+            .write_without_data(&mut second, Severity::Info, "-", "second ünicode", None)
+            .unwrap();
+
+        assert_eq!(udp, [&first[..], &second[..]].concat());
+        assert_eq!(
+            tcp,
+            [
+                format!("{} ", first.len()).as_bytes(),
+                &first,
+                format!("{} ", second.len()).as_bytes(),
+                &second,
+            ]
+            .concat()
+        );
+    }
+
+    /// A writer that blocks each write until it's released, or doesn't block without a release channel
+    struct SlowWriter {
+        release: Option<mpsc::Receiver<()>>,
+        tx: mpsc::Sender<Vec<u8>>,
+    }
+
+    impl io::Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(release) = &self.release {
+                // a dropped release channel releases the writer too
+                let _ = release.recv();
+            }
+            // the test may have finished already
+            let _ = self.tx.send(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_time_out_on_slow_writer() {
+        use std::io::Write;
+
+        let (tx, rx) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel();
+        let writer = SlowWriter {
+            release: Some(release_rx),
+            tx,
+        };
+        let mut sink = TimeoutSink::new(writer, Duration::from_millis(10));
+
+        sink.write_all(b"first").unwrap();
+        let err = sink.flush().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        // the timed out message is still written once the writer unblocks
+        release.send(()).unwrap();
+        assert_eq!(rx.recv().unwrap(), b"first");
+    }
+
+    #[test]
+    fn should_write_within_timeout() {
+        use std::io::Write;
+
+        let (tx, rx) = mpsc::channel();
+        let writer = SlowWriter { release: None, tx };
+        let mut sink = TimeoutSink::new(writer, Duration::from_secs(10));
+
+        sink.write_all(b"first").unwrap();
+        sink.flush().unwrap();
+        sink.write_all(b"second").unwrap();
+        drop(sink);
+
+        assert_eq!(
+            rx.iter().collect::<Vec<_>>(),
+            [b"first".to_vec(), b"second".to_vec()]
+        );
+    }
+
+    #[test]
+    fn should_not_wait_for_blocked_writer_on_drop() {
+        use std::io::Write;
+
+        let (tx, rx) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel();
+        let writer = SlowWriter {
+            release: Some(release_rx),
+            tx,
+        };
+        let mut sink = TimeoutSink::new(writer, Duration::from_secs(60));
+
+        sink.write_all(b"unflushed").unwrap();
+        // returns while the writer is blocked, instead of waiting for the timeout
+        drop(sink);
+
+        release.send(()).unwrap();
+        assert_eq!(rx.recv().unwrap(), b"unflushed");
+        // the thread stops and drops the writer once the queued messages are written
+        assert_eq!(rx.recv(), Err(mpsc::RecvError));
+    }
+
+    #[test]
+    fn should_report_disconnected_channel() {
+        use std::io::Write;
+
+        let (tx, rx) = mpsc::channel();
+        let mut sink = ChannelSink::new(tx);
+        drop(rx);
+
+        sink.write_all(b"msg").unwrap();
+        let err = sink.flush().unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+    }
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_retries: 3,
+        backoff: Duration::from_secs(60),
+    };
+
+    #[test]
+    fn should_send_message_within_retry_budget() {
+        let msg = b"<165>1 - - - - - -";
+        let mut sink = FlakySink::new(3);
+        let mut backoffs = vec![];
+
+        let bytes_sent = POLICY
+            .send_with_sleep(|| sink.send(msg), |backoff| backoffs.push(backoff))
+            .unwrap();
+
+        assert_eq!(bytes_sent, msg.len());
+        assert_eq!(sink.attempts, 4);
+        assert_eq!(sink.sent, msg);
+        assert_eq!(backoffs, [POLICY.backoff; 3]);
+    }
+
+    #[test]
+    fn should_give_up_when_retry_budget_is_exhausted() {
+        let mut sink = FlakySink::new(4);
+        let mut backoffs = vec![];
+
+        let err = POLICY
+            .send_with_sleep(|| sink.send(b"msg"), |backoff| backoffs.push(backoff))
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+        assert_eq!(sink.attempts, 4);
+        assert!(sink.sent.is_empty());
+        assert_eq!(backoffs, [POLICY.backoff; 3]);
+    }
+
+    #[test]
+    fn should_wait_for_backoff_with_thread_sleep() {
+        let policy = RetryPolicy {
+            max_retries: 1,
+            backoff: Duration::from_millis(1),
+        };
+        let mut sink = FlakySink::new(1);
+
+        let start = Instant::now();
+        policy.send(|| sink.send(b"msg")).unwrap();
+
+        assert!(start.elapsed() >= policy.backoff);
+        assert_eq!(sink.attempts, 2);
+    }
+
+    #[test]
+    fn should_not_retry_permanent_errors() {
+        let mut attempts = 0;
+
+        let err = POLICY
+            .send_with_sleep(
+                || {
+                    attempts += 1;
+                    Err::<(), _>(io::Error::from(ErrorKind::ConnectionRefused))
+                },
+                |_backoff| panic!("a permanent error isn't retried"),
+            )
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        assert_eq!(attempts, 1);
+    }
+}
@@ -1,9 +1,28 @@
 //! A Formatter and associated types that converts a message and optional structured data
 //! into an [RFC 5424](https://datatracker.ietf.org/doc/html/rfc5424) compliant message.
-use core::fmt;
-use std::io;
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt,
+    ops::Range,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+#[cfg(feature = "std")]
+use std::{
+    io,
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crate::{Facility, Priority, Severity};
+#[cfg(not(feature = "std"))]
+use crate::io;
+use crate::{Facility, IntToEnumError, Priority, Severity};
 
 const SPACE_BYTE: u8 = 0x20;
 
@@ -14,1043 +33,10159 @@ pub struct Config<'a> {
     pub hostname: Option<&'a Hostname>,
     pub app_name: Option<&'a AppName>,
     pub proc_id: Option<&'a ProcId>,
+    pub data: DataConfig,
+    /// Append a NUL (`\0`) terminator after each message, for consumers that read a message as a C string.
+    ///
+    /// This is not part of RFC 5424 and is disabled by default. It is unrelated to any
+    /// framing of the transport, e.g. a LF or octet counting, which is applied outside of the formatter.
+    /// The fine-grained writers, like [Formatter::write_header], never write the terminator.
+    pub trailing_nul: bool,
+    /// Write the facility and severity by name in an SD-ELEMENT with the given SD-ID,
+    /// e.g. `[pri@32473 facility="Auth" severity="Crit"]`, in front of the other structured data.
+    ///
+    /// This helps collectors that don't decode the PRI value to filter messages. Disabled by default.
+    pub pri_sd_id: Option<&'a SdId>,
+    /// Use a session counter as the PROCID instead of `proc_id`, starting at 1.
+    ///
+    /// A change in the PROCID signals a discontinuity in the syslog reporting to a collector.
+    /// Start a new session with [Formatter::new_session], e.g. after reconnecting to the collector.
+    pub session_proc_id: bool,
+    /// Use the ID of the current process as the PROCID instead of `proc_id`.
+    ///
+    /// The ID is formatted directly into the preformatted header fields. Disabled by default,
+    /// [Config::session_proc_id] takes precedence if both are enabled.
+    #[cfg(feature = "std")]
+    pub process_proc_id: bool,
+    /// How a MSG that consists of only whitespace is written
+    pub whitespace_msg: WhitespaceMsg,
+    /// Leave out the VERSION, e.g. `<34>2003-10-11T22:14:15.003Z ...` instead of `<34>1 2003-10-11T22:14:15.003Z ...`.
+    ///
+    /// This is not part of RFC 5424 and is disabled by default. It's meant for bridging to
+    /// RFC 3164 consumers that don't expect a VERSION after the PRI.
+    pub omit_version: bool,
+    /// Write the version of the schema of the logs in an SD-ELEMENT, e.g. `[schema@32473 version="2"]`,
+    /// in front of the other structured data.
+    ///
+    /// This lets downstream consumers interpret the fields of a message when their meaning evolves. Disabled by default.
+    pub schema_version: Option<SchemaVersion<'a>>,
+    /// Track the highest severity of the messages that are written, see [Formatter::max_severity]
+    /// and [Formatter::write_max_severity_summary]. Disabled by default.
+    ///
+    /// Like the stats, only the methods that write a whole message are tracked.
+    pub track_max_severity: bool,
+    /// Whether a UTF-8 MSG is prefixed with the BOM
+    pub bom: Bom,
+    /// How the trailing dot of a fully qualified hostname, e.g. `mymachine.example.com.`, is written
+    pub hostname_trailing_dot: TrailingDot,
+    /// How a `proc_id` longer than the 128 octets allowed by the spec is handled
+    pub proc_id_overflow: ProcIdOverflow,
+    /// Count the messages per severity, their bytes and the truncations, see [Formatter::stats].
+    ///
+    /// This lets operators monitor the volume of their own logs. Disabled by default.
+    pub collect_stats: bool,
+    /// The maximum length in bytes of a message, including a NUL terminator.
+    ///
+    /// The MSG is truncated at a UTF-8 char boundary so the message fits, RFC 5424 recommends
+    /// that receivers accept messages of up to 2048 bytes. The header and structured data are never
+    /// truncated, if they don't leave room for the MSG it's left out entirely. Disabled by default.
+    pub max_len: Option<usize>,
+    /// How a timestamp before the Unix epoch, e.g. of a system whose clock isn't set, is handled
+    pub pre_epoch: PreEpoch,
 }
 
-impl<'a> Config<'a> {
-    pub fn into_formatter(self) -> Formatter {
-        self.into()
-    }
+/// How a `proc_id` longer than the 128 octets allowed by the spec is handled,
+/// e.g. when a long container ID is used as the PROCID.
+///
+/// This applies to the configured `proc_id` and to a PROCID given per message, see [Formatter::write_with_proc_id].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ProcIdOverflow {
+    /// Report the `proc_id` as [ConfigError::TooLong] when validating the config,
+    /// a PROCID given per message is rejected with an error
+    #[default]
+    Reject,
+    /// Truncate the `proc_id` to 128 octets, it isn't reported when validating the config
+    Truncate,
 }
 
-impl<'a> From<Config<'a>> for Formatter {
-    fn from(config: Config<'a>) -> Self {
-        Formatter::from_config(config)
+impl ProcIdOverflow {
+    fn apply(self, proc_id: &str) -> &str {
+        match self {
+            ProcIdOverflow::Reject => proc_id,
+            ProcIdOverflow::Truncate => truncate_str(proc_id, PROC_ID_MAX_LEN),
+        }
     }
 }
 
-/// Formats a message and optional structured data into a into an [RFC 5424](https://datatracker.ietf.org/doc/html/rfc5424) compliant message.
-#[derive(Clone, Debug)]
-pub struct Formatter {
-    facility: Facility,
+/// How a timestamp before the Unix epoch, `1970-01-01T00:00:00Z`, is handled.
+///
+/// This applies to the timestamps that are based on the epoch, [Timestamp::SystemTime], [Timestamp::Epoch],
+/// [Timestamp::CreateSystemNow] and [Timestamp::Deferred]. The other timestamps are written as is.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PreEpoch {
+    /// Write the timestamp, e.g. `1969-12-31T23:59:59.000000Z`
+    #[default]
+    Write,
+    /// Reject the message with an `InvalidInput` error, for a collector that can't handle such a timestamp
+    Reject,
+}
 
-    /// The hostname, app_name and pid substring can be preformatted
-    /// given that they don't change per syslog session
-    host_app_proc_id: Box<str>,
+impl PreEpoch {
+    /// Check the timestamp, a timestamp that's created when it's written is created here
+    /// so the checked time is the written time
+    fn apply(self, timestamp: Timestamp<'_>) -> io::Result<Timestamp<'_>> {
+        if self == PreEpoch::Write {
+            return Ok(timestamp);
+        }
+
+        let timestamp = match timestamp {
+            #[cfg(feature = "std")]
+            Timestamp::CreateSystemNow => Timestamp::SystemTime(SystemTime::now()),
+            #[cfg(feature = "std")]
+            Timestamp::Deferred(clock) => Timestamp::SystemTime(clock.now()),
+            timestamp => timestamp,
+        };
+
+        let is_pre_epoch = match timestamp {
+            #[cfg(feature = "std")]
+            Timestamp::SystemTime(time) => time < UNIX_EPOCH,
+            Timestamp::Epoch { secs, .. } => secs < 0,
+            _ => false,
+        };
+
+        if is_pre_epoch {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The timestamp is before the Unix epoch",
+            ));
+        }
+
+        Ok(timestamp)
+    }
 }
 
-impl Default for Formatter {
-    fn default() -> Self {
-        Config::default().into_formatter()
+/// How the trailing dot of a fully qualified hostname, e.g. `mymachine.example.com.`, is written.
+///
+/// The trailing dot marks the root of the DNS, collectors vary in how they handle it.
+/// This applies to the configured hostname and to a hostname given per message.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TrailingDot {
+    /// Write the hostname as is
+    #[default]
+    Keep,
+    /// Strip a single trailing dot, e.g. `mymachine.example.com.` becomes `mymachine.example.com`
+    Strip,
+}
+
+impl TrailingDot {
+    fn apply(self, hostname: &str) -> &str {
+        match self {
+            TrailingDot::Keep => hostname,
+            TrailingDot::Strip => hostname.strip_suffix('.').unwrap_or(hostname),
+        }
     }
 }
 
-impl Formatter {
-    /// Create a new syslog 5424 formatter
+/// Whether a UTF-8 MSG is prefixed with the BOM, a MSG of [Msg::NonUnicodeBytes] never is.
+///
+/// The BOM is required by RFC 5424, but some collectors display it as garbage.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Bom {
+    /// Prefix the MSG with the BOM as the spec requires
+    #[default]
+    Write,
+    /// Write the MSG without the BOM, this is not part of RFC 5424.
     ///
-    /// Even though the hostname is optional, it's considered highly unlikely that you can't supply one.
-    /// See <https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.4>.
-    /// A warning will be logged if no hostname is provided.
-    pub fn from_config(config: Config<'_>) -> Self {
-        let hostname = config.hostname;
-        let app_name = config.app_name;
-        let proc_id = config.proc_id;
+    /// The collector can no longer tell from the message that the MSG is UTF-8.
+    Omit,
+}
 
-        let hostname = hostname.unwrap_or(NILVALUE);
-        let app_name = app_name.unwrap_or(NILVALUE);
-        let proc_id = proc_id.unwrap_or(NILVALUE);
+/// The version of the application-defined schema of the logs, see [Config::schema_version]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SchemaVersion<'a> {
+    /// The SD-ID of the element, including the private enterprise number, e.g. `schema@32473`
+    pub sd_id: &'a SdId,
+    /// The value of the `version` param
+    pub version: &'a str,
+}
+
+/// How a MSG that consists of only whitespace, e.g. `"  "`, is written.
+///
+/// A whitespace-only MSG isn't empty, so by default it's written with a BOM like any other MSG.
+/// Some collectors trim the MSG and are left with only the BOM.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WhitespaceMsg {
+    /// Write the BOM and the whitespace
+    #[default]
+    Keep,
+    /// Write the MSG as an empty MSG, without the BOM and the whitespace.
+    ///
+    /// This applies to a `&str`, a `String` and `fmt::Arguments` without arguments.
+    /// A [Msg::Display] or [Msg::NonUnicodeBytes] is always kept.
+    AsEmpty,
+}
 
-        let host_app_proc_id = format!("{hostname} {app_name} {proc_id}").into_boxed_str();
+impl<'a> Config<'a> {
+    pub fn into_formatter(self) -> Formatter {
+        self.into()
+    }
 
-        Self {
-            facility: config.facility,
-            host_app_proc_id,
+    /// Create a formatter after validating the header fields of the config,
+    /// the first issue found is returned as the error.
+    ///
+    /// The hostname is optional, so a missing hostname isn't an error.
+    /// Use [Config::validate] to report all the issues of a config at once.
+    ///
+    /// ```rust
+    /// use syslog_fmt::v5424::{Config, ConfigError, Field};
+    ///
+    /// let hostname = "a".repeat(256);
+    /// let config = Config {
+    ///     hostname: Some(&hostname),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(
+    ///     config.try_into_formatter().unwrap_err(),
+    ///     ConfigError::TooLong { field: Field::Hostname, len: 256, max_len: 255 }
+    /// );
+    /// ```
+    pub fn try_into_formatter(self) -> Result<Formatter, ConfigError> {
+        let report = self.validate();
+
+        match report
+            .issues
+            .into_iter()
+            .find(|issue| *issue != ConfigError::MissingHostname)
+        {
+            Some(issue) => Err(issue),
+            None => Ok(self.into_formatter()),
         }
     }
 
-    /// Format a syslog 5424 message with structured data.
+    /// Validate the header fields of the config and report all the issues found.
     ///
-    /// This method is a special case as the use of structured data is less likely than providing a simple string message.
+    /// All fields are checked, rather than stopping at the first issue,
+    /// so that an entire config can be diagnosed in one pass, e.g. at startup.
     ///
     /// ```rust
-    /// use std::io::Write;
+    /// use syslog_fmt::v5424::{Config, ConfigError, Field};
     ///
-    /// use syslog_fmt::{Severity, Facility, v5424::{Config, Formatter, Timestamp}};
+    /// let config = Config {
+    ///     app_name: Some("my app"),
+    ///     ..Default::default()
+    /// };
+    /// let report = config.validate();
     ///
-    /// let mut buf = Vec::<u8>::new();
-    /// let formatter = Config {
-    ///     facility: Facility::Local7,
-    ///     hostname: Some("localhost"),
-    ///     app_name: Some("app-name"),
-    ///     proc_id: Some("proc-id"),
-    /// }
-    /// .into_formatter();
-    /// formatter.write_with_data(
-    ///     &mut buf,
-    ///     Severity::Info,
-    ///     Timestamp::CreateChronoLocal,
-    ///     "this is a message",
-    ///     Some("msg-id"),
-    ///     vec![("elem-a", vec![("param-a", "value-a")])]
+    /// assert_eq!(
+    ///     report.issues,
+    ///     [
+    ///         ConfigError::MissingHostname,
+    ///         ConfigError::IllegalChar { field: Field::AppName, ch: ' ', index: 2 },
+    ///     ]
     /// );
+    /// println!("{report}");
     /// ```
-    pub fn write_with_data<'a, W, TS, M, I, P>(
-        &self,
-        w: &mut W,
-        severity: Severity,
-        timestamp: TS,
-        msg: M,
-        msg_id: Option<&MsgId>,
-        data: I,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-        TS: Into<Timestamp<'a>>,
-        M: Into<Msg<'a>>,
-        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
-        P: IntoIterator<Item = SdParam<'a>> + 'a,
-    {
-        self.write_header(w, severity, timestamp, msg_id)?;
-        write_data(w, data)?;
-        write_msg(w, msg)
+    pub fn validate(&self) -> ConfigReport {
+        let mut issues = vec![];
+
+        match self.hostname {
+            Some(hostname) => {
+                validate_header_field(Field::Hostname, hostname, HOSTNAME_MAX_LEN, &mut issues);
+            }
+            None => issues.push(ConfigError::MissingHostname),
+        }
+
+        if let Some(app_name) = self.app_name {
+            validate_header_field(Field::AppName, app_name, APP_NAME_MAX_LEN, &mut issues);
+        }
+
+        if let Some(proc_id) = self.proc_id {
+            let proc_id = self.proc_id_overflow.apply(proc_id);
+            validate_header_field(Field::ProcId, proc_id, PROC_ID_MAX_LEN, &mut issues);
+        }
+
+        ConfigReport { issues }
     }
 
-    /// Format a syslog 5424 message given a simple string message.
-    /// An optional MSG-ID can be provided by using a two string tuple for the msg param:
+    /// Validate the config like [Config::validate] and also report a facility
+    /// that isn't one of the local use facilities `Local0` to `Local7`.
+    ///
+    /// Many deployments mandate a local use facility for application logs,
+    /// this prevents the accidental use of a system facility.
     ///
     /// ```rust
-    /// use std::io::Write;
+    /// use syslog_fmt::{Facility, v5424::{Config, ConfigError}};
     ///
-    /// use syslog_fmt::{Severity, Facility, v5424::{Config, Formatter, Timestamp}};
+    /// let config = Config {
+    ///     facility: Facility::Auth,
+    ///     hostname: Some("mymachine.example.com"),
+    ///     ..Default::default()
+    /// };
     ///
-    /// let mut buf = Vec::<u8>::new();
-    /// let formatter = Config {
-    ///     facility: Facility::Local7,
-    ///     hostname: Some("localhost"),
-    ///     app_name: Some("app-name"),
-    ///     proc_id: Some("proc-id"),
-    /// }
-    /// .into_formatter();
-    /// formatter.write_without_data(
-    ///     &mut buf,
-    ///     Severity::Info,
-    ///     Timestamp::CreateChronoLocal,
-    ///     "this is a message",
-    ///     Some("msg-id")
+    /// assert_eq!(
+    ///     config.validate_local_only().issues,
+    ///     [ConfigError::NonLocalFacility { facility: Facility::Auth }]
     /// );
     /// ```
-    pub fn write_without_data<'a, W, TS, M>(
-        &self,
-        w: &mut W,
-        severity: Severity,
-        timestamp: TS,
-        msg: M,
-        msg_id: Option<&MsgId>,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-        TS: Into<Timestamp<'a>>,
-        M: Into<Msg<'a>>,
-    {
-        self.write_header(w, severity, timestamp, msg_id)?;
-        write_nil_value(w)?;
-        write_msg(w, msg)
+    pub fn validate_local_only(&self) -> ConfigReport {
+        let mut report = self.validate();
+
+        if !self.facility.is_local() {
+            report.issues.push(ConfigError::NonLocalFacility {
+                facility: self.facility,
+            });
+        }
+
+        report
     }
+}
 
-    /// Write a header
-    pub fn write_header<'a, W, TS>(
-        &self,
-        w: &mut W,
-        severity: Severity,
-        timestamp: TS,
-        msg_id: Option<&MsgId>,
-    ) -> io::Result<()>
-    where
-        W: io::Write,
-        TS: Into<Timestamp<'a>>,
-    {
-        let Self {
-            facility,
-            host_app_proc_id,
-        } = self;
+/// The maximum length of the HOSTNAME field
+const HOSTNAME_MAX_LEN: usize = 255;
+/// The maximum length of the APP-NAME field
+const APP_NAME_MAX_LEN: usize = 48;
+/// The maximum length of the PROCID field
+const PROC_ID_MAX_LEN: usize = 128;
+/// The maximum length of the MSGID field
+const MSG_ID_MAX_LEN: usize = 32;
+/// The length of the PRI, VERSION, TIMESTAMP, MSGID and NILVALUE STRUCTURED-DATA, including the spaces,
+/// of a typical message
+#[cfg(feature = "bytes")]
+const HEADER_LEN_HINT: usize =
+    "<191>1 2003-08-24T05:14:15.000003-07:00  ".len() + MSG_ID_MAX_LEN + " -".len();
 
-        let prio = encode_priority(severity, *facility);
-        let msg_id = msg_id.unwrap_or(NILVALUE);
+/// Truncate `value` to at most `max_len` bytes, without splitting a character
+fn truncate_str(value: &str, max_len: usize) -> &str {
+    if value.len() <= max_len {
+        return value;
+    }
 
-        write!(w, "<{prio}>{VERSION} ")?;
+    let end = (0..=max_len)
+        .rev()
+        .find(|i| value.is_char_boundary(*i))
+        .unwrap_or(0);
+    &value[..end]
+}
 
-        let timestamp = timestamp.into();
+/// Validate a header field, which must consist of 1 up to `max_len` PRINTUSASCII characters.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6)
+fn validate_header_field(field: Field, value: &str, max_len: usize, issues: &mut Vec<ConfigError>) {
+    if value.is_empty() {
+        issues.push(ConfigError::Empty { field });
+        return;
+    }
 
-        match timestamp {
-            #[cfg(feature = "chrono")]
-            Timestamp::Chrono(datetime) => {
-                write_chrono_datetime(w, datetime)?;
-            }
-            #[cfg(feature = "chrono")]
-            Timestamp::CreateChronoLocal => {
-                let datetime = chrono::Local::now();
-                write_chrono_datetime(w, &datetime)?;
-            }
-            Timestamp::PreformattedStr(s) => w.write_all(s.as_bytes())?,
-            Timestamp::PreformattedString(s) => w.write_all(s.as_bytes())?,
-            Timestamp::None => write_nil_value(w)?,
-        };
+    if let Some((index, ch)) = value.char_indices().find(|(_, ch)| !is_print_us_ascii(*ch)) {
+        issues.push(ConfigError::IllegalChar { field, ch, index });
+    }
 
-        write!(w, " {host_app_proc_id} {msg_id}")?;
-        Ok(())
+    if value.len() > max_len {
+        issues.push(ConfigError::TooLong {
+            field,
+            len: value.len(),
+            max_len,
+        });
     }
 }
 
-/// Write structured data with a space prefixed
+/// Validate a header field that's given for a single message, the first issue is returned as an `InvalidInput` error
+fn validate_override(field: Field, value: &str, max_len: usize) -> io::Result<()> {
+    let mut issues = vec![];
+    validate_header_field(field, value, max_len, &mut issues);
+
+    match issues.into_iter().next() {
+        Some(issue) => Err(io::Error::new(io::ErrorKind::InvalidInput, issue)),
+        None => Ok(()),
+    }
+}
+
+/// Validate the MSGID of a message before writing it, `None` is written as the NILVALUE and is always valid.
 ///
-/// STRUCTURED-DATA provides a mechanism to express information in a well
-/// defined, easily parseable and interpretable data format. There are
-/// multiple usage scenarios. For example, it may express meta-
-/// information about the syslog message or application-specific
-/// information such as traffic counters or IP addresses.
+/// The MSGID is given for each message rather than in the [Config], so it isn't checked by
+/// [Config::try_into_formatter]. The first issue found is returned.
 ///
-/// STRUCTURED-DATA can contain zero, one, or multiple structured data
-/// elements, SD-ELEMENT.
+/// ```rust
+/// use syslog_fmt::v5424::{self, ConfigError, Field};
 ///
-/// In case of zero structured data elements, the STRUCTURED-DATA field
-/// MUST contain the NILVALUE.
+/// assert!(v5424::validate_msg_id(Some("ID47")).is_ok());
+/// assert!(v5424::validate_msg_id(None).is_ok());
+/// assert_eq!(
+///     v5424::validate_msg_id(Some("a msg id")),
+///     Err(ConfigError::IllegalChar { field: Field::MsgId, ch: ' ', index: 1 })
+/// );
+/// ```
+pub fn validate_msg_id(msg_id: Option<&MsgId>) -> Result<(), ConfigError> {
+    let Some(msg_id) = msg_id else {
+        return Ok(());
+    };
+
+    let mut issues = vec![];
+    validate_header_field(Field::MsgId, msg_id, MSG_ID_MAX_LEN, &mut issues);
+
+    match issues.into_iter().next() {
+        Some(issue) => Err(issue),
+        None => Ok(()),
+    }
+}
+
+/// Make a MSGID out of an arbitrary string, e.g. the target of a log record or the name of a span.
 ///
-/// The character set used in STRUCTURED-DATA MUST be seven-bit ASCII in
-/// an eight-bit field as described in [RFC5234](https://datatracker.ietf.org/doc/html/rfc5234).
-/// These are the ASCII codes as defined in "USA Standard Code for Information Interchange"
-/// [ANSI.X3-4.1968](https://datatracker.ietf.org/doc/html/rfc5424#ref-ANSI.X3-4.1968).
-/// An exception is the PARAM-VALUE field, in which UTF-8 encoding MUST be used.
+/// The MSGID is truncated to 32 characters and each character that isn't printable US-ASCII,
+/// or is one of '=', ']' and '"', is replaced by a '_'. An empty MSGID becomes the NILVALUE,
+/// which is also how an empty MSGID is written. The string is borrowed if it's already valid.
 ///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3)
+/// ```rust
+/// use syslog_fmt::v5424;
 ///
-/// An SD-ELEMENT consists of a name and parameter name-value pairs. The
-/// name is referred to as SD-ID. The name-value pairs are referred to
-/// as SD-PARAM.
+/// assert_eq!(v5424::sanitize_msg_id("ID47"), "ID47");
+/// assert_eq!(v5424::sanitize_msg_id("user login"), "user_login");
+/// assert_eq!(v5424::sanitize_msg_id(""), "-");
+/// ```
+pub fn sanitize_msg_id(raw: &str) -> Cow<'_, str> {
+    sanitize_name(raw, MSG_ID_MAX_LEN, &['=', ']', '"'], NILVALUE)
+}
+
+/// Make a PARAM-NAME out of an arbitrary string, e.g. the name of a field of a `tracing` event.
 ///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.1)
-pub fn write_data<'a, W, I, P>(w: &mut W, data: I) -> io::Result<()>
-where
-    W: io::Write,
-    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
-    P: IntoIterator<Item = SdParam<'a>> + 'a,
-{
-    let mut elems = data.into_iter();
+/// The name is truncated to 32 characters and each character that isn't printable US-ASCII,
+/// or is one of '=', ' ', ']' and '"', is replaced by a '_'. An empty name becomes a single '_'.
+/// The string is borrowed if it's already valid.
+///
+/// ```rust
+/// use syslog_fmt::v5424;
+///
+/// assert_eq!(v5424::sanitize_param_name("iut"), "iut");
+/// assert_eq!(v5424::sanitize_param_name("user name"), "user_name");
+/// ```
+pub fn sanitize_param_name(raw: &str) -> Cow<'_, str> {
+    sanitize_name(raw, SD_NAME_MAX_LEN, &['=', ' ', ']', '"'], "_")
+}
 
-    let Some(elem) = elems.next() else {
-        write!(w, " {NILVALUE}")?;
-        return Ok(());
-    };
+/// Truncate a name to `max_len` characters and replace each character that isn't printable US-ASCII
+/// or is forbidden by a '_', an empty name becomes `empty`
+fn sanitize_name<'a>(
+    raw: &'a str,
+    max_len: usize,
+    forbidden: &[char],
+    empty: &'static str,
+) -> Cow<'a, str> {
+    let is_valid = |ch: char| is_print_us_ascii(ch) && !forbidden.contains(&ch);
+
+    if raw.is_empty() {
+        return Cow::Borrowed(empty);
+    }
 
-    write!(w, " ")?;
-    write_data_elem(w, elem)?;
+    // a valid name is ASCII, so truncating the bytes truncates the characters
+    let truncated = truncate_str(raw, max_len);
 
-    for elem in elems {
-        write_data_elem(w, elem)?;
+    if truncated.chars().all(is_valid) {
+        return Cow::Borrowed(truncated);
     }
 
-    Ok(())
+    // the non-ASCII characters are replaced, so each character is a single byte
+    raw.chars()
+        .take(max_len)
+        .map(|ch| if is_valid(ch) { ch } else { '_' })
+        .collect()
 }
 
-fn write_data_elem<'a, W, P>(w: &mut W, elem: (&'a SdId, P)) -> io::Result<()>
-where
-    W: io::Write,
-    P: IntoIterator<Item = SdParam<'a>> + 'a,
-{
-    let (id, params) = elem;
+/// All the issues found by [Config::validate]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigReport {
+    pub issues: Vec<ConfigError>,
+}
 
-    let mut params = params.into_iter();
+impl ConfigReport {
+    /// Returns true if no issues were found
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
 
-    let Some(param) = params.next() else {
-        write!(w, "[{id}]")?;
-        return Ok(());
-    };
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return f.write_str("The config is valid");
+        }
 
-    let (name, value) = param;
-    write!(w, "[{id} {name}=\"{value}\"")?;
+        writeln!(f, "The config has {} issue(s):", self.issues.len())?;
 
-    for param in params {
-        let (name, value) = param;
-        write!(w, " {name}=\"{value}\"")?;
+        for issue in &self.issues {
+            writeln!(f, "- {issue}")?;
+        }
+
+        Ok(())
     }
+}
 
-    write!(w, "]")
+/// A field of a syslog message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Pri,
+    Version,
+    Timestamp,
+    Hostname,
+    AppName,
+    ProcId,
+    MsgId,
+    StructuredData,
+    Msg,
 }
 
-/// Write a msg with a space prefixed
-pub fn write_msg<'a, W, M>(w: &mut W, msg: M) -> io::Result<()>
-where
-    W: io::Write,
-    M: Into<Msg<'a>>,
-{
-    let msg = msg.into();
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Field::Pri => "PRI",
+            Field::Version => "VERSION",
+            Field::Timestamp => "TIMESTAMP",
+            Field::Hostname => "HOSTNAME",
+            Field::AppName => "APP-NAME",
+            Field::ProcId => "PROCID",
+            Field::MsgId => "MSGID",
+            Field::StructuredData => "STRUCTURED-DATA",
+            Field::Msg => "MSG",
+        };
 
-    match msg {
-        Msg::Utf8Str(s) => write_str_msg(w, s),
-        Msg::Utf8String(s) => write_str_msg(w, &s),
-        Msg::NonUnicodeBytes(bytes) => {
-            let bytes_written = w.write(&[SPACE_BYTE])?;
-            debug_assert_eq!(bytes_written, 1);
-            let bytes_written = w.write(bytes)?;
-            debug_assert_eq!(bytes_written, bytes.len());
-            Ok(())
-        }
-        Msg::FmtArguments(args) => write!(w, " {args}"),
-        Msg::FmtArgumentsRef(args) => write!(w, " {args}"),
+        f.write_str(name)
     }
 }
 
-/// Write a NILVALUE ('-') prefixed with a space
-pub fn write_nil_value<W>(w: &mut W) -> io::Result<()>
-where
-    W: io::Write,
-{
-    write!(w, " {NILVALUE}")?;
-    Ok(())
+/// An issue found when validating a [Config]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// No hostname is given, see [Formatter::from_config]
+    MissingHostname,
+    /// The field is empty, use `None` to write the NILVALUE instead
+    Empty { field: Field },
+    /// The field is longer than allowed
+    TooLong {
+        field: Field,
+        len: usize,
+        max_len: usize,
+    },
+    /// The field contains a character that is not PRINTUSASCII.
+    /// The index is the byte offset of the character within the field.
+    IllegalChar {
+        field: Field,
+        ch: char,
+        index: usize,
+    },
+    /// The facility isn't a local use facility, see [Config::validate_local_only]
+    NonLocalFacility { facility: Facility },
 }
 
-#[cfg(feature = "chrono")]
-pub fn write_chrono_datetime<W: io::Write>(
-    w: &mut W,
-    datetime: &ChronoLocalTime,
-) -> io::Result<()> {
-    use chrono::Timelike;
-
-    const MILLI_IN_NANO: u32 = 1000;
-    const SEC_IN_HOUR: i32 = 3600;
-    const PLUS: &str = "+";
-    const MIN: &str = "-";
-
-    // reuse chrono `Debug` impls which already print ISO 8601 format.
-    let date = datetime.date_naive();
-    let time = datetime.time();
-    let h = time.hour();
-    let m = time.minute();
-    let s = time.second();
-    let ms = time.nanosecond() / MILLI_IN_NANO;
-    let offset_hour = datetime.offset().local_minus_utc() / SEC_IN_HOUR;
-    let sign = if offset_hour >= 0 { PLUS } else { MIN };
-
-    write!(
-        w,
-        "{date:?}T{h:02}:{m:02}:{s:02}.{ms:06}{sign}{offset_hour:02}:00"
-    )?;
-
-    Ok(())
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MissingHostname => f.write_str("The HOSTNAME is missing"),
+            ConfigError::Empty { field } => write!(f, "The {field} is empty"),
+            ConfigError::TooLong {
+                field,
+                len,
+                max_len,
+            } => write!(
+                f,
+                "The {field} is {len} characters long, the maximum is {max_len}"
+            ),
+            ConfigError::IllegalChar { field, ch, index } => {
+                write!(f, "The {field} contains the illegal char {ch:?} at {index}")
+            }
+            ConfigError::NonLocalFacility { facility } => {
+                write!(f, "The facility {facility} is not a local use facility")
+            }
+        }
+    }
 }
 
-/// Write a UTF8 BOM prefixed by a space
-pub fn write_utf8_bom<W: io::Write>(w: &mut W) -> io::Result<()> {
-    // the BOM is prefixed by an ASCII space
-    const BOM: [u8; 4] = [SPACE_BYTE, 0xEF, 0xBB, 0xBF];
-    w.write_all(&BOM)
-}
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
 
-/// Write a UTF8 string with a BOM prefixed as stated in the spec
-fn write_str_msg<W: io::Write>(w: &mut W, s: &str) -> io::Result<()> {
-    if !s.is_empty() {
-        write_utf8_bom(w)?;
-        w.write_all(s.as_bytes())?;
+impl<'a> From<Config<'a>> for Formatter {
+    fn from(config: Config<'a>) -> Self {
+        Formatter::from_config(config)
     }
-
-    Ok(())
 }
 
-const NILVALUE: &str = "-";
-
-/// The VERSION field denotes the version of the syslog protocol
-/// specification. The version number MUST be incremented for any new
-/// syslog protocol specification that changes any part of the HEADER
-/// format.
+/// Detect the hostname of the system, to use as [Config::hostname].
 ///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.2)
-const VERSION: &str = "1";
-
-#[cfg(feature = "chrono")]
-type ChronoLocalTime = chrono::DateTime<chrono::Local>;
+/// This is the name the OS reports, via `gethostname` on unix and `GetComputerNameEx` on Windows.
+/// It isn't necessarily a FQDN, which the spec prefers over a hostname without a domain.
+/// `None` is returned if the call fails or the name isn't valid UTF-8.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.4)
+///
+/// ```rust
+/// use syslog_fmt::v5424::{self, Config};
+///
+/// let hostname = v5424::detect_hostname();
+/// let formatter = Config {
+///     hostname: hostname.as_deref(),
+///     ..Default::default()
+/// }
+/// .into_formatter();
+/// ```
+#[cfg(feature = "hostname")]
+pub fn detect_hostname() -> Option<String> {
+    let hostname = hostname::get().ok()?.into_string().ok()?;
+    Some(hostname).filter(|hostname| !hostname.is_empty())
+}
 
-/// The TIMESTAMP field is a formalized timestamp derived from [RFC3339](https://datatracker.ietf.org/doc/html/rfc3339).
+/// A fluent builder of a [Formatter], an alternative to a [Config] struct literal.
 ///
-/// Whereas [RFC3339](https://datatracker.ietf.org/doc/html/rfc3339) makes allowances for multiple syntaxes,
-/// the Syslog 5424 spec imposes further restrictions. The TIMESTAMP value MUST
-/// follow these restrictions:
+/// The options that aren't set have their default value.
 ///
-/// *  The "T" and "Z" characters in this syntax MUST be upper case.
-/// *  Usage of the "T" character is REQUIRED.
-/// *  Leap seconds MUST NOT be used.
+/// ```rust
+/// use syslog_fmt::{v5424::{Config, ConfigBuilder}, Facility, Severity};
 ///
-/// A syslog application MUST use the NILVALUE as TIMESTAMP if the syslog
-/// application is incapable of obtaining system time.
+/// let from_builder = ConfigBuilder::new()
+///     .facility(Facility::Local4)
+///     .hostname("mymachine.example.com")
+///     .app_name("evntslog")
+///     .proc_id("8710")
+///     .build();
 ///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.3)
-pub enum Timestamp<'a> {
-    /// Provide a datatime to be formatted.
-    /// A custom formatter is used that does not perform any heap allcations
-    #[cfg(feature = "chrono")]
-    Chrono(&'a ChronoLocalTime),
-    /// The formatter will create a new `chrono::DateTime<Local>`
-    /// A custom formatter is used that does not perform any heap allcations
-    #[cfg(feature = "chrono")]
-    CreateChronoLocal,
-    /// Provide a preformatted timestamp.
-    /// This string is not validated. The onus is on the provider to verify it as an RFC3339 timestamp
-    /// See the [Timestamp] docs above for details on how to format a timestamp.
-    PreformattedStr(&'a str),
-    /// Provide a preformatted timestamp.
-    /// This string is not validated. The onus is on the provider to verify it as an RFC3339 timestamp
-    /// See the [Timestamp] docs above for details on how to format a timestamp.
-    PreformattedString(String),
-    /// No timestamp can be provided.
-    None,
+/// let from_config = Config {
+///     facility: Facility::Local4,
+///     hostname: Some("mymachine.example.com"),
+///     app_name: Some("evntslog"),
+///     proc_id: Some("8710"),
+///     ..Default::default()
+/// }
+/// .into_formatter();
+///
+/// let format = |formatter: syslog_fmt::v5424::Formatter| {
+///     formatter.format_to_vec(Severity::Notice, "2003-10-11T22:14:15.003Z", "a message", None).unwrap()
+/// };
+/// assert_eq!(format(from_builder), format(from_config));
+/// ```
+#[derive(Default)]
+pub struct ConfigBuilder<'a> {
+    config: Config<'a>,
 }
 
-impl<'a> From<&'a str> for Timestamp<'a> {
-    fn from(s: &'a str) -> Self {
-        Self::PreformattedStr(s)
+impl<'a> ConfigBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
     }
-}
 
-impl<'a> From<String> for Timestamp<'a> {
-    fn from(s: String) -> Self {
-        Self::PreformattedString(s)
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.config.facility = facility;
+        self
     }
-}
 
-#[cfg(feature = "chrono")]
-impl<'a> From<&'a ChronoLocalTime> for Timestamp<'a> {
-    fn from(datetime: &'a ChronoLocalTime) -> Self {
-        Self::Chrono(datetime)
+    pub fn hostname(mut self, hostname: &'a Hostname) -> Self {
+        self.config.hostname = Some(hostname);
+        self
+    }
+
+    pub fn app_name(mut self, app_name: &'a AppName) -> Self {
+        self.config.app_name = Some(app_name);
+        self
+    }
+
+    pub fn proc_id(mut self, proc_id: &'a ProcId) -> Self {
+        self.config.proc_id = Some(proc_id);
+        self
+    }
+
+    /// The config with the options set so far, e.g. to set one of the other options
+    pub fn into_config(self) -> Config<'a> {
+        self.config
+    }
+
+    pub fn build(self) -> Formatter {
+        self.config.into_formatter()
     }
 }
 
-/// The HOSTNAME field identifies the machine that originally sent the syslog message.
-///
-/// The HOSTNAME field SHOULD contain the hostname and the domain name of
-/// the originator in the format specified in STD 13 [RFC1034](https://datatracker.ietf.org/doc/html/rfc1034).
-/// This format is called a Fully Qualified Domain Name (FQDN).
-///
-/// In practice, not all syslog applications are able to provide an FQDN.
-/// As such, other values MAY also be present in HOSTNAME. Below are
-/// provisions for using other values in such situations. A syslog
-/// application SHOULD provide the most specific available value first.
-/// The order of preference for the contents of the HOSTNAME field is as
-/// follows:
-///
-/// 1. FQDN
-/// 2. Static IP address
-/// 3. hostname
-/// 4. Dynamic IP address
-/// 5. the NILVALUE
-///
-/// If an IPv4 address is used, it MUST be in the format of the dotted
-/// decimal notation as used in STD 13 [RFC1035](https://datatracker.ietf.org/doc/html/rfc1035).
-/// If an IPv6 address is used, a valid textual representation as described in
-/// [RFC4291, Section 2.2](https://datatracker.ietf.org/doc/html/rfc4291#section-2.2), MUST be used.
+/// Formats a message and optional structured data into a into an [RFC 5424](https://datatracker.ietf.org/doc/html/rfc5424) compliant message.
 ///
-/// Syslog applications SHOULD consistently use the same value in the
-/// HOSTNAME field for as long as possible.
+/// # Reproducible output
 ///
-/// The NILVALUE SHOULD only be used when the syslog application has no
-/// way to obtain its real hostname. This situation is considered highly
-/// unlikely.
+/// The timestamp is the only part of a message that depends on the clock.
+/// Supply a fixed preformatted timestamp, e.g. `Timestamp::PreformattedStr("2003-10-11T22:14:15.003Z")`,
+/// to produce byte for byte identical output on every run. This is useful for golden file or snapshot tests.
+/// The structured data is written in the order of the given iterator, so use an ordered collection
+/// like an array, `Vec` or `BTreeMap` instead of a `HashMap`.
 ///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.4)
-type Hostname = str;
+/// See the [golden test](https://github.com/tandemdrive/syslog-fmt/blob/main/tests/golden.rs) for an example.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    facility: Facility,
 
-/// The APP-NAME field SHOULD identify the device or application that
-/// originated the message. It is a string without further semantics.
-/// It is intended for filtering messages on a relay or collector.
-///
-/// The NILVALUE MAY be used when the syslog application has no idea of
-/// its APP-NAME or cannot provide that information. It may be that a
-/// device is unable to provide that information either because of a
-/// local policy decision, or because the information is not available,
-/// or not applicable, on the device.
-///
-/// This field MAY be operator-assigned.
-///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.5)
-type AppName = str;
+    /// The hostname, app_name and pid substring can be preformatted
+    /// given that they don't change per syslog session
+    host_app_proc_id: Box<str>,
 
-/// PROCID is a value that is included in the message, having no
-/// interoperable meaning, except that a change in the value indicates
-/// there has been a discontinuity in syslog reporting. The field does
-/// not have any specific syntax or semantics; the value is
-/// implementation-dependent and/or operator-assigned. The NILVALUE MAY
-/// be used when no value is provided.
-///
-/// The PROCID field is often used to provide the process name or process
-/// ID associated with a syslog system. The NILVALUE might be used when
-/// a process ID is not available. On an embedded system without any
-/// operating system process ID, PROCID might be a reboot ID.
-///
-/// PROCID can enable log analyzers to detect discontinuities in syslog
-/// reporting by detecting a change in the syslog process ID. However,
-/// PROCID is not a reliable identification of a restarted process since
-/// the restarted syslog process might be assigned the same process ID as
-/// the previous syslog process.
-///
-/// PROCID can also be used to identify which messages belong to a group
-/// of messages. For example, an SMTP mail transfer agent might put its
-/// SMTP transaction ID into PROCID, which would allow the collector or
-/// relay to group messages based on the SMTP transaction.
-///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.6)
-type ProcId = str;
+    /// The length of the hostname at the start of `host_app_proc_id`,
+    /// so the hostname can be replaced for a single message
+    hostname_len: usize,
 
-/// The MSGID SHOULD identify the type of message. For example, a
-/// firewall might use the MSGID "TCPIN" for incoming TCP traffic and the
-/// MSGID "TCPOUT" for outgoing TCP traffic. Messages with the same
-/// MSGID should reflect events of the same semantics. The MSGID itself
-/// is a string without further semantics. It is intended for filtering
-/// messages on a relay or collector.
-///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.7)
-type MsgId = str;
+    /// The length of the app_name after the hostname in `host_app_proc_id`
+    app_name_len: usize,
 
-/// The MSG part contains a free-form message that provides information
-/// about the event.
-///
-/// The character set used in MSG SHOULD be UNICODE, encoded using UTF-8
-/// as specified in [RFC3629](https://datatracker.ietf.org/doc/html/rfc3629).
-/// If the syslog application cannot encode
-/// the MSG in Unicode, it MAY use any other encoding.
-///
-/// The syslog application SHOULD avoid octet values below 32 (the
-/// traditional US-ASCII control character range except DEL). These
-/// values are legal, but a syslog application MAY modify these
-/// characters upon reception. For example, it might change them into an
-/// escape sequence (e.g., value 0 may be changed to "\0"). A syslog
-/// application SHOULD NOT modify any other octet values.
-///
-/// If a syslog application encodes MSG in UTF-8, the string MUST start
-/// with the Unicode byte order mask (BOM), which for UTF-8 is ABNF
-/// %xEF.BB.BF. The syslog application MUST encode in the "shortest
-/// form" and MAY use any valid UTF-8 sequence.
-///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.4)
-pub enum Msg<'a> {
-    /// A BOM will be prefixed to UTF8 encoded strings
-    Utf8Str(&'a str),
-    /// A BOM will be prefixed to UTF8 encoded strings
-    Utf8String(String),
-    /// Bytes not encoded as Unicode not be prefixed by a BOM
-    NonUnicodeBytes(&'a [u8]),
-    /// Accepting fmt::Arguments can make life easier when working with logging frameworks
-    FmtArguments(fmt::Arguments<'a>),
-    /// Accepting fmt::Arguments can make life easier when working with logging frameworks
-    FmtArgumentsRef(&'a fmt::Arguments<'a>),
+    data: DataConfig,
+
+    trailing_nul: bool,
+
+    pri_sd_id: Option<Box<SdId>>,
+
+    /// The session counter that is used as the PROCID, if enabled
+    session: Option<u64>,
+
+    whitespace_msg: WhitespaceMsg,
+
+    omit_version: bool,
+
+    /// The preformatted schema version SD-ELEMENT, if configured
+    schema_elem: Option<Box<str>>,
+
+    /// The highest severity written, if tracking is enabled
+    max_severity: Option<SeverityTracker>,
+
+    bom: Bom,
+
+    hostname_trailing_dot: TrailingDot,
+
+    /// How a PROCID given per message that's too long is handled
+    proc_id_overflow: ProcIdOverflow,
+
+    pre_epoch: PreEpoch,
+
+    /// The counters of the messages written, if collecting stats is enabled
+    stats: Option<StatsCounters>,
+
+    max_len: Option<usize>,
 }
 
-impl<'a> From<&'a str> for Msg<'a> {
-    fn from(s: &'a str) -> Self {
-        Self::Utf8Str(s)
-    }
+/// The highest severity written, a clone starts with the highest severity of the original
+#[derive(Debug)]
+struct SeverityTracker {
+    /// The numerical value of the severity, [SeverityTracker::NONE] if nothing is written
+    severity: AtomicU8,
 }
 
-impl<'a> From<String> for Msg<'a> {
-    fn from(s: String) -> Self {
-        Self::Utf8String(s)
+impl SeverityTracker {
+    const NONE: u8 = u8::MAX;
+
+    fn new() -> Self {
+        Self {
+            severity: AtomicU8::new(Self::NONE),
+        }
     }
-}
 
-impl<'a> From<&'a [u8]> for Msg<'a> {
-    fn from(bytes: &'a [u8]) -> Self {
-        Self::NonUnicodeBytes(bytes)
+    /// Record a written severity, the most severe has the lowest value
+    fn record(&self, severity: Severity) {
+        self.severity.fetch_min(severity as u8, Ordering::Relaxed);
     }
-}
 
-impl<'a> From<fmt::Arguments<'a>> for Msg<'a> {
-    fn from(args: fmt::Arguments<'a>) -> Self {
-        Self::FmtArguments(args)
+    fn get(&self) -> Option<Severity> {
+        Severity::try_from(self.severity.load(Ordering::Relaxed)).ok()
     }
-}
 
-impl<'a> From<&'a fmt::Arguments<'a>> for Msg<'a> {
-    fn from(args: &'a fmt::Arguments<'a>) -> Self {
-        Self::FmtArgumentsRef(args)
+    /// Take the highest severity and reset it in a single step, so a severity recorded meanwhile isn't lost
+    #[cfg(feature = "std")]
+    fn take(&self) -> Option<Severity> {
+        Severity::try_from(self.severity.swap(Self::NONE, Ordering::Relaxed)).ok()
     }
 }
 
-/// [SdId]s are case-sensitive and uniquely identify the type and purpose
-/// of the SD-ELEMENT. The same [SdId] MUST NOT exist more than once in a
-/// message.
-///
-/// There are two formats for [SdId] names:
-///
-/// - Names that do not contain an at-sign ("@", ABNF %d64) are reserved
-/// to be assigned by IETF Review as described in BCP26 [RFC5226](https://datatracker.ietf.org/doc/html/rfc5226).
-/// Currently, these are the names defined in Section 7. Names of
-/// this format are only valid if they are first registered with the
-/// IANA. Registered names MUST NOT contain an at-sign ('@', ABNF %d64),
-/// an equal-sign ('=', ABNF %d61), a closing brace (']', ABNF
-/// %d93), a quote-character ('"', ABNF %d34), whitespace, or control
-/// characters (ASCII code 127 and codes 32 or less).
-///
-/// - Anyone can define additional SD-IDs using names in the format
-/// `name@<private enterprise number>`, e.g., "ourSDID@32473". The
-/// format of the part preceding the at-sign is not specified;
-/// however, these names MUST be printable US-ASCII strings, and MUST
-/// NOT contain an at-sign ('@', ABNF %d64), an equal-sign ('=', ABNF
-/// %d61), a closing brace (']', ABNF %d93), a quote-character ('"',
-/// ABNF %d34), whitespace, or control characters. The part following
-/// the at-sign MUST be a private enterprise number as specified in
-/// Section 7.2.2. Please note that throughout this document the
-/// value of 32473 is used for all private enterprise numbers. This
-/// value has been reserved by IANA to be used as an example number in
-/// documentation. Implementors will need to use their own private
-/// enterprise number for the enterpriseId parameter, and when
-/// creating locally extensible SD-ID names.
-///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2)
-type SdId = str;
+impl Clone for SeverityTracker {
+    fn clone(&self) -> Self {
+        Self {
+            severity: AtomicU8::new(self.severity.load(Ordering::Relaxed)),
+        }
+    }
+}
 
-/// Each SD-PARAM consists of a name, referred to as PARAM-NAME, and a
-/// value, referred to as PARAM-VALUE.
-///
-/// PARAM-NAME is case-sensitive. IANA controls all PARAM-NAMEs, with
-/// the exception of those in SD-IDs whose names contain an at-sign. The
-/// PARAM-NAME scope is within a specific SD-ID. Thus, equally named
-/// PARAM-NAME values contained in two different SD-IDs are not the same.
-///
-/// To support international characters, the PARAM-VALUE field MUST be
-/// encoded using UTF-8. A syslog application MAY issue any valid UTF-8
-/// sequence. A syslog application MUST accept any valid UTF-8 sequence
-/// in the "shortest form". It MUST NOT fail if control characters are
-/// present in PARAM-VALUE. The syslog application MAY modify messages
-/// containing control characters (e.g., by changing an octet with value
-/// 0 (USASCII NUL) to the four characters "#000"). For the reasons
-/// outlined in UNICODE TR36 [UNICODE-TR36, section 3.1](https://datatracker.ietf.org/doc/html/rfc5424#ref-UNICODE-TR36),
-/// an originator MUST encode messages in the "shortest form" and a collector or relay
-/// MUST NOT interpret messages in the "non-shortest form".
-///
-/// Inside PARAM-VALUE, the characters '"' (ABNF %d34), '\' (ABNF %d92),
-/// and ']' (ABNF %d93) MUST be escaped. This is necessary to avoid
-/// parsing errors. Escaping ']' would not strictly be necessary but is
-/// REQUIRED by this specification to avoid syslog application
-/// implementation errors. Each of these three characters MUST be
-/// escaped as '\"', '\\', and '\]' respectively. The backslash is used
-/// for control character escaping for consistency with its use for
-/// escaping in other parts of the syslog message as well as in
-/// traditional syslog.
-///
-/// A backslash ('\') followed by none of the three described characters
-/// is considered an invalid escape sequence. In this case, the
-/// backslash MUST be treated as a regular backslash and the following
-/// character as a regular character. Thus, the invalid sequence MUST
-/// not be altered.
-///
-/// An SD-PARAM MAY be repeated multiple times inside an SD-ELEMENT.
-///
-/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.3)
-type SdParam<'a> = (ParamName<'a>, ParamValue<'a>);
-type ParamName<'a> = &'a str;
-type ParamValue<'a> = &'a str;
+/// The counters behind [Stats], a clone starts with the counts of the original
+#[derive(Debug, Default)]
+struct StatsCounters {
+    /// The number of messages, indexed by the numerical value of the severity
+    messages: [AtomicU64; 8],
+    bytes: AtomicU64,
+    truncations: AtomicU64,
+}
+
+impl StatsCounters {
+    fn record_message(&self, severity: Severity, len: usize) {
+        self.messages[severity as usize].fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    fn record_truncation(&self) {
+        self.truncations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Stats {
+        Stats {
+            messages: core::array::from_fn(|i| self.messages[i].load(Ordering::Relaxed)),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            truncations: self.truncations.load(Ordering::Relaxed),
+        }
+    }
+}
 
-fn encode_priority(severity: Severity, facility: Facility) -> Priority {
-    facility as u8 | severity as u8
+impl Clone for StatsCounters {
+    fn clone(&self) -> Self {
+        let stats = self.get();
+
+        Self {
+            messages: stats.messages.map(AtomicU64::new),
+            bytes: AtomicU64::new(stats.bytes),
+            truncations: AtomicU64::new(stats.truncations),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::io::ErrorKind;
+/// A snapshot of the counters of the messages written by a formatter, see [Formatter::stats]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    messages: [u64; 8],
+    /// The total length in bytes of the messages
+    pub bytes: u64,
+    /// The number of messages that are truncated to the capacity of a buffer, see [FullBuffer::Truncate],
+    /// or of which the MSG is truncated to [Config::max_len]
+    pub truncations: u64,
+}
 
-    use assert_matches::assert_matches;
+impl Stats {
+    /// The number of messages with the given severity
+    pub fn messages(&self, severity: Severity) -> u64 {
+        self.messages[severity as usize]
+    }
+
+    /// The number of messages of all severities
+    pub fn total_messages(&self) -> u64 {
+        self.messages.iter().sum()
+    }
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Config::default().into_formatter()
+    }
+}
+
+impl Formatter {
+    /// Create a new syslog 5424 formatter
+    ///
+    /// Even though the hostname is optional, it's considered highly unlikely that you can't supply one.
+    /// See <https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.4>.
+    /// A warning will be logged if no hostname is provided.
+    pub fn from_config(config: Config<'_>) -> Self {
+        let hostname = config
+            .hostname
+            .map(|hostname| config.hostname_trailing_dot.apply(hostname));
+        let app_name = config.app_name;
+        let proc_id = config
+            .proc_id
+            .map(|proc_id| config.proc_id_overflow.apply(proc_id));
+
+        // an empty field would result in two consecutive spaces, use the NILVALUE instead
+        let hostname = hostname.filter(|s| !s.is_empty()).unwrap_or(NILVALUE);
+        let app_name = app_name.filter(|s| !s.is_empty()).unwrap_or(NILVALUE);
+        let proc_id = proc_id.filter(|s| !s.is_empty()).unwrap_or(NILVALUE);
+
+        let session = config.session_proc_id.then_some(1);
+
+        let schema_elem = config.schema_version.map(|schema| {
+            let mut elem = vec![];
+            // writing to a `Vec` never fails
+            let _ = write_data_elem(
+                &mut elem,
+                (schema.sd_id, [("version", schema.version)]),
+                &config.data,
+            );
+            String::from_utf8_lossy(&elem).into()
+        });
+
+        let host_app_proc_id = match session {
+            Some(session) => format!("{hostname} {app_name} {session}"),
+            #[cfg(feature = "std")]
+            None if config.process_proc_id => {
+                format!("{hostname} {app_name} {}", std::process::id())
+            }
+            None => format!("{hostname} {app_name} {proc_id}"),
+        }
+        .into_boxed_str();
+
+        Self {
+            facility: config.facility,
+            host_app_proc_id,
+            hostname_len: hostname.len(),
+            app_name_len: app_name.len(),
+            data: config.data,
+            trailing_nul: config.trailing_nul,
+            pri_sd_id: config.pri_sd_id.map(Into::into),
+            session,
+            whitespace_msg: config.whitespace_msg,
+            omit_version: config.omit_version,
+            schema_elem,
+            max_severity: config.track_max_severity.then(SeverityTracker::new),
+            bom: config.bom,
+            hostname_trailing_dot: config.hostname_trailing_dot,
+            proc_id_overflow: config.proc_id_overflow,
+            stats: config.collect_stats.then(StatsCounters::default),
+            max_len: config.max_len,
+            pre_epoch: config.pre_epoch,
+        }
+    }
+
+    /// Start a new session by incrementing the session counter that is used as the PROCID.
+    ///
+    /// Returns the new session, or `None` if [Config::session_proc_id] isn't enabled,
+    /// in which case the PROCID doesn't change.
+    ///
+    /// ```rust
+    /// use syslog_fmt::v5424::Config;
+    ///
+    /// let mut formatter = Config {
+    ///     session_proc_id: true,
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    ///
+    /// // e.g. after reconnecting to the collector
+    /// assert_eq!(formatter.new_session(), Some(2));
+    /// ```
+    pub fn new_session(&mut self) -> Option<u64> {
+        let session = self.session.as_mut()?;
+        *session += 1;
+
+        let host_app = &self.host_app_proc_id[..self.hostname_len + 1 + self.app_name_len];
+        self.host_app_proc_id = format!("{host_app} {session}").into_boxed_str();
+
+        Some(*session)
+    }
+
+    /// Create a copy of the formatter with another PROCID, e.g. after a worker restarts or in a forked child.
+    ///
+    /// Only the preformatted header fields are rebuilt, the other options are kept.
+    /// `None` or an empty proc_id is written as the NILVALUE. The PROCID no longer follows
+    /// the session counter if [Config::session_proc_id] is enabled.
+    ///
+    /// The PROCID is handled according to [Config::proc_id_overflow] and checked like the configured `proc_id`,
+    /// the first issue is returned.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Config};
+    ///
+    /// let formatter = Config {
+    ///     hostname: Some("mymachine.example.com"),
+    ///     app_name: Some("worker"),
+    ///     proc_id: Some("8710"),
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    ///
+    /// let restarted = formatter.with_proc_id(Some("8711")).unwrap();
+    /// let buf = restarted.format_to_vec(Severity::Info, "-", "", None).unwrap();
+    /// assert_eq!(buf, b"<134>1 - mymachine.example.com worker 8711 - -");
+    ///
+    /// assert!(formatter.with_proc_id(Some("pid 8711")).is_err());
+    /// ```
+    pub fn with_proc_id(&self, proc_id: Option<&ProcId>) -> Result<Formatter, ConfigError> {
+        let proc_id = match proc_id.filter(|s| !s.is_empty()) {
+            Some(proc_id) => self.proc_id_overflow.apply(proc_id),
+            None => NILVALUE,
+        };
+
+        let mut issues = vec![];
+        validate_header_field(Field::ProcId, proc_id, PROC_ID_MAX_LEN, &mut issues);
+        if let Some(issue) = issues.into_iter().next() {
+            return Err(issue);
+        }
+
+        let host_app = &self.host_app_proc_id[..self.hostname_len + 1 + self.app_name_len];
+
+        Ok(Formatter {
+            host_app_proc_id: format!("{host_app} {proc_id}").into_boxed_str(),
+            session: None,
+            ..self.clone()
+        })
+    }
+
+    /// Format a syslog 5424 message with structured data.
+    ///
+    /// This method is a special case as the use of structured data is less likely than providing a simple string message.
+    ///
+    /// The data can be any `IntoIterator` of SD-IDs with their SD-PARAMs, e.g. an owned
+    /// `BTreeMap<&str, BTreeMap<&str, &str>>`. The elements and params are written in iteration
+    /// order, a `BTreeMap` sorts them by name while a `HashMap` writes them in an arbitrary order.
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    ///
+    /// use syslog_fmt::{Severity, Facility, v5424::{Config, Formatter, Timestamp}};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// let formatter = Config {
+    ///     facility: Facility::Local7,
+    ///     hostname: Some("localhost"),
+    ///     app_name: Some("app-name"),
+    ///     proc_id: Some("proc-id"),
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    /// formatter.write_with_data(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     Timestamp::CreateSystemNow,
+    ///     "this is a message",
+    ///     Some("msg-id"),
+    ///     vec![("elem-a", vec![("param-a", "value-a")])]
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_with_data<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        self.write_message(
+            w,
+            Priority::new(self.facility, severity),
+            timestamp.into(),
+            Overrides::default(),
+            msg.into(),
+            msg_id,
+            data,
+        )
+    }
+
+    /// An upper bound of the length of a message written by [Formatter::write_with_data].
+    ///
+    /// The PRI is assumed to be at its maximum length, as the severity isn't known.
+    /// The other fields are measured exactly, the TIMESTAMP is formatted to measure it,
+    /// as is a MSG that's formatted, e.g. [Msg::Display]. A timestamp that's created when it's written,
+    /// e.g. [Timestamp::CreateSystemNow], always has the same length. Use it to reserve a buffer
+    /// or to pick the capacity of a stack buffer.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::{Formatter, Msg, Timestamp}};
+    ///
+    /// let formatter = Formatter::default();
+    /// let data = [("exampleSDID@32473", [("iut", "3")])];
+    /// let timestamp = Timestamp::from("2003-10-11T22:14:15.003Z");
+    /// let len = formatter.estimated_len(&timestamp, &Msg::Utf8Str("a message"), Some("ID47"), data);
+    ///
+    /// let mut buf = Vec::with_capacity(len);
+    /// formatter
+    ///     .write_with_data(&mut buf, Severity::Info, "2003-10-11T22:14:15.003Z", "a message", Some("ID47"), data)
+    ///     .unwrap();
+    /// assert!(buf.len() <= len);
+    /// ```
+    pub fn estimated_len<'a, I, P>(
+        &self,
+        timestamp: &Timestamp<'_>,
+        msg: &Msg<'_>,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> usize
+    where
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        const PRI_VERSION_MAX_LEN: usize = "<191>1 ".len();
+
+        let mut timestamp_len = ByteCounter::default();
+        // writing to a `ByteCounter` never fails, an invalid timestamp fails the message itself
+        let _ = write_timestamp_field(&mut timestamp_len, timestamp.reborrow());
+
+        let msg_id_len = msg_id.filter(|s| !s.is_empty()).map_or(1, str::len);
+        let header_len = PRI_VERSION_MAX_LEN
+            + timestamp_len.len
+            + 1
+            + self.host_app_proc_id.len()
+            + 1
+            + msg_id_len;
+
+        // the PRI element is longest with "Warning", the longest severity name
+        let priority = Priority::new(self.facility, Severity::Warning);
+        let mut data_len = ByteCounter::default();
+        let _ = self.write_data(&mut data_len, priority, data);
+
+        let mut msg_len = ByteCounter::default();
+        let _ = write_msg_with_bom(&mut msg_len, self.prepare_msg(msg.reborrow()), self.bom);
+
+        let without_msg = header_len + data_len.len + usize::from(self.trailing_nul);
+        let len = without_msg + msg_len.len;
+
+        // the MSG is truncated to fit, but the header and data are never truncated
+        self.max_len
+            .map_or(len, |max_len| len.min(max_len.max(without_msg)))
+    }
+
+    /// Format a syslog 5424 message with structured data, with the MSG and PARAM-VALUEs
+    /// normalized to Unicode Normalization Form C (NFC).
+    ///
+    /// Canonically equivalent strings, e.g. a precomposed `é` and an `e` followed by a
+    /// combining acute accent, produce identical bytes. This helps collectors to
+    /// deduplicate and index messages consistently.
+    ///
+    /// A [Msg::NonUnicodeBytes] is written as is. Normalizing allocates, so this is only
+    /// available with the `unicode-normalization` feature.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// Formatter::default().write_nfc(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "cafe\u{301}",
+    ///     None,
+    ///     [("exampleSDID@32473", [("name", "Rene\u{301}")])],
+    /// );
+    ///
+    /// assert!(buf.ends_with("caf\u{e9}".as_bytes()));
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn write_nfc<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        use unicode_normalization::UnicodeNormalization as _;
+
+        let msg = match msg.into() {
+            Msg::NonUnicodeBytes(bytes) => Msg::NonUnicodeBytes(bytes),
+            Msg::Utf8Str(s) => Msg::Utf8String(s.nfc().collect()),
+            Msg::Utf8String(s) => Msg::Utf8String(s.nfc().collect()),
+            Msg::FmtArguments(args) => Msg::Utf8String(args.to_string().nfc().collect()),
+            Msg::FmtArgumentsRef(args) => Msg::Utf8String(args.to_string().nfc().collect()),
+            Msg::Display(value) => Msg::Utf8String(value.to_string().nfc().collect()),
+        };
+
+        let data = data
+            .into_iter()
+            .map(|(id, params)| {
+                let params = params
+                    .into_iter()
+                    .map(|(name, value)| (name, value.nfc().collect::<String>()))
+                    .collect::<Vec<_>>();
+                (id, params)
+            })
+            .collect::<Vec<_>>();
+        let data = data.iter().map(|(id, params)| {
+            let params = params.iter().map(|(name, value)| (*name, value.as_str()));
+            (*id, params)
+        });
+
+        self.write_message(
+            w,
+            Priority::new(self.facility, severity),
+            timestamp.into(),
+            Overrides::default(),
+            msg,
+            msg_id,
+            data,
+        )
+    }
+
+    /// Format a syslog 5424 message with a single SD-ELEMENT given by its SD-ID and a flat list of params.
+    ///
+    /// This is a shorthand for [Formatter::write_with_data] for the common case of one element.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// Formatter::default().write_with_params(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "this is a message",
+    ///     None,
+    ///     "exampleSDID@32473",
+    ///     &[("iut", "3"), ("eventSource", "Application")],
+    /// );
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    #[cfg(feature = "std")]
+    pub fn write_with_params<'a, W, TS, M, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        sd_id: &'a SdId,
+        params: P,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        P: IntoIterator<Item = &'a SdParam<'a>> + 'a,
+    {
+        let params = params.into_iter().copied();
+        self.write_with_data(w, severity, timestamp, msg, msg_id, [(sd_id, params)])
+    }
+
+    /// Format a syslog 5424 message with structured data using a raw PRI value
+    /// instead of the facility of the formatter and a severity.
+    ///
+    /// This is useful for a relay that forwards a parsed message, the PRI is written as is
+    /// without decoding and encoding it again, see [Priority] to convert a raw value.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{v5424::Formatter, Priority};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    /// Formatter::default().write_with_priority(
+    ///     &mut buf,
+    ///     Priority::try_from(165).unwrap(),
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "a relayed message",
+    ///     None,
+    ///     no_data,
+    /// );
+    /// assert!(buf.starts_with(b"<165>1 "));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_with_priority<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        priority: Priority,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        self.write_message(
+            w,
+            priority,
+            timestamp.into(),
+            Overrides::default(),
+            msg.into(),
+            msg_id,
+            data,
+        )
+    }
+
+    /// Format a syslog 5424 message given a simple string message.
+    /// An optional MSG-ID can be provided by using a two string tuple for the msg param:
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    ///
+    /// use syslog_fmt::{Severity, Facility, v5424::{Config, Formatter, Timestamp}};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// let formatter = Config {
+    ///     facility: Facility::Local7,
+    ///     hostname: Some("localhost"),
+    ///     app_name: Some("app-name"),
+    ///     proc_id: Some("proc-id"),
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    /// formatter.write_without_data(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     Timestamp::CreateSystemNow,
+    ///     "this is a message",
+    ///     Some("msg-id")
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_without_data<'a, W, TS, M>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        self.write_with_data(w, severity, timestamp, msg, msg_id, no_data)
+    }
+
+    /// Format a syslog 5424 message with structured data and write it with octet counting framing,
+    /// e.g. to a TCP stream. See [frame_octet_counting].
+    ///
+    /// The message is formatted into a buffer first to count its length.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut stream = Vec::<u8>::new();
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    /// Formatter::default()
+    ///     .write_framed(&mut stream, Severity::Info, "2003-10-11T22:14:15.003Z", "", None, no_data)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(stream, b"41 <134>1 2003-10-11T22:14:15.003Z - - - - -");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_framed<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let mut buf = Vec::new();
+        self.write_with_data(&mut buf, severity, timestamp, msg, msg_id, data)?;
+        frame_octet_counting(w, &buf)
+    }
+
+    /// Format a syslog 5424 message followed by a LF and write it to stderr.
+    ///
+    /// This is meant for local logging. The message is formatted into a buffer first, so
+    /// it's written with a single write while stderr is locked, which keeps the messages of
+    /// multiple threads from interleaving.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// Formatter::default()
+    ///     .write_to_stderr(Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None)
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to_stderr<'a, TS, M>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        self.write_line(io::stderr().lock(), severity, timestamp, msg, msg_id)
+    }
+
+    /// Format a syslog 5424 message followed by a LF and write it to stdout.
+    ///
+    /// See [Formatter::write_to_stderr] for details.
+    #[cfg(feature = "std")]
+    pub fn write_to_stdout<'a, TS, M>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        self.write_line(io::stdout().lock(), severity, timestamp, msg, msg_id)
+    }
+
+    /// Format a syslog 5424 message followed by a LF into a buffer and write it with a single write.
+    ///
+    /// Pass a locked writer, e.g. `io::stderr().lock()`, to write to it while the lock is held only once.
+    #[cfg(feature = "std")]
+    pub fn write_line<'a, W, TS, M>(
+        &self,
+        mut w: W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        let mut buf = vec![];
+        self.write_without_data(&mut buf, severity, timestamp, msg, msg_id)?;
+        buf.push(b'\n');
+
+        w.write_all(&buf)?;
+        w.flush()
+    }
+
+    /// Format a syslog 5424 message and soft-wrap the MSG at the given column for readability.
+    ///
+    /// Each wrapped line ends with a `\\` continuation marker followed by a LF.
+    /// The header counts towards the column of the first line.
+    ///
+    /// **The output is not a valid syslog message**, it's meant for human-facing local logs only.
+    /// Use the other write methods for the messages sent to a syslog daemon.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::{Formatter, Timestamp}};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// Formatter::default()
+    ///     .write_wrapped(&mut buf, Severity::Info, Timestamp::None, "a message", None, 20)
+    ///     .unwrap();
+    /// assert_eq!(buf, "<134>1 - - - - - - \u{feff}a\\\n message".as_bytes());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_wrapped<'a, W, TS, M>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        column: usize,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        let annotated = self.format_annotated(severity, timestamp, msg, msg_id, no_data)?;
+        let msg_range = annotated
+            .fields
+            .iter()
+            .find(|(field, _)| *field == Field::Msg)
+            .map(|(_, range)| range.clone())
+            .unwrap_or_default();
+
+        let (head, msg) = annotated.bytes[..msg_range.end].split_at(msg_range.start);
+        let tail = &annotated.bytes[msg_range.end..];
+
+        // the BOM has no width
+        let bom = &SPACED_BOM[1..];
+        let (bom, msg) = match msg.strip_prefix(bom) {
+            Some(msg) => (bom, msg),
+            None => (&[][..], msg),
+        };
+
+        let is_char_start = |b: &u8| b & 0b1100_0000 != 0b1000_0000;
+
+        w.write_all(head)?;
+        w.write_all(bom)?;
+
+        let mut col = head.iter().filter(|b| is_char_start(b)).count();
+        let mut start = 0;
+
+        for (i, b) in msg.iter().enumerate() {
+            if !is_char_start(b) {
+                continue;
+            }
+
+            if col >= column {
+                w.write_all(&msg[start..i])?;
+                w.write_all(b"\\\n")?;
+                start = i;
+                col = 0;
+            }
+
+            col += 1;
+        }
+
+        w.write_all(&msg[start..])?;
+        w.write_all(tail)
+    }
+
+    /// Format a syslog 5424 message with structured data like [Formatter::write_with_data]
+    /// and write it to a `tokio::io::AsyncWrite`.
+    ///
+    /// The message is formatted into a buffer before the returned future is polled,
+    /// so the future only borrows the writer and its writes apply backpressure as usual.
+    /// A message that fails to format is not written at all.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let mut stream = Vec::<u8>::new();
+    /// Formatter::default()
+    ///     .write_with_data_async(
+    ///         &mut stream,
+    ///         Severity::Info,
+    ///         "2003-10-11T22:14:15.003Z",
+    ///         "this is a message",
+    ///         None,
+    ///         [("exampleSDID@32473", [("iut", "3")])],
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn write_with_data_async<'a, 'w, W, TS, M, I, P>(
+        &self,
+        w: &'w mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> impl std::future::Future<Output = io::Result<()>> + 'w
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let mut buf = Vec::new();
+        let result = self.write_with_data(&mut buf, severity, timestamp, msg, msg_id, data);
+
+        write_formatted_async(w, result.map(|()| buf))
+    }
+
+    /// Format a syslog 5424 message like [Formatter::write_without_data] and write it to a `tokio::io::AsyncWrite`,
+    /// see [Formatter::write_with_data_async].
+    #[cfg(feature = "tokio")]
+    pub fn write_without_data_async<'a, 'w, W, TS, M>(
+        &self,
+        w: &'w mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> impl std::future::Future<Output = io::Result<()>> + 'w
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        let mut buf = Vec::new();
+        let result = self.write_without_data(&mut buf, severity, timestamp, msg, msg_id);
+
+        write_formatted_async(w, result.map(|()| buf))
+    }
+
+    /// Format a syslog 5424 message with structured data directly into a `BytesMut`.
+    ///
+    /// Capacity for the header and MSG is reserved up front, so the buffer grows at most once
+    /// for a typical message. The message is appended after the existing content of the buffer.
+    ///
+    /// ```rust
+    /// use bytes::BytesMut;
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut buf = BytesMut::new();
+    /// Formatter::default()
+    ///     .write_to_bytes_mut(
+    ///         &mut buf,
+    ///         Severity::Info,
+    ///         "2003-10-11T22:14:15.003Z",
+    ///         "this is a message",
+    ///         None,
+    ///         [("exampleSDID@32473", [("iut", "3")])],
+    ///     )
+    ///     .unwrap();
+    ///
+    /// assert!(buf.starts_with(b"<134>1 2003-10-11T22:14:15.003Z"));
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn write_to_bytes_mut<'a, TS, M, I, P>(
+        &self,
+        buf: &mut bytes::BytesMut,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        use bytes::BufMut as _;
+
+        let msg = msg.into();
+        buf.reserve(HEADER_LEN_HINT + self.host_app_proc_id.len() + msg.len_hint());
+
+        let mut w = buf.writer();
+        self.write_with_data(&mut w, severity, timestamp, msg, msg_id, data)
+    }
+
+    /// Append a syslog 5424 message with structured data after the existing content of the buffer.
+    ///
+    /// The new total length of the buffer is returned, this marks the end of the appended message.
+    /// Use these lengths to find the boundaries of multiple messages written to a single buffer.
+    #[cfg(feature = "std")]
+    pub fn append_with_data<'a, B, TS, M, I, P>(
+        &self,
+        buf: &mut B,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<usize>
+    where
+        B: io::Write + AsRef<[u8]>,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        self.write_with_data(buf, severity, timestamp, msg, msg_id, data)?;
+        Ok(buf.as_ref().len())
+    }
+
+    /// Append a syslog 5424 message after the existing content of the buffer.
+    ///
+    /// The new total length of the buffer is returned, this marks the end of the appended message.
+    /// Use these lengths to find the boundaries of multiple messages written to a single buffer.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let formatter = Formatter::default();
+    /// let mut buf = Vec::<u8>::new();
+    ///
+    /// let first_end = formatter
+    ///     .append_without_data(&mut buf, Severity::Info, "2003-10-11T22:14:15.003Z", "first", None)
+    ///     .unwrap();
+    /// let second_end = formatter
+    ///     .append_without_data(&mut buf, Severity::Info, "2003-10-11T22:14:15.003Z", "second", None)
+    ///     .unwrap();
+    ///
+    /// let first = &buf[..first_end];
+    /// let second = &buf[first_end..second_end];
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn append_without_data<'a, B, TS, M>(
+        &self,
+        buf: &mut B,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<usize>
+    where
+        B: io::Write + AsRef<[u8]>,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        self.write_without_data(buf, severity, timestamp, msg, msg_id)?;
+        Ok(buf.as_ref().len())
+    }
+
+    /// Format a syslog 5424 message with structured data into a `fmt::Write`, e.g. a `String`,
+    /// without an intermediate `Vec<u8>` and UTF-8 check.
+    ///
+    /// This is available without the `std` feature, it only needs `alloc`.
+    /// The message is the same as the output of [Formatter::write_with_data],
+    /// the BOM is written as the `'\u{feff}'` char.
+    ///
+    /// As `fmt::Error` carries no information, any error is reported as a `fmt::Error`,
+    /// e.g. a [Msg::NonUnicodeBytes] that isn't valid UTF-8, a timestamp rejected by [Config::pre_epoch]
+    /// or an error of the writer.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut s = String::new();
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    /// Formatter::default()
+    ///     .format_into(&mut s, Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None, no_data)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(s, "<134>1 2003-10-11T22:14:15.003Z - - - - - \u{feff}a message");
+    /// ```
+    pub fn format_into<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> fmt::Result
+    where
+        W: fmt::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let mut bridge = IoBridge { w };
+        self.write_message(
+            &mut bridge,
+            Priority::new(self.facility, severity),
+            timestamp.into(),
+            Overrides::default(),
+            msg.into(),
+            msg_id,
+            data,
+        )
+        .map_err(|_err| fmt::Error)
+    }
+
+    /// Write a syslog 5424 message with structured data into a `fmt::Write`, e.g. a `String`.
+    ///
+    /// A [Msg::NonUnicodeBytes] that isn't valid UTF-8 is an error of kind `InvalidData`.
+    #[deprecated(
+        note = "use `Formatter::format_into`, which is also available without the `std` feature"
+    )]
+    #[cfg(feature = "std")]
+    pub fn write_to_fmt<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: fmt::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let mut bridge = IoBridge { w };
+        self.write_with_data(&mut bridge, severity, timestamp, msg, msg_id, data)
+    }
+
+    /// Write a syslog 5424 message with structured data into a buffer with a limited capacity,
+    /// handling a message that doesn't fit according to the given [FullBuffer] policy.
+    ///
+    /// A partially written message is useless for a datagram transport,
+    /// use [FullBuffer::Error] to leave the buffer untouched instead.
+    ///
+    /// With [FullBuffer::Truncate] the message is written directly into the buffer. The other policies
+    /// need the length of the message before anything is written, so it's formatted into a temporary `Vec` first.
+    /// Only the bytes that end up in the buffer are counted in the [Stats].
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::{Formatter, FullBuffer}};
+    ///
+    /// let mut storage = [0; 32];
+    /// let mut buf = std::io::Cursor::new(&mut storage[..]);
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    ///
+    /// let result = Formatter::default().write_to_buffer(
+    ///     &mut buf,
+    ///     FullBuffer::Error,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "this message doesn't fit",
+    ///     None,
+    ///     no_data,
+    /// );
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(buf.position(), 0);
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    #[cfg(feature = "std")]
+    pub fn write_to_buffer<'a, B, TS, M, I, P>(
+        &self,
+        buf: &mut B,
+        policy: FullBuffer,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        B: Buffer,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let priority = Priority::new(self.facility, severity);
+        let timestamp = timestamp.into();
+        let msg = msg.into();
+        let remaining = buf.remaining_capacity();
+
+        if policy == FullBuffer::Truncate {
+            let mut bounded = BoundedWriter {
+                inner: buf,
+                remaining,
+            };
+            let mut w = CountingWriter::new(&mut bounded);
+            let overrides = Overrides::default();
+            let res = self.write_message_unrecorded(
+                &mut w, priority, timestamp, overrides, msg, msg_id, data,
+            );
+            let len = w.len;
+
+            return match res {
+                Ok(truncated) => {
+                    self.record_message(severity, len, truncated);
+                    Ok(())
+                }
+                Err(err) if err.kind() == io::ErrorKind::WriteZero && bounded.remaining == 0 => {
+                    self.record_message(severity, len, true);
+                    Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "the message is truncated to the capacity of the buffer",
+                    ))
+                }
+                Err(err) => Err(err),
+            };
+        }
+
+        let mut message = Vec::new();
+        let truncated = self.write_message_unrecorded(
+            &mut CountingWriter::new(&mut message),
+            priority,
+            timestamp,
+            Overrides::default(),
+            msg,
+            msg_id,
+            data,
+        )?;
+
+        let fits = message.len() <= remaining
+            || policy == FullBuffer::Grow && buf.try_grow(message.len() - remaining);
+
+        if !fits {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                format!(
+                    "the message of {} bytes doesn't fit the remaining {remaining} bytes of the buffer",
+                    message.len()
+                ),
+            ));
+        }
+
+        buf.write_all(&message)?;
+        self.record_message(severity, message.len(), truncated);
+        Ok(())
+    }
+
+    /// Write an `Info` message that marks the start of the log of an application.
+    ///
+    /// The message has no MSG and a single SD-ELEMENT with the given SD-ID
+    /// and the param `event="startup"`, e.g. `[lifecycle@32473 event="startup"]`.
+    #[cfg(feature = "std")]
+    pub fn write_startup_marker<'a, W, TS>(
+        &self,
+        w: &mut W,
+        timestamp: TS,
+        sd_id: &'a SdId,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        self.write_lifecycle_marker(w, timestamp, sd_id, "startup")
+    }
+
+    /// Write an `Info` message that marks the end of the log of an application.
+    ///
+    /// The message has no MSG and a single SD-ELEMENT with the given SD-ID
+    /// and the param `event="shutdown"`, e.g. `[lifecycle@32473 event="shutdown"]`.
+    #[cfg(feature = "std")]
+    pub fn write_shutdown_marker<'a, W, TS>(
+        &self,
+        w: &mut W,
+        timestamp: TS,
+        sd_id: &'a SdId,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        self.write_lifecycle_marker(w, timestamp, sd_id, "shutdown")
+    }
+
+    /// Write the smallest valid message as a keep-alive, e.g. to keep a TCP connection to a collector open.
+    ///
+    /// The message is a `Debug` message of the configured facility with the NILVALUE
+    /// for every header field and the structured data, and without a MSG, e.g. `<135>1 - - - - - -`.
+    /// It's written like any other message, so the options of the config apply, e.g.
+    /// [Config::omit_version], [Config::trailing_nul], [Config::pri_sd_id] and [Config::collect_stats].
+    /// Frame the message like any other message, e.g. with [frame_octet_counting].
+    ///
+    /// ```rust
+    /// use syslog_fmt::v5424::Formatter;
+    ///
+    /// let mut buf = vec![];
+    /// Formatter::default().write_keep_alive(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"<135>1 - - - - - -");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_keep_alive<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let nil_fields = Overrides {
+            hostname: Some(NILVALUE),
+            app_name: Some(NILVALUE),
+            proc_id: Some(NILVALUE),
+        };
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+
+        self.write_message(
+            w,
+            Priority::new(self.facility, Severity::Debug),
+            Timestamp::None,
+            nil_fields,
+            Msg::Utf8Str(""),
+            None,
+            no_data,
+        )
+    }
+
+    /// The facility of the messages written with a [Severity], see [Config::facility]
+    pub fn facility(&self) -> Facility {
+        self.facility
+    }
+
+    /// The highest severity of the messages written since the formatter was created or the last summary,
+    /// `None` if no message is written or [Config::track_max_severity] isn't enabled.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{v5424::Config, Severity};
+    ///
+    /// let formatter = Config {
+    ///     track_max_severity: true,
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    ///
+    /// let mut buf = vec![];
+    /// for severity in [Severity::Info, Severity::Crit, Severity::Warning] {
+    ///     formatter.write_without_data(&mut buf, severity, "-", "a message", None).unwrap();
+    /// }
+    ///
+    /// assert_eq!(formatter.max_severity(), Some(Severity::Crit));
+    /// ```
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.max_severity.as_ref()?.get()
+    }
+
+    /// The counters of the messages written, `None` if [Config::collect_stats] isn't enabled.
+    ///
+    /// A message is counted by the methods that write a whole message, the bytes are those of the
+    /// formatted message before any framing. The fine-grained writers, like [Formatter::write_header],
+    /// aren't counted.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Config};
+    ///
+    /// let formatter = Config {
+    ///     collect_stats: true,
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    ///
+    /// let mut buf = vec![];
+    /// formatter.write_without_data(&mut buf, Severity::Warning, "-", "disk almost full", None).unwrap();
+    ///
+    /// let stats = formatter.stats().unwrap();
+    /// assert_eq!(stats.messages(Severity::Warning), 1);
+    /// assert_eq!(stats.bytes, buf.len() as u64);
+    /// ```
+    pub fn stats(&self) -> Option<Stats> {
+        Some(self.stats.as_ref()?.get())
+    }
+
+    /// Write an `Info` message with the highest severity written so far in an SD-ELEMENT
+    /// with the given SD-ID, e.g. `[session@32473 maxSeverity="crit"]`, for health reporting.
+    ///
+    /// The severity is written as its lowercase keyword, like `syslog.conf` uses, e.g. `emerg`, `crit` or `info`.
+    /// The tracked severity is taken and reset at once, so each summary covers the messages written since
+    /// the previous summary, also when messages are written concurrently. The summary itself isn't tracked.
+    /// The element has no params if no message is written or [Config::track_max_severity] isn't enabled.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{v5424::Config, Severity};
+    ///
+    /// let formatter = Config {
+    ///     track_max_severity: true,
+    ///     ..Default::default()
+    /// }
+    /// .into_formatter();
+    ///
+    /// let mut buf = vec![];
+    /// formatter.write_without_data(&mut buf, Severity::Crit, "-", "a message", None).unwrap();
+    ///
+    /// buf.clear();
+    /// formatter.write_max_severity_summary(&mut buf, "-", "session@32473").unwrap();
+    /// assert_eq!(buf, b"<134>1 - - - - - [session@32473 maxSeverity=\"crit\"]");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_max_severity_summary<'a, W, TS>(
+        &self,
+        w: &mut W,
+        timestamp: TS,
+        sd_id: &'a SdId,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        let tracker = self.max_severity.as_ref();
+        let max_severity = tracker.and_then(SeverityTracker::take);
+        let params = max_severity.map(|severity| ("maxSeverity", severity_keyword(severity)));
+
+        // the summary itself isn't part of the next summary, so it's only recorded in the stats
+        let mut w = CountingWriter::new(w);
+        let res = self.write_message_unrecorded(
+            &mut w,
+            Priority::new(self.facility, Severity::Info),
+            timestamp.into(),
+            Overrides::default(),
+            Msg::Utf8Str(""),
+            None,
+            [(sd_id, params)],
+        );
+
+        match res {
+            Ok(truncated) => {
+                self.record_stats(Severity::Info, w.len, truncated);
+                Ok(())
+            }
+            Err(err) => {
+                // the severity is reported by the next summary instead
+                if let (Some(tracker), Some(max_severity)) = (tracker, max_severity) {
+                    tracker.record(max_severity);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn write_lifecycle_marker<'a, W, TS>(
+        &self,
+        w: &mut W,
+        timestamp: TS,
+        sd_id: &'a SdId,
+        event: &'a str,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        self.write_with_data(
+            w,
+            Severity::Info,
+            timestamp,
+            "",
+            None,
+            [(sd_id, [("event", event)])],
+        )
+    }
+
+    /// Format a syslog 5424 message into a `Vec` with a capacity equal to the length of the message.
+    ///
+    /// The message is written twice, once to measure its length and once into the allocated `Vec`.
+    /// A chrono timestamp that is created by the formatter is only created once, so both writes are equal.
+    /// The length of any other timestamp created by the formatter doesn't change between the writes.
+    #[cfg(feature = "std")]
+    pub fn format_to_vec<'a, TS, M>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<Vec<u8>>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+    {
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        self.format_to_vec_with_data(severity, timestamp, msg, msg_id, no_data)
+    }
+
+    /// Format a syslog 5424 message with structured data into a `Vec` with a capacity
+    /// equal to the length of the message.
+    ///
+    /// See [Formatter::format_to_vec] for details.
+    #[cfg(feature = "std")]
+    pub fn format_to_vec_with_data<'a, TS, M, I, P>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<Vec<u8>>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + Clone + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        #[cfg(feature = "chrono")]
+        let now;
+        let timestamp = match timestamp.into() {
+            #[cfg(feature = "chrono")]
+            Timestamp::CreateChronoLocal => {
+                now = chrono::Local::now();
+                Timestamp::Chrono(&now)
+            }
+            timestamp => timestamp,
+        };
+        let msg = self.prepare_msg(msg);
+        let priority = Priority::new(self.facility, severity);
+
+        let mut counter = ByteCounter::default();
+        self.write_message_unrecorded(
+            &mut CountingWriter::new(&mut counter),
+            priority,
+            timestamp.reborrow(),
+            Overrides::default(),
+            msg.reborrow(),
+            msg_id,
+            data.clone(),
+        )?;
+
+        let mut buf = Vec::with_capacity(counter.len);
+        let overrides = Overrides::default();
+        self.write_message(&mut buf, priority, timestamp, overrides, msg, msg_id, data)?;
+
+        Ok(buf)
+    }
+
+    /// Format a syslog 5424 message with structured data like [Formatter::write_with_data],
+    /// and in debug builds parse the STRUCTURED-DATA of the output to verify it's well formed.
+    ///
+    /// This catches structured data that results in an unparseable message during development,
+    /// e.g. an illegal char in a PARAM-NAME. In debug builds the message is formatted into a
+    /// buffer first and a malformed message panics before anything is written.
+    /// Release builds write the message directly without any checks.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// Formatter::default().write_checked(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "this is a message",
+    ///     None,
+    ///     [("exampleSDID@32473", [("iut", "3")])],
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_checked<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        #[cfg(debug_assertions)]
+        {
+            let annotated = self.format_annotated(severity, timestamp, msg, msg_id, data)?;
+            let data = annotated.get(Field::StructuredData).unwrap_or_default();
+            let data = core::str::from_utf8(data).unwrap_or_default();
+
+            if let Err(reason) = check_structured_data(data) {
+                panic!("The self-check of the formatted message failed, {reason}: {data}");
+            }
+
+            w.write_all(&annotated.bytes)
+        }
+
+        #[cfg(not(debug_assertions))]
+        self.write_with_data(w, severity, timestamp, msg, msg_id, data)
+    }
+
+    /// Format a syslog 5424 message with structured data like [Formatter::write_with_data],
+    /// after validating each SD-ID and PARAM-NAME with [validate_data].
+    ///
+    /// Nothing is written if a name is invalid, the error is of kind `InvalidInput`
+    /// and wraps an [InvalidSdName] that identifies the name.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::{Formatter, InvalidSdName}};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// let err = Formatter::default()
+    ///     .write_with_checked_data(
+    ///         &mut buf,
+    ///         Severity::Info,
+    ///         "2003-10-11T22:14:15.003Z",
+    ///         "this is a message",
+    ///         None,
+    ///         [("bad id", [("iut", "3")])],
+    ///     )
+    ///     .unwrap_err();
+    ///
+    /// let invalid = err.get_ref().unwrap().downcast_ref::<InvalidSdName>().unwrap();
+    /// assert_eq!(invalid.sd_id, "bad id");
+    /// assert!(buf.is_empty());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_with_checked_data<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + Clone + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        validate_data(data.clone()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        self.write_with_data(w, severity, timestamp, msg, msg_id, data)
+    }
+
+    /// Format a syslog 5424 message with structured data together with the byte range of each field.
+    ///
+    /// The bytes are identical to the output of [Formatter::write_with_data].
+    /// This is meant for debugging and for tools that show an annotated or highlighted view of a message.
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::{Field, Formatter}};
+    ///
+    /// let annotated = Formatter::default()
+    ///     .format_annotated(
+    ///         Severity::Info,
+    ///         "2003-10-11T22:14:15.003Z",
+    ///         "a message",
+    ///         Some("ID47"),
+    ///         [("exampleSDID@32473", [("iut", "3")])],
+    ///     )
+    ///     .unwrap();
+    ///
+    /// assert_eq!(annotated.get(Field::MsgId), Some(&b"ID47"[..]));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn format_annotated<'a, TS, M, I, P>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<AnnotatedMessage>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let priority = Priority::new(self.facility, severity);
+        let mut bytes = vec![];
+        let mut w = CountingWriter::recording_fields(&mut bytes);
+
+        let truncated = self.write_message_unrecorded(
+            &mut w,
+            priority,
+            timestamp.into(),
+            Overrides::default(),
+            msg.into(),
+            msg_id,
+            data,
+        )?;
+        let fields = w.fields.take().unwrap_or_default();
+        self.record_message(severity, bytes.len(), truncated);
+
+        Ok(AnnotatedMessage { bytes, fields })
+    }
+
+    /// Format a syslog 5424 message with structured data and report how it deviates from the spec.
+    ///
+    /// The message is formatted as is, a deviation never causes an error. This allows monitoring
+    /// the conformance of the messages in production, e.g. by validating a sample of the messages.
+    /// The bytes are identical to the output of [Formatter::write_with_data].
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::{Formatter, Msg, Warning}};
+    ///
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    /// let (bytes, warnings) = Formatter::default()
+    ///     .format_and_validate(
+    ///         Severity::Info,
+    ///         "2003-10-11T22:14:15.003Z",
+    ///         Msg::NonUnicodeBytes(b"a message"),
+    ///         None,
+    ///         no_data,
+    ///     )
+    ///     .unwrap();
+    ///
+    /// assert!(warnings.contains(&Warning::BomOmitted));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn format_and_validate<'a, TS, M, I, P>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<(Vec<u8>, Vec<Warning>)>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let annotated = self.format_annotated(severity, timestamp, msg, msg_id, data)?;
+        let field = |field| {
+            let bytes = annotated.get(field).unwrap_or_default();
+            String::from_utf8_lossy(bytes)
+        };
+
+        let mut issues = vec![];
+
+        match &*field(Field::Hostname) {
+            NILVALUE => issues.push(ConfigError::MissingHostname),
+            hostname => {
+                validate_header_field(Field::Hostname, hostname, HOSTNAME_MAX_LEN, &mut issues);
+            }
+        }
+
+        let header_fields = [
+            (Field::AppName, APP_NAME_MAX_LEN),
+            (Field::ProcId, PROC_ID_MAX_LEN),
+            (Field::MsgId, MSG_ID_MAX_LEN),
+        ];
+
+        for (name, max_len) in header_fields {
+            validate_header_field(name, &field(name), max_len, &mut issues);
+        }
+
+        let mut warnings: Vec<Warning> = issues.into_iter().map(Warning::Field).collect();
+
+        let data = field(Field::StructuredData);
+        for elem in sd_elements(&data) {
+            let id = elem[1..].split([' ', ']']).next().unwrap_or_default();
+
+            if let Err(e) = validate_sd_id(id) {
+                warnings.push(Warning::SdId(e));
+            }
+        }
+
+        let msg = annotated.get(Field::Msg).unwrap_or_default();
+        if !msg.is_empty() {
+            if !msg.starts_with(&SPACED_BOM[1..]) {
+                warnings.push(Warning::BomOmitted);
+            }
+
+            if core::str::from_utf8(msg).is_err() {
+                warnings.push(Warning::NonUtf8Msg);
+            }
+        }
+
+        Ok((annotated.bytes, warnings))
+    }
+
+    /// Format a syslog 5424 message with structured data together with a hash of its content.
+    ///
+    /// The hash covers the severity, the MSG and the SD-ELEMENTs sorted by their formatted bytes.
+    /// The timestamp and the other header fields are left out, so downstream systems can use
+    /// the hash to deduplicate identical events. The params within an element aren't sorted.
+    /// The hash is a 64-bit FNV-1a hash, which is stable across platforms and releases.
+    /// It's not a cryptographic hash.
+    /// The bytes are identical to the output of [Formatter::write_with_data].
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let formatter = Formatter::default();
+    /// let data = [("exampleSDID@32473", [("iut", "3")])];
+    ///
+    /// let (_, first) = formatter
+    ///     .format_with_content_hash(Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None, data)
+    ///     .unwrap();
+    /// let (_, second) = formatter
+    ///     .format_with_content_hash(Severity::Info, "2003-10-11T22:14:16.003Z", "a message", None, data)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(first, second);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn format_with_content_hash<'a, TS, M, I, P>(
+        &self,
+        severity: Severity,
+        timestamp: TS,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<(Vec<u8>, u64)>
+    where
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let annotated = self.format_annotated(severity, timestamp, msg, msg_id, data)?;
+
+        let data = annotated.get(Field::StructuredData).unwrap_or_default();
+        let data = String::from_utf8_lossy(data);
+        let mut elems: Vec<&str> = sd_elements(&data).collect();
+        elems.sort_unstable();
+
+        let mut hasher = Fnv1a::default();
+        hasher.write(&[severity as u8]);
+        // each part is prefixed with its length, so the boundaries between the parts are part of the hash
+        for part in elems.iter().map(|elem| elem.as_bytes()) {
+            hasher.write(&part.len().to_le_bytes());
+            hasher.write(part);
+        }
+        let msg = annotated.get(Field::Msg).unwrap_or_default();
+        hasher.write(&msg.len().to_le_bytes());
+        hasher.write(msg);
+
+        Ok((annotated.bytes, hasher.0))
+    }
+
+    /// Convert the MSG to the MSG to write, applying the [WhitespaceMsg] option
+    fn prepare_msg<'m, M: Into<Msg<'m>>>(&self, msg: M) -> Msg<'m> {
+        let msg = msg.into();
+
+        if self.whitespace_msg == WhitespaceMsg::Keep {
+            return msg;
+        }
+
+        let is_whitespace = |s: &str| s.chars().all(char::is_whitespace);
+        let s = match &msg {
+            Msg::Utf8Str(s) => Some(*s),
+            Msg::Utf8String(s) => Some(s.as_str()),
+            Msg::FmtArguments(args) => args.as_str(),
+            Msg::FmtArgumentsRef(args) => args.as_str(),
+            Msg::NonUnicodeBytes(_) | Msg::Display(_) => None,
+        };
+
+        if s.is_some_and(is_whitespace) {
+            Msg::Utf8Str("")
+        } else {
+            msg
+        }
+    }
+
+    /// Write the structured data, with the PRI and schema version elements in front if they're configured
+    fn write_data<'a, W, I, P>(&self, w: &mut W, priority: Priority, data: I) -> io::Result<()>
+    where
+        W: io::Write,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        // a raw PRI value may not decode into a known facility
+        let pri = self
+            .pri_sd_id
+            .as_ref()
+            .and_then(|pri_sd_id| Some((pri_sd_id, decode_priority(priority.value()).ok()?)));
+
+        if pri.is_none() && self.schema_elem.is_none() {
+            return write_structured_data(w, data, &self.data);
+        }
+
+        write!(w, " ")?;
+
+        if let Some((pri_sd_id, (facility, severity))) = pri {
+            // the names are plain ASCII, so they never need to be escaped
+            write!(
+                w,
+                "[{pri_sd_id} facility=\"{facility}\" severity=\"{severity}\"]"
+            )?;
+        }
+
+        if let Some(schema_elem) = &self.schema_elem {
+            w.write_all(schema_elem.as_bytes())?;
+        }
+
+        write_data_elems(w, data, &self.data, false)?;
+
+        Ok(())
+    }
+
+    /// Record a message that is written in the tracked severity and the stats, if these are enabled
+    fn record_message(&self, severity: Severity, len: usize, truncated: bool) {
+        if let Some(max_severity) = &self.max_severity {
+            max_severity.record(severity);
+        }
+
+        self.record_stats(severity, len, truncated);
+    }
+
+    /// Record a message that is written in the stats, if collecting stats is enabled
+    fn record_stats(&self, severity: Severity, len: usize, truncated: bool) {
+        if let Some(stats) = &self.stats {
+            stats.record_message(severity, len);
+
+            if truncated {
+                stats.record_truncation();
+            }
+        }
+    }
+
+    /// Write the MSG, truncated so the message fits within [Config::max_len] if it's configured.
+    ///
+    /// The MSG is left out entirely if not even its prefix fits. Returns whether the MSG is truncated.
+    fn write_msg<W: io::Write>(
+        &self,
+        w: &mut CountingWriter<'_, W>,
+        msg: Msg<'_>,
+    ) -> io::Result<bool> {
+        let msg = self.prepare_msg(msg);
+
+        let Some(max_len) = self.max_len else {
+            write_msg_with_bom(w, msg, self.bom)?;
+            return Ok(false);
+        };
+
+        let prefix_len = match (&msg, self.bom) {
+            (Msg::NonUnicodeBytes(_), _) | (_, Bom::Omit) => 1,
+            (_, Bom::Write) => SPACED_BOM.len(),
+        };
+        let remaining = max_len.saturating_sub(w.len + usize::from(self.trailing_nul));
+        let remaining = if remaining > prefix_len { remaining } else { 0 };
+
+        let mut limit = LimitWriter {
+            inner: w,
+            remaining,
+            truncated: false,
+        };
+        write_msg_with_bom(&mut limit, msg, self.bom)?;
+
+        Ok(limit.truncated)
+    }
+
+    /// Write the NUL terminator if it's configured
+    fn write_trailer<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.trailing_nul {
+            w.write_all(b"\0")?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a header
+    #[cfg(feature = "std")]
+    pub fn write_header<'a, W, TS>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        let prio = Priority::new(self.facility, severity);
+        self.write_header_with_priority(w, prio, timestamp, msg_id)
+    }
+
+    /// Write a header using a raw PRI value, see [Formatter::write_with_priority]
+    #[cfg(feature = "std")]
+    pub fn write_header_with_priority<'a, W, TS>(
+        &self,
+        w: &mut W,
+        priority: Priority,
+        timestamp: TS,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        let mut w = CountingWriter::new(w);
+        self.write_header_fields(
+            &mut w,
+            priority,
+            timestamp.into(),
+            Overrides::default(),
+            msg_id,
+        )
+    }
+
+    /// Format a syslog 5424 message with structured data using the given hostname
+    /// instead of the hostname of the formatter, for this message only.
+    ///
+    /// This is useful for a relay that forwards messages from multiple origins.
+    /// The APP-NAME and PROCID of the formatter are kept. An empty hostname is written as the NILVALUE.
+    ///
+    /// Nothing is written if the hostname isn't valid, e.g. longer than 255 characters,
+    /// the error is of kind `InvalidInput` and wraps the [ConfigError].
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    /// Formatter::default().write_with_hostname(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "origin.example.com",
+    ///     "a relayed message",
+    ///     None,
+    ///     no_data,
+    /// );
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    #[cfg(feature = "std")]
+    pub fn write_with_hostname<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        hostname: &Hostname,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let overrides = Overrides {
+            hostname: Some(hostname),
+            ..Default::default()
+        };
+
+        self.write_message(
+            w,
+            Priority::new(self.facility, severity),
+            timestamp.into(),
+            overrides,
+            msg.into(),
+            msg_id,
+            data,
+        )
+    }
+
+    /// Write a header using the given hostname, see [Formatter::write_with_hostname]
+    #[cfg(feature = "std")]
+    pub fn write_header_with_hostname<'a, W, TS>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        hostname: &Hostname,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        let prio = Priority::new(self.facility, severity);
+        let overrides = Overrides {
+            hostname: Some(hostname),
+            ..Default::default()
+        };
+        let mut w = CountingWriter::new(w);
+        self.write_header_fields(&mut w, prio, timestamp.into(), overrides, msg_id)
+    }
+
+    /// Format a syslog 5424 message with structured data using the given PROCID
+    /// instead of the PROCID of the formatter, for this message only.
+    ///
+    /// This is useful for a relay or multiplexer that forwards messages of multiple processes,
+    /// without creating a formatter for each process. The HOSTNAME and APP-NAME of the formatter are kept.
+    /// An empty PROCID is written as the NILVALUE.
+    ///
+    /// A PROCID longer than 128 characters is handled according to [Config::proc_id_overflow].
+    /// Nothing is written if the PROCID isn't valid, the error is of kind `InvalidInput`
+    /// and wraps the [ConfigError].
+    ///
+    /// ```rust
+    /// use syslog_fmt::{Severity, v5424::Formatter};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+    /// Formatter::default().write_with_proc_id(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     "8710",
+    ///     "a message of process 8710",
+    ///     None,
+    ///     no_data,
+    /// );
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    #[cfg(feature = "std")]
+    pub fn write_with_proc_id<'a, W, TS, M, I, P>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        proc_id: &ProcId,
+        msg: M,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+        M: Into<Msg<'a>>,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let overrides = Overrides {
+            proc_id: Some(proc_id),
+            ..Default::default()
+        };
+
+        self.write_message(
+            w,
+            Priority::new(self.facility, severity),
+            timestamp.into(),
+            overrides,
+            msg.into(),
+            msg_id,
+            data,
+        )
+    }
+
+    /// Write a header using the given PROCID, see [Formatter::write_with_proc_id]
+    #[cfg(feature = "std")]
+    pub fn write_header_with_proc_id<'a, W, TS>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        timestamp: TS,
+        proc_id: &ProcId,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        TS: Into<Timestamp<'a>>,
+    {
+        let prio = Priority::new(self.facility, severity);
+        let overrides = Overrides {
+            proc_id: Some(proc_id),
+            ..Default::default()
+        };
+        let mut w = CountingWriter::new(w);
+        self.write_header_fields(&mut w, prio, timestamp.into(), overrides, msg_id)
+    }
+
+    /// Write a whole message and record it in the stats, the methods that write a whole message share this.
+    #[allow(clippy::too_many_arguments)]
+    fn write_message<'a, W, I, P>(
+        &self,
+        w: &mut W,
+        priority: Priority,
+        timestamp: Timestamp<'_>,
+        overrides: Overrides<'_>,
+        msg: Msg<'_>,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        let mut w = CountingWriter::new(w);
+        let truncated = self
+            .write_message_unrecorded(&mut w, priority, timestamp, overrides, msg, msg_id, data)?;
+        self.record_message(priority.severity(), w.len, truncated);
+        Ok(())
+    }
+
+    /// Write a whole message without recording it in the stats. Returns whether the MSG is truncated.
+    #[allow(clippy::too_many_arguments)]
+    fn write_message_unrecorded<'a, W, I, P>(
+        &self,
+        w: &mut CountingWriter<'_, W>,
+        priority: Priority,
+        timestamp: Timestamp<'_>,
+        overrides: Overrides<'_>,
+        msg: Msg<'_>,
+        msg_id: Option<&MsgId>,
+        data: I,
+    ) -> io::Result<bool>
+    where
+        W: io::Write,
+        I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+        P: IntoIterator<Item = SdParam<'a>> + 'a,
+    {
+        self.write_header_fields(w, priority, timestamp, overrides, msg_id)?;
+
+        // each field after the PRI is prefixed with a space, which isn't part of the field
+        let start = w.len + 1;
+        self.write_data(w, priority, data)?;
+        w.record_field(Field::StructuredData, start..w.len);
+
+        // an empty MSG isn't written, not even the space
+        let start = w.len;
+        let truncated = self.write_msg(w, msg)?;
+        w.record_field(Field::Msg, (start + 1).min(w.len)..w.len);
+
+        self.write_trailer(w)?;
+        Ok(truncated)
+    }
+
+    /// Write the header fields, recording the range of each field if the writer records them
+    fn write_header_fields<W: io::Write>(
+        &self,
+        w: &mut CountingWriter<'_, W>,
+        priority: Priority,
+        timestamp: Timestamp<'_>,
+        overrides: Overrides<'_>,
+        msg_id: Option<&MsgId>,
+    ) -> io::Result<()> {
+        let Self {
+            facility: _,
+            host_app_proc_id,
+            hostname_len,
+            app_name_len,
+            data: _,
+            trailing_nul: _,
+            pri_sd_id: _,
+            session: _,
+            whitespace_msg: _,
+            omit_version,
+            schema_elem: _,
+            max_severity: _,
+            bom: _,
+            hostname_trailing_dot,
+            proc_id_overflow,
+            stats: _,
+            max_len: _,
+            pre_epoch,
+        } = self;
+
+        fn or_nil(field: &str) -> &str {
+            if field.is_empty() {
+                NILVALUE
+            } else {
+                field
+            }
+        }
+
+        let Overrides {
+            hostname,
+            app_name,
+            proc_id,
+        } = overrides;
+
+        // the fields given per message and the timestamp aren't validated by the config,
+        // so they're checked before anything is written
+        let hostname = hostname.map(|hostname| or_nil(hostname_trailing_dot.apply(hostname)));
+        if let Some(hostname) = hostname {
+            validate_override(Field::Hostname, hostname, HOSTNAME_MAX_LEN)?;
+        }
+
+        let proc_id = proc_id.map(|proc_id| or_nil(proc_id_overflow.apply(proc_id)));
+        if let Some(proc_id) = proc_id {
+            validate_override(Field::ProcId, proc_id, PROC_ID_MAX_LEN)?;
+        }
+
+        let timestamp = pre_epoch.apply(timestamp)?;
+        let msg_id = msg_id.filter(|s| !s.is_empty()).unwrap_or(NILVALUE);
+
+        use io::Write as _;
+
+        let start = w.len;
+        write!(w, "<{priority}>")?;
+        w.record_field(Field::Pri, start..w.len);
+
+        // an omitted VERSION is an empty field and the TIMESTAMP directly follows the PRI
+        let start = w.len;
+        if !omit_version {
+            write!(w, "{VERSION}")?;
+        }
+        w.record_field(Field::Version, start..w.len);
+        if !omit_version {
+            write!(w, " ")?;
+        }
+
+        let start = w.len;
+        write_timestamp_field(w, timestamp)?;
+        w.record_field(Field::Timestamp, start..w.len);
+
+        let start = w.len + 1;
+        let (hostname_len, app_name_len) =
+            if hostname.is_none() && app_name.is_none() && proc_id.is_none() {
+                write!(w, " {host_app_proc_id}")?;
+                (*hostname_len, *app_name_len)
+            } else {
+                // the preformatted fields are separated by a single space
+                let app_name_start = hostname_len + 1;
+                let proc_id_start = app_name_start + app_name_len + 1;
+
+                let hostname = hostname.unwrap_or(&host_app_proc_id[..*hostname_len]);
+                let app_name =
+                    app_name.map_or(&host_app_proc_id[app_name_start..proc_id_start - 1], or_nil);
+                let proc_id = proc_id.unwrap_or(&host_app_proc_id[proc_id_start..]);
+
+                write!(w, " {hostname} {app_name} {proc_id}")?;
+                (hostname.len(), app_name.len())
+            };
+        let app_name_start = start + hostname_len + 1;
+        let proc_id_start = app_name_start + app_name_len + 1;
+        w.record_field(Field::Hostname, start..start + hostname_len);
+        w.record_field(
+            Field::AppName,
+            app_name_start..app_name_start + app_name_len,
+        );
+        w.record_field(Field::ProcId, proc_id_start..w.len);
+
+        let start = w.len + 1;
+        write!(w, " {msg_id}")?;
+        w.record_field(Field::MsgId, start..w.len);
+
+        Ok(())
+    }
+}
+
+/// The header fields that replace those of the formatter for a single message
+#[derive(Copy, Clone, Default)]
+struct Overrides<'o> {
+    hostname: Option<&'o Hostname>,
+    app_name: Option<&'o AppName>,
+    proc_id: Option<&'o ProcId>,
+}
+
+/// A formatted message with the byte range of each field, see [Formatter::format_annotated]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnotatedMessage {
+    /// The formatted message
+    pub bytes: Vec<u8>,
+    /// The byte range of each field within `bytes`, in the order they appear
+    pub fields: Vec<(Field, Range<usize>)>,
+}
+
+impl AnnotatedMessage {
+    /// The bytes of the given field
+    pub fn get(&self, field: Field) -> Option<&[u8]> {
+        self.fields
+            .iter()
+            .find(|(f, _)| *f == field)
+            .map(|(_, range)| &self.bytes[range.clone()])
+    }
+}
+
+/// A shared [Formatter] with a pool of scratch buffers for logging from many threads.
+///
+/// Cloning the pool is cheap, the clones share the formatter and the buffers.
+/// A buffer is taken from the pool by [FormatterPool::get] and returned when the
+/// [PooledFormatter] is dropped, so the buffers are reused instead of allocated per message.
+///
+/// ```rust
+/// use std::thread;
+///
+/// use syslog_fmt::{Severity, v5424::{Formatter, FormatterPool}};
+///
+/// let pool = FormatterPool::new(Formatter::default());
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|i| {
+///         let pool = pool.clone();
+///         thread::spawn(move || {
+///             let mut pooled = pool.get();
+///             let (formatter, buf) = pooled.split();
+///             formatter
+///                 .write_without_data(buf, Severity::Info, "-", format!("message {i}"), None)
+///                 .unwrap();
+///             // send the message in `buf`
+///         })
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct FormatterPool {
+    inner: Arc<PoolInner>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct PoolInner {
+    formatter: Formatter,
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+#[cfg(feature = "std")]
+impl FormatterPool {
+    pub fn new(formatter: Formatter) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                formatter,
+                buffers: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    /// The shared formatter
+    pub fn formatter(&self) -> &Formatter {
+        &self.inner.formatter
+    }
+
+    /// Take an empty scratch buffer from the pool, a new buffer is created if none is available
+    pub fn get(&self) -> PooledFormatter<'_> {
+        let buf = self
+            .inner
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_default();
+
+        PooledFormatter { pool: self, buf }
+    }
+}
+
+/// The shared formatter and a scratch buffer taken from a [FormatterPool].
+///
+/// The buffer is cleared and returned to the pool on drop.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct PooledFormatter<'p> {
+    pool: &'p FormatterPool,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<'p> PooledFormatter<'p> {
+    /// The shared formatter and the scratch buffer to format into
+    pub fn split(&mut self) -> (&'p Formatter, &mut Vec<u8>) {
+        (self.pool.formatter(), &mut self.buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'p> Drop for PooledFormatter<'p> {
+    fn drop(&mut self) {
+        let mut buf = core::mem::take(&mut self.buf);
+        buf.clear();
+
+        self.pool
+            .inner
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(buf);
+    }
+}
+
+/// A deviation from the spec found by [Formatter::format_and_validate]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// A header field is missing or doesn't conform
+    Field(ConfigError),
+    /// An SD-ID doesn't conform
+    SdId(SdError),
+    /// The MSG is written without a BOM, so it isn't known to be UTF-8
+    BomOmitted,
+    /// The MSG is not valid UTF-8
+    NonUtf8Msg,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Field(e) => e.fmt(f),
+            Warning::SdId(e) => write!(f, "Invalid SD-ID: {e}"),
+            Warning::BomOmitted => f.write_str("The MSG has no BOM"),
+            Warning::NonUtf8Msg => f.write_str("The MSG is not valid UTF-8"),
+        }
+    }
+}
+
+/// Write the TIMESTAMP field, without a space prefixed.
+///
+/// The `Timestamp::None` variant is written as the NILVALUE.
+/// Use this to build a custom layout from the individual fields of a message.
+///
+/// ```rust
+/// use syslog_fmt::v5424::{self, Timestamp};
+///
+/// let mut buf = Vec::<u8>::new();
+/// v5424::write_timestamp(&mut buf, "2003-10-11T22:14:15.003Z").unwrap();
+/// assert_eq!(buf, b"2003-10-11T22:14:15.003Z");
+///
+/// buf.clear();
+/// v5424::write_timestamp(&mut buf, Timestamp::None).unwrap();
+/// assert_eq!(buf, b"-");
+/// ```
+#[cfg(feature = "std")]
+pub fn write_timestamp<'a, W, TS>(w: &mut W, timestamp: TS) -> io::Result<()>
+where
+    W: io::Write,
+    TS: Into<Timestamp<'a>>,
+{
+    write_timestamp_field(w, timestamp.into())
+}
+
+/// Write the TIMESTAMP field, see [write_timestamp]
+fn write_timestamp_field<W: io::Write>(w: &mut W, timestamp: Timestamp<'_>) -> io::Result<()> {
+    match timestamp {
+        #[cfg(feature = "chrono")]
+        Timestamp::Chrono(datetime) => write_chrono_datetime(w, datetime),
+        #[cfg(feature = "chrono")]
+        Timestamp::CreateChronoLocal => {
+            let datetime = chrono::Local::now();
+            write_chrono_datetime(w, &datetime)
+        }
+        #[cfg(feature = "chrono")]
+        Timestamp::ChronoUtc(datetime) => write_chrono_utc_datetime(w, datetime),
+        #[cfg(feature = "chrono")]
+        Timestamp::CreateChronoUtc => write_chrono_utc_datetime(w, &chrono::Utc::now()),
+        #[cfg(feature = "time")]
+        Timestamp::Time(datetime) => write_time_datetime(w, datetime),
+        #[cfg(feature = "time")]
+        Timestamp::CreateTimeLocal => {
+            let datetime = time::OffsetDateTime::now_local()
+                .unwrap_or_else(|_err| time::OffsetDateTime::now_utc());
+            write_time_datetime(w, &datetime)
+        }
+        #[cfg(feature = "time")]
+        Timestamp::CreateTimeUtc => write_time_datetime(w, &time::OffsetDateTime::now_utc()),
+        #[cfg(feature = "std")]
+        Timestamp::CreateSystemNow => write_system_time(w, SystemTime::now()),
+        #[cfg(feature = "std")]
+        Timestamp::SystemTime(time) => write_system_time(w, time),
+        #[cfg(feature = "std")]
+        Timestamp::Deferred(clock) => write_system_time(w, clock.now()),
+        Timestamp::Epoch { secs, nanos } => {
+            write_unix_datetime(w, secs, nanos.min(NANOS_IN_SEC - 1))
+        }
+        Timestamp::Components {
+            year,
+            month,
+            day,
+            hour,
+            min,
+            sec,
+            nanos,
+            offset_minutes,
+        } => {
+            let micros = nanos / MICRO_IN_NANO;
+            write!(
+                w,
+                "{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}.{micros:06}"
+            )?;
+            write_utc_offset(w, offset_minutes)
+        }
+        Timestamp::PreformattedStr(s) => w.write_all(s.as_bytes()),
+        Timestamp::PreformattedString(s) => w.write_all(s.as_bytes()),
+        Timestamp::None => w.write_all(NILVALUE.as_bytes()),
+    }
+}
+
+/// Write structured data with a space prefixed
+///
+/// STRUCTURED-DATA provides a mechanism to express information in a well
+/// defined, easily parseable and interpretable data format. There are
+/// multiple usage scenarios. For example, it may express meta-
+/// information about the syslog message or application-specific
+/// information such as traffic counters or IP addresses.
+///
+/// STRUCTURED-DATA can contain zero, one, or multiple structured data
+/// elements, SD-ELEMENT.
+///
+/// In case of zero structured data elements, the STRUCTURED-DATA field
+/// MUST contain the NILVALUE.
+///
+/// The character set used in STRUCTURED-DATA MUST be seven-bit ASCII in
+/// an eight-bit field as described in [RFC5234](https://datatracker.ietf.org/doc/html/rfc5234).
+/// These are the ASCII codes as defined in "USA Standard Code for Information Interchange"
+/// [ANSI.X3-4.1968](https://datatracker.ietf.org/doc/html/rfc5424#ref-ANSI.X3-4.1968).
+/// An exception is the PARAM-VALUE field, in which UTF-8 encoding MUST be used.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3)
+///
+/// An SD-ELEMENT consists of a name and parameter name-value pairs. The
+/// name is referred to as SD-ID. The name-value pairs are referred to
+/// as SD-PARAM.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.1)
+#[cfg(feature = "std")]
+pub fn write_data<'a, W, I, P>(w: &mut W, data: I) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    write_data_with_config(w, data, &DataConfig::default())
+}
+
+/// Write structured data with a space prefixed using the given config.
+///
+/// See [write_data] for details on structured data.
+#[cfg(feature = "std")]
+pub fn write_data_with_config<'a, W, I, P>(
+    w: &mut W,
+    data: I,
+    config: &DataConfig,
+) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    write_structured_data(w, data, config)
+}
+
+/// Write structured data with a space prefixed, see [write_data_with_config]
+fn write_structured_data<'a, W, I, P>(w: &mut W, data: I, config: &DataConfig) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    if !write_data_elems(w, data, config, true)? {
+        write!(w, " {NILVALUE}")?;
+    }
+
+    Ok(())
+}
+
+/// Write the SD-ELEMENTs, skipping the empty elements if configured.
+///
+/// The first element written is prefixed with a space if `space_prefix` is true.
+/// Returns whether any element was written.
+fn write_data_elems<'a, W, I, P>(
+    w: &mut W,
+    data: I,
+    config: &DataConfig,
+    mut space_prefix: bool,
+) -> io::Result<bool>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    let mut written = 0;
+    let mut dropped = 0;
+
+    for (id, params) in data {
+        let mut params = params.into_iter().peekable();
+
+        if config.empty_elements == EmptyElements::Suppress && params.peek().is_none() {
+            continue;
+        }
+
+        if config.max_elements.is_some_and(|max| written >= max.max) {
+            dropped += 1;
+            continue;
+        }
+
+        if space_prefix {
+            write!(w, " ")?;
+            space_prefix = false;
+        }
+
+        write_data_elem(w, (id, params), config)?;
+        written += 1;
+    }
+
+    if let Some(max) = config.max_elements.filter(|_| dropped > 0) {
+        if space_prefix {
+            write!(w, " ")?;
+        }
+
+        write!(w, "[{} dropped=\"{dropped}\"]", max.summary_sd_id)?;
+        written += 1;
+    }
+
+    Ok(written > 0)
+}
+
+/// The number of bytes [write_data] will write for the given structured data.
+///
+/// The length includes the space prefix and can be used to budget the size of a message
+/// before writing it. The data is consumed, so pass a clone or a fresh iterator
+/// if the same data is to be written afterwards.
+///
+/// ```rust
+/// use syslog_fmt::v5424;
+///
+/// let data = [("exampleSDID@32473", [("iut", "3")])];
+/// let mut buf = Vec::<u8>::new();
+/// v5424::write_data(&mut buf, data).unwrap();
+///
+/// assert_eq!(v5424::structured_data_len(data), buf.len());
+/// ```
+pub fn structured_data_len<'a, I, P>(data: I) -> usize
+where
+    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    let mut counter = ByteCounter::default();
+    // writing to a `ByteCounter` never fails
+    let _ = write_structured_data(&mut counter, data, &DataConfig::default());
+    counter.len
+}
+
+fn write_data_elem<'a, W, P>(w: &mut W, elem: (&'a SdId, P), config: &DataConfig) -> io::Result<()>
+where
+    W: io::Write,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    let (id, params) = elem;
+
+    write!(w, "[")?;
+    write_sd_name(w, id, config)?;
+
+    for param in params {
+        let (name, value) = param;
+        write!(w, " ")?;
+        write_sd_name(w, name, config)?;
+        write!(w, "=\"")?;
+        write_param_value(w, value, config)?;
+        write!(w, "\"")?;
+    }
+
+    write!(w, "]")
+}
+
+/// Write an SD-ID or PARAM-NAME, handling the non-ASCII characters as configured.
+fn write_sd_name<W: io::Write>(w: &mut W, name: &str, config: &DataConfig) -> io::Result<()> {
+    if name.is_ascii() {
+        return w.write_all(name.as_bytes());
+    }
+
+    match config.non_ascii_names {
+        NonAsciiNames::Passthrough => w.write_all(name.as_bytes()),
+        NonAsciiNames::Reject => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The SD-ID or PARAM-NAME {name:?} contains non-ASCII characters"),
+        )),
+        NonAsciiNames::Replace => {
+            for ch in name.chars() {
+                let ch = if ch.is_ascii() { ch } else { '_' };
+                write!(w, "{ch}")?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Write a PARAM-VALUE, modifying the control characters as configured.
+fn write_param_value<W: io::Write>(w: &mut W, value: &str, config: &DataConfig) -> io::Result<()> {
+    let escape_control_chars = config.control_chars == ControlChars::Escape;
+    let bytes = value.as_bytes();
+    let mut start = 0;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        if is_param_value_escape(b) {
+            w.write_all(&bytes[start..i])?;
+            w.write_all(&[b'\\', b])?;
+            start = i + 1;
+        } else if escape_control_chars && b.is_ascii_control() {
+            w.write_all(&bytes[start..i])?;
+            write!(w, "#{b:03}")?;
+            start = i + 1;
+        }
+    }
+
+    w.write_all(&bytes[start..])
+}
+
+/// Whether a byte of a PARAM-VALUE must be escaped with a backslash.
+///
+/// The characters '"' (ABNF %d34), '\' (ABNF %d92), and ']' (ABNF %d93) MUST be escaped.
+/// A backslash is always escaped, also when it's followed by none of these characters.
+/// The receiver treats such an invalid escape sequence as a regular backslash, but escaping it
+/// keeps the value unambiguous, e.g. `C:\dir` is written as `C:\\dir`.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.3)
+fn is_param_value_escape(b: u8) -> bool {
+    matches!(b, b'"' | b'\\' | b']')
+}
+
+/// Options for writing structured data
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DataConfig {
+    pub control_chars: ControlChars,
+    pub empty_elements: EmptyElements,
+    pub max_elements: Option<MaxElements>,
+    pub non_ascii_names: NonAsciiNames,
+}
+
+/// The maximum number of SD-ELEMENTs written in a message, this bounds the size of the message.
+///
+/// The elements beyond the maximum are dropped and a summary element is appended,
+/// e.g. `[truncated@32473 dropped="3"]`, to signal the loss to the collector.
+/// The summary element isn't counted towards the maximum.
+///
+/// ```rust
+/// use syslog_fmt::v5424::{self, DataConfig, MaxElements};
+///
+/// let config = DataConfig {
+///     max_elements: Some(MaxElements {
+///         max: 1,
+///         summary_sd_id: "truncated@32473",
+///     }),
+///     ..Default::default()
+/// };
+///
+/// let mut buf = Vec::<u8>::new();
+/// v5424::write_data_with_config(
+///     &mut buf,
+///     [("first@32473", [("n", "1")]), ("second@32473", [("n", "2")])],
+///     &config,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(buf, br#" [first@32473 n="1"][truncated@32473 dropped="1"]"#);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct MaxElements {
+    /// The maximum number of elements written before the summary element
+    pub max: usize,
+    /// The SD-ID of the summary element, with a `dropped` param holding the number of dropped elements
+    pub summary_sd_id: &'static SdId,
+}
+
+/// How an SD-ELEMENT without params, e.g. `[exampleSDID@32473]`, is written.
+///
+/// An element without params is valid, but some collectors don't handle it well.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EmptyElements {
+    /// Write the element with only its SD-ID
+    #[default]
+    Keep,
+    /// Leave the element out, the NILVALUE is written if no element is left
+    Suppress,
+}
+
+/// How control characters within a PARAM-VALUE are written.
+///
+/// The syslog application MUST NOT fail if control characters are present in PARAM-VALUE.
+/// The syslog application MAY modify messages containing control characters
+/// (e.g., by changing an octet with value 0 (USASCII NUL) to the four characters "#000").
+///
+/// Some collectors mangle control characters, escaping them keeps the value intact.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.3)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ControlChars {
+    /// Write the control characters unmodified
+    #[default]
+    Passthrough,
+    /// Write each control character as a '#' followed by its three digit decimal value, e.g. NUL becomes "#000"
+    Escape,
+}
+
+/// How non-ASCII characters in an SD-ID or PARAM-NAME are handled.
+///
+/// Except for the PARAM-VALUE, the structured data MUST be 7-bit ASCII.
+/// A PARAM-VALUE is UTF-8 and is always written as is.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum NonAsciiNames {
+    /// Write the names unmodified
+    #[default]
+    Passthrough,
+    /// Fail the write with an error of kind `InvalidInput`
+    Reject,
+    /// Write each non-ASCII character as an underscore, e.g. `café` becomes `caf_`
+    Replace,
+}
+
+/// Append the SD-ELEMENT of a relay to the structured data of a message that is being re-emitted.
+///
+/// A relay that forwards a message MAY record its own details in an SD-ELEMENT with a private SD-ID,
+/// e.g. `relay@32473`. Don't use the "origin" SD-ID for this, it describes the originator of the message.
+/// The same SD-ID MUST NOT exist more than once in a message, so an incoming element with the same
+/// SD-ID as the relay element is dropped and replaced by the relay element.
+/// This keeps the relay information of the last hop when a message passes through multiple relays.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-7.2)
+///
+/// ```rust
+/// use syslog_fmt::{Severity, v5424::{self, Formatter}};
+///
+/// let mut buf = Vec::<u8>::new();
+/// let data = [
+///     ("origin", vec![("ip", "192.0.2.1")]),
+///     ("relay@32473", vec![("ip", "192.0.2.2")]),
+/// ];
+/// let relay = ("relay@32473", vec![("ip", "192.0.2.3"), ("software", "relay")]);
+///
+/// Formatter::default().write_with_data(
+///     &mut buf,
+///     Severity::Info,
+///     "2003-10-11T22:14:15.003Z",
+///     "this is a message",
+///     None,
+///     v5424::append_relay_data(data, relay),
+/// )
+/// .unwrap();
+///
+/// let msg = v5424::parse(&buf).unwrap();
+/// assert_eq!(
+///     msg.structured_data,
+///     Some(r#"[origin ip="192.0.2.1"][relay@32473 ip="192.0.2.3" software="relay"]"#)
+/// );
+/// ```
+pub fn append_relay_data<'a, I, P>(
+    data: I,
+    relay: (&'a SdId, P),
+) -> impl Iterator<Item = (&'a SdId, P)> + 'a
+where
+    I: IntoIterator<Item = (&'a SdId, P)> + 'a,
+    I::IntoIter: 'a,
+    P: IntoIterator<Item = SdParam<'a>> + 'a,
+{
+    let (relay_id, _) = relay;
+
+    data.into_iter()
+        .filter(move |(id, _)| *id != relay_id)
+        .chain(core::iter::once(relay))
+}
+
+/// Limit the number of params of an SD-ELEMENT to `max`, dropping the params beyond it as given by the policy.
+///
+/// This protects the size of a message when the params come from an unbounded source.
+/// The length of the params must be known upfront, so no allocation is needed.
+///
+/// ```rust
+/// use syslog_fmt::v5424::{self, DropPolicy};
+///
+/// let params = [("a", "1"), ("b", "2"), ("c", "3")];
+/// let data = [("exampleSDID@32473", v5424::cap_params(params, 2, DropPolicy::Oldest))];
+///
+/// let mut buf = Vec::<u8>::new();
+/// v5424::write_data(&mut buf, data).unwrap();
+/// assert_eq!(buf, br#" [exampleSDID@32473 b="2" c="3"]"#);
+/// ```
+pub fn cap_params<'a, P>(
+    params: P,
+    max: usize,
+    policy: DropPolicy,
+) -> impl ExactSizeIterator<Item = SdParam<'a>>
+where
+    P: IntoIterator<Item = SdParam<'a>>,
+    P::IntoIter: ExactSizeIterator,
+{
+    let params = params.into_iter();
+
+    let skip = match policy {
+        DropPolicy::Oldest => params.len().saturating_sub(max),
+        DropPolicy::Newest => 0,
+    };
+
+    params.skip(skip).take(max)
+}
+
+/// Which params are dropped by [cap_params] when there are too many
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Drop the first params, keeping the last ones
+    #[default]
+    Oldest,
+    /// Drop the last params, keeping the first ones
+    Newest,
+}
+
+/// Assemble STRUCTURED-DATA in which each SD-ID occurs only once, as required by the spec.
+///
+/// The elements are written in the order they are added. Adding an element with an SD-ID
+/// that was already added returns a [DuplicateSdId] error and keeps the first element.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2)
+///
+/// ```rust
+/// use syslog_fmt::v5424::{self, StructuredDataBuilder};
+///
+/// let mut builder = StructuredDataBuilder::new();
+/// builder.add_element("exampleSDID@32473", [("iut", "3")]).unwrap();
+/// builder.add_element("examplePriority@32473", [("class", "high")]).unwrap();
+/// assert!(builder.add_element("exampleSDID@32473", [("iut", "4")]).is_err());
+///
+/// let mut buf = Vec::<u8>::new();
+/// v5424::write_data(&mut buf, builder.finish()).unwrap();
+/// assert_eq!(buf, br#" [exampleSDID@32473 iut="3"][examplePriority@32473 class="high"]"#);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StructuredDataBuilder<'a> {
+    elements: Vec<(&'a SdId, Vec<SdParam<'a>>)>,
+}
+
+impl<'a> StructuredDataBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an SD-ELEMENT, unless an element with the same SD-ID was already added
+    pub fn add_element<P>(&mut self, id: &'a SdId, params: P) -> Result<&mut Self, DuplicateSdId>
+    where
+        P: IntoIterator<Item = SdParam<'a>>,
+    {
+        if self.elements.iter().any(|(added, _)| *added == id) {
+            return Err(DuplicateSdId {
+                sd_id: id.to_owned(),
+            });
+        }
+
+        self.elements.push((id, params.into_iter().collect()));
+        Ok(self)
+    }
+
+    /// The elements in the order they were added, to be passed as the data of a message
+    pub fn finish(self) -> Vec<(&'a SdId, Vec<SdParam<'a>>)> {
+        self.elements
+    }
+}
+
+/// Error returned if an SD-ID is added to a [StructuredDataBuilder] more than once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateSdId {
+    /// The SD-ID that was already added
+    pub sd_id: String,
+}
+
+impl fmt::Display for DuplicateSdId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The SD-ID {:?} was already added to the structured data",
+            self.sd_id
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DuplicateSdId {}
+
+/// Write structured data one SD-ELEMENT and SD-PARAM at a time, directly to the writer.
+///
+/// This suits data that's discovered incrementally, e.g. by a visitor, without collecting it first.
+/// Like [write_data] the structured data is prefixed with a space and [SdWriter::finish]
+/// writes the NILVALUE if no element was written.
+///
+/// The `empty_elements` and `max_elements` options of the [DataConfig] aren't applied,
+/// as each element is written when it's begun.
+///
+/// ```rust
+/// use syslog_fmt::v5424::SdWriter;
+///
+/// let mut buf = Vec::<u8>::new();
+/// let mut sd = SdWriter::new(&mut buf);
+/// sd.begin_element("exampleSDID@32473").unwrap();
+/// sd.param("iut", "3").unwrap();
+/// sd.param("eventSource", "Application").unwrap();
+/// sd.end_element().unwrap();
+/// sd.finish().unwrap();
+///
+/// assert_eq!(buf, br#" [exampleSDID@32473 iut="3" eventSource="Application"]"#);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct SdWriter<'w, W> {
+    w: &'w mut W,
+    config: DataConfig,
+    in_element: bool,
+    written: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'w, W: io::Write> SdWriter<'w, W> {
+    pub fn new(w: &'w mut W) -> Self {
+        Self::with_config(w, &DataConfig::default())
+    }
+
+    pub fn with_config(w: &'w mut W, config: &DataConfig) -> Self {
+        Self {
+            w,
+            config: *config,
+            in_element: false,
+            written: false,
+        }
+    }
+
+    /// Open an SD-ELEMENT with the given SD-ID
+    pub fn begin_element(&mut self, id: &SdId) -> io::Result<()> {
+        if self.in_element {
+            return Err(sd_writer_misuse("The previous SD-ELEMENT isn't ended"));
+        }
+
+        let prefix = if self.written { "[" } else { " [" };
+        self.w.write_all(prefix.as_bytes())?;
+        write_sd_name(self.w, id, &self.config)?;
+        self.in_element = true;
+        self.written = true;
+        Ok(())
+    }
+
+    /// Write an SD-PARAM to the open SD-ELEMENT, the value is escaped
+    pub fn param(&mut self, name: ParamName<'_>, value: ParamValue<'_>) -> io::Result<()> {
+        if !self.in_element {
+            return Err(sd_writer_misuse(
+                "The SD-PARAM is written outside an SD-ELEMENT",
+            ));
+        }
+
+        write!(self.w, " ")?;
+        write_sd_name(self.w, name, &self.config)?;
+        write!(self.w, "=\"")?;
+        write_param_value(self.w, value, &self.config)?;
+        write!(self.w, "\"")
+    }
+
+    /// Close the open SD-ELEMENT
+    pub fn end_element(&mut self) -> io::Result<()> {
+        if !self.in_element {
+            return Err(sd_writer_misuse("There is no SD-ELEMENT to end"));
+        }
+
+        self.in_element = false;
+        write!(self.w, "]")
+    }
+
+    /// End the structured data, the NILVALUE is written if no element was written
+    pub fn finish(self) -> io::Result<()> {
+        if self.in_element {
+            return Err(sd_writer_misuse("The last SD-ELEMENT isn't ended"));
+        }
+
+        if !self.written {
+            write!(self.w, " {NILVALUE}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn sd_writer_misuse(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Iterate over each SD-ELEMENT, including the brackets, at the start of the STRUCTURED-DATA of a message.
+///
+/// A `]` or `"` within a PARAM-VALUE is escaped with a `\` and doesn't end the element or value.
+/// The iteration stops at the first byte that doesn't start an element, e.g. the space before the MSG.
+/// See [SdElements::remainder] for the part of the input after the elements.
+///
+/// ```rust
+/// use syslog_fmt::v5424;
+///
+/// let data = r#"[exampleSDID@32473 iut="3" note="a \] in a value"][examplePriority@32473 class="high"] the msg"#;
+/// let mut elems = v5424::sd_elements(data);
+///
+/// assert_eq!(elems.next(), Some(r#"[exampleSDID@32473 iut="3" note="a \] in a value"]"#));
+/// assert_eq!(elems.next(), Some(r#"[examplePriority@32473 class="high"]"#));
+/// assert_eq!(elems.next(), None);
+/// assert_eq!(elems.remainder(), " the msg");
+/// ```
+pub fn sd_elements(data: &str) -> SdElements<'_> {
+    SdElements { rest: data }
+}
+
+/// An iterator over the SD-ELEMENTs of STRUCTURED-DATA, see [sd_elements]
+#[derive(Clone, Debug)]
+pub struct SdElements<'a> {
+    rest: &'a str,
+}
+
+impl<'a> SdElements<'a> {
+    /// The part of the input that hasn't been yielded as an element.
+    ///
+    /// After the iteration ends this is the part following the STRUCTURED-DATA,
+    /// or the start of an element that isn't closed.
+    pub fn remainder(&self) -> &'a str {
+        self.rest
+    }
+}
+
+impl<'a> Iterator for SdElements<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.rest.starts_with('[') {
+            return None;
+        }
+
+        let mut in_value = false;
+        let mut escaped = false;
+
+        // the delimiters are ASCII, so they never match a byte within a multi-byte UTF-8 char
+        for (i, b) in self.rest.bytes().enumerate() {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' if in_value => escaped = true,
+                b'"' => in_value = !in_value,
+                b']' if !in_value => {
+                    let (elem, rest) = self.rest.split_at(i + 1);
+                    self.rest = rest;
+                    return Some(elem);
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+/// Parse a single unframed syslog 5424 message into its fields, borrowed from the input.
+///
+/// A header field with the NILVALUE is `None`. The MSG may be any bytes, the other fields must be UTF-8.
+/// A `]` or `"` that is escaped within a PARAM-VALUE doesn't end the SD-ELEMENT, see [sd_elements].
+///
+/// ```rust
+/// use syslog_fmt::{Facility, Severity, v5424};
+///
+/// let input = br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3"] An event"#;
+/// let message = v5424::parse(input).unwrap();
+///
+/// assert_eq!(message.priority.facility().unwrap(), Facility::Local4);
+/// assert_eq!(message.priority.severity(), Severity::Notice);
+/// assert_eq!(message.hostname, Some("mymachine.example.com"));
+/// assert_eq!(message.proc_id, None);
+/// assert_eq!(message.structured_data, Some(r#"[exampleSDID@32473 iut="3"]"#));
+/// assert_eq!(message.msg_str(), Some("An event"));
+/// ```
+pub fn parse(input: &[u8]) -> Result<Message<'_>, ParseError> {
+    let pri_error = ParseError { field: Field::Pri };
+
+    let rest = input.strip_prefix(b"<").ok_or(pri_error)?;
+    let end = rest
+        .iter()
+        .position(|b| *b == b'>')
+        .filter(|end| (1..=3).contains(end))
+        .ok_or(pri_error)?;
+    let priority = parse_digits(&rest[..end])
+        .and_then(|priority| u8::try_from(priority).ok())
+        .and_then(|priority| Priority::try_from(priority).ok())
+        .ok_or(pri_error)?;
+    let mut rest = &rest[end + 1..];
+
+    let version = parse_header_field(&mut rest, Field::Version)?;
+    let version = Some(version.as_bytes())
+        .filter(|version| version.len() <= 3 && !version.starts_with(b"0"))
+        .and_then(parse_digits)
+        .ok_or(ParseError {
+            field: Field::Version,
+        })?;
+
+    let mut nil_or_field = |field| {
+        parse_header_field(&mut rest, field).map(|value| Some(value).filter(|v| *v != NILVALUE))
+    };
+    let timestamp = nil_or_field(Field::Timestamp)?;
+    let hostname = nil_or_field(Field::Hostname)?;
+    let app_name = nil_or_field(Field::AppName)?;
+    let proc_id = nil_or_field(Field::ProcId)?;
+    let msg_id = nil_or_field(Field::MsgId)?;
+
+    let data_error = ParseError {
+        field: Field::StructuredData,
+    };
+
+    let (structured_data, rest) = if let Some(rest) = rest.strip_prefix(NILVALUE.as_bytes()) {
+        (None, rest)
+    } else {
+        // the STRUCTURED-DATA is UTF-8, only the MSG that follows it may be any bytes
+        let valid = match core::str::from_utf8(rest) {
+            Ok(valid) => valid,
+            Err(e) => core::str::from_utf8(&rest[..e.valid_up_to()]).unwrap_or_default(),
+        };
+
+        let mut elems = sd_elements(valid);
+        if elems.by_ref().count() == 0 {
+            return Err(data_error);
+        }
+
+        let (data, _) = valid.split_at(valid.len() - elems.remainder().len());
+        (Some(data), &rest[data.len()..])
+    };
+
+    let msg = match rest {
+        [] | [SPACE_BYTE] => None,
+        [SPACE_BYTE, msg @ ..] => Some(msg),
+        _ => return Err(data_error),
+    };
+
+    Ok(Message {
+        priority,
+        version,
+        timestamp,
+        hostname,
+        app_name,
+        proc_id,
+        msg_id,
+        structured_data,
+        msg,
+    })
+}
+
+/// Split off a header field and the space that follows it.
+///
+/// The field must consist of PRINTUSASCII characters.
+fn parse_header_field<'a>(rest: &mut &'a [u8], field: Field) -> Result<&'a str, ParseError> {
+    let end = rest
+        .iter()
+        .position(|b| *b == SPACE_BYTE)
+        .ok_or(ParseError { field })?;
+    let (value, tail) = rest.split_at(end);
+
+    let value = core::str::from_utf8(value)
+        .ok()
+        .filter(|value| !value.is_empty() && value.chars().all(is_print_us_ascii))
+        .ok_or(ParseError { field })?;
+    *rest = &tail[1..];
+
+    Ok(value)
+}
+
+/// Parse a non-empty sequence of ASCII digits
+fn parse_digits(digits: &[u8]) -> Option<u16> {
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    core::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// A syslog 5424 message split into its fields by [parse]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Message<'a> {
+    pub priority: Priority,
+    pub version: u16,
+    pub timestamp: Option<&'a str>,
+    pub hostname: Option<&'a str>,
+    pub app_name: Option<&'a str>,
+    pub proc_id: Option<&'a str>,
+    pub msg_id: Option<&'a str>,
+    /// The SD-ELEMENTs including the brackets, e.g. `[exampleSDID@32473 iut="3"][examplePriority@32473 class="high"]`
+    pub structured_data: Option<&'a str>,
+    /// The MSG including the BOM, if any
+    pub msg: Option<&'a [u8]>,
+}
+
+impl<'a> Message<'a> {
+    /// Iterate over the SD-ELEMENTs of the STRUCTURED-DATA
+    pub fn sd_elements(&self) -> SdElements<'a> {
+        sd_elements(self.structured_data.unwrap_or_default())
+    }
+
+    /// The MSG as a string without the BOM, `None` if there's no MSG or it isn't UTF-8
+    pub fn msg_str(&self) -> Option<&'a str> {
+        let msg = core::str::from_utf8(self.msg?).ok()?;
+        Some(msg.strip_prefix('\u{feff}').unwrap_or(msg))
+    }
+}
+
+/// Error returned by [parse] if a message is malformed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The first field that is missing or malformed
+    pub field: Field,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The {} is missing or malformed", self.field)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// Check that the STRUCTURED-DATA of a message is a NILVALUE or a sequence of well formed SD-ELEMENTs.
+///
+/// Returns the reason when the data is malformed.
+#[cfg(all(debug_assertions, feature = "std"))]
+fn check_structured_data(data: &str) -> Result<(), &'static str> {
+    if data == NILVALUE {
+        return Ok(());
+    }
+
+    let mut elems = sd_elements(data);
+
+    for elem in elems.by_ref() {
+        check_sd_element(elem)?;
+    }
+
+    // the SD-ELEMENTs are directly adjacent, e.g. `[a][b]`
+    let rest = elems.remainder();
+    if rest.starts_with(char::is_whitespace) && rest.trim_start().starts_with('[') {
+        return Err("an SD-ELEMENT is preceded by whitespace");
+    }
+
+    if !rest.is_empty() {
+        return Err("the structured data doesn't end with a complete SD-ELEMENT");
+    }
+
+    Ok(())
+}
+
+/// Check a single SD-ELEMENT including the brackets, e.g. `[exampleSDID@32473 iut="3"]`
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3)
+#[cfg(all(debug_assertions, feature = "std"))]
+fn check_sd_element(elem: &str) -> Result<(), &'static str> {
+    let elem = elem
+        .strip_prefix('[')
+        .and_then(|elem| elem.strip_suffix(']'))
+        .ok_or("an SD-ELEMENT isn't enclosed in brackets")?;
+
+    let (id, mut rest) = elem.split_at(elem.find(' ').unwrap_or(elem.len()));
+    validate_sd_id(id).map_err(|_err| "an SD-ID is invalid")?;
+
+    while !rest.is_empty() {
+        let param = rest
+            .strip_prefix(' ')
+            .ok_or("an SD-PARAM isn't preceded by a space")?;
+        let (name, value) = param.split_once("=\"").ok_or("an SD-PARAM has no value")?;
+        validate_sd_name(name).map_err(|_err| "a PARAM-NAME is invalid")?;
+
+        let mut escaped = false;
+        let mut end = None;
+
+        for (i, b) in value.bytes().enumerate() {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b']' => return Err("a PARAM-VALUE contains an unescaped ']'"),
+                b'"' => {
+                    end = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let end = end.ok_or("a PARAM-VALUE isn't closed")?;
+        rest = &value[end + 1..];
+    }
+
+    Ok(())
+}
+
+/// A type that converts into a single SD-ELEMENT, with each param value formatted as a string.
+///
+/// With the `derive` feature enabled this trait can be derived for structs with named fields,
+/// each field that isn't skipped becomes a param.
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use syslog_fmt::v5424::IntoStructuredData;
+///
+/// #[derive(IntoStructuredData)]
+/// #[sd(id = "order@32473")]
+/// struct Order {
+///     id: u32,
+///     #[sd(rename = "customerName")]
+///     customer: String,
+///     #[sd(skip)]
+///     notes: String,
+/// }
+///
+/// let order = Order {
+///     id: 7,
+///     customer: "lonvick".into(),
+///     notes: "not logged".into(),
+/// };
+/// let mut buf = Vec::<u8>::new();
+/// order.write_sd_element(&mut buf).unwrap();
+///
+/// assert_eq!(buf, br#" [order@32473 id="7" customerName="lonvick"]"#);
+/// # }
+/// ```
+pub trait IntoStructuredData {
+    /// The SD-ID of the element
+    fn sd_id(&self) -> &'static SdId;
+
+    /// The PARAM-NAME and PARAM-VALUE pairs of the element
+    fn sd_params(&self) -> Vec<(&'static str, String)>;
+
+    /// Write the element as the structured data with a space prefixed, see [write_data]
+    #[cfg(feature = "std")]
+    fn write_sd_element<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+        Self: Sized,
+    {
+        let params = self.sd_params();
+        let params = params.iter().map(|(name, value)| (*name, value.as_str()));
+
+        write_data(w, [(self.sd_id(), params)])
+    }
+}
+
+#[cfg(feature = "derive")]
+pub use syslog_fmt_derive::IntoStructuredData;
+
+/// Write a msg with a space prefixed
+#[cfg(feature = "std")]
+pub fn write_msg<'a, W, M>(w: &mut W, msg: M) -> io::Result<()>
+where
+    W: io::Write,
+    M: Into<Msg<'a>>,
+{
+    write_msg_with_bom(w, msg, Bom::Write)
+}
+
+/// Write a msg with a space prefixed, prefixing a UTF-8 msg with the BOM as configured
+fn write_msg_with_bom<'a, W, M>(w: &mut W, msg: M, bom: Bom) -> io::Result<()>
+where
+    W: io::Write,
+    M: Into<Msg<'a>>,
+{
+    let msg = msg.into();
+    let prefix = match bom {
+        Bom::Write => &SPACED_BOM[..],
+        Bom::Omit => &SPACED_BOM[..1],
+    };
+
+    match msg {
+        Msg::Utf8Str(s) => write_str_msg(w, s, prefix),
+        Msg::Utf8String(s) => write_str_msg(w, &s, prefix),
+        Msg::NonUnicodeBytes([]) => Ok(()),
+        Msg::NonUnicodeBytes(bytes) => {
+            w.write_all(&[SPACE_BYTE])?;
+            w.write_all(bytes)
+        }
+        Msg::FmtArguments(args) => write_fmt_msg(w, args, prefix),
+        Msg::FmtArgumentsRef(args) => write_fmt_msg(w, *args, prefix),
+        Msg::Display(value) => write_display_msg(w, value, prefix),
+    }
+}
+
+/// Write a formatted message with octet counting framing, used to transport messages over a stream, e.g. TCP.
+///
+/// The message is prefixed with its length in bytes, including the BOM of the MSG, and a space.
+/// An empty message is framed as `0 `.
+///
+/// MSG-LEN SP SYSLOG-MSG
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc6587#section-3.4.1)
+///
+/// ```rust
+/// use syslog_fmt::v5424;
+///
+/// let mut stream = Vec::<u8>::new();
+/// v5424::frame_octet_counting(&mut stream, b"<134>1 - - - - - -").unwrap();
+/// assert_eq!(stream, b"18 <134>1 - - - - - -");
+/// ```
+#[cfg(feature = "std")]
+pub fn frame_octet_counting<W: io::Write>(w: &mut W, msg: &[u8]) -> io::Result<()> {
+    write!(w, "{} ", msg.len())?;
+    w.write_all(msg)
+}
+
+/// Write a formatted message with non-transparent framing, the message followed by a LF.
+/// Many syslog servers expect this framing for a stream transport, e.g. TCP.
+///
+/// An unescaped LF within the message, e.g. in a multi-line MSG, would split it into two frames.
+/// Such a message is handled according to the [EmbeddedLf] policy, by default nothing is written
+/// and an error of kind `InvalidInput` is returned. Use [frame_octet_counting] to send a multi-line
+/// message without changing it.
+///
+/// Format the message into a buffer first, e.g. with [Formatter::format_to_vec], and then frame it.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc6587#section-3.4.2)
+///
+/// ```rust
+/// use syslog_fmt::{Severity, v5424::{self, EmbeddedLf, Formatter}};
+///
+/// let formatter = Formatter::default();
+/// let mut stream = Vec::<u8>::new();
+///
+/// let msg = formatter
+///     .format_to_vec(Severity::Info, "2003-10-11T22:14:15.003Z", "a message", None)
+///     .unwrap();
+/// v5424::frame_lf(&mut stream, &msg, EmbeddedLf::Reject).unwrap();
+/// assert!(stream.ends_with(b"a message\n"));
+///
+/// let msg = formatter
+///     .format_to_vec(Severity::Info, "2003-10-11T22:14:15.003Z", "first\nsecond", None)
+///     .unwrap();
+/// assert!(v5424::frame_lf(&mut stream, &msg, EmbeddedLf::Reject).is_err());
+///
+/// v5424::frame_lf(&mut stream, &msg, EmbeddedLf::Escape).unwrap();
+/// assert!(stream.ends_with(b"first#010second\n"));
+/// ```
+#[cfg(feature = "std")]
+pub fn frame_lf<W: io::Write>(w: &mut W, msg: &[u8], embedded_lf: EmbeddedLf) -> io::Result<()> {
+    const LF: u8 = b'\n';
+
+    if embedded_lf == EmbeddedLf::Reject && msg.contains(&LF) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the message contains a LF, which would split it into two frames",
+        ));
+    }
+
+    for (i, line) in msg.split(|b| *b == LF).enumerate() {
+        if i > 0 {
+            w.write_all(b"#010")?;
+        }
+
+        w.write_all(line)?;
+    }
+
+    w.write_all(&[LF])
+}
+
+/// The lowercase keyword of a severity, e.g. `crit`, the form `syslog.conf` uses
+#[cfg(feature = "std")]
+fn severity_keyword(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Emerg => "emerg",
+        Severity::Alert => "alert",
+        Severity::Crit => "crit",
+        Severity::Err => "err",
+        Severity::Warning => "warning",
+        Severity::Notice => "notice",
+        Severity::Info => "info",
+        Severity::Debug => "debug",
+    }
+}
+
+/// What [frame_lf] does with a message that contains a LF, which would split the message into two frames
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EmbeddedLf {
+    /// Write nothing and return an error of kind `InvalidInput`
+    #[default]
+    Reject,
+    /// Replace each LF within the message by `#010`, like [ControlChars::Escape] does.
+    ///
+    /// **This loses data**, the receiver can't tell an escaped LF from a `#010` that was in the message,
+    /// so the original message can't be restored.
+    Escape,
+}
+
+/// Write a NILVALUE ('-') prefixed with a space
+#[cfg(feature = "std")]
+pub fn write_nil_value<W>(w: &mut W) -> io::Result<()>
+where
+    W: io::Write,
+{
+    write!(w, " {NILVALUE}")?;
+    Ok(())
+}
+
+#[cfg(feature = "chrono")]
+pub fn write_chrono_datetime<W: io::Write>(
+    w: &mut W,
+    datetime: &ChronoLocalTime,
+) -> io::Result<()> {
+    write_chrono_fixed_datetime(w, &datetime.fixed_offset())
+}
+
+/// Write a `chrono::DateTime` with microsecond precision and its numeric offset,
+/// e.g. `2003-08-24T05:14:15.000003-07:00`.
+///
+/// A leap second is written as the last microsecond of the preceding second.
+#[cfg(feature = "chrono")]
+fn write_chrono_fixed_datetime<W: io::Write>(
+    w: &mut W,
+    datetime: &chrono::DateTime<chrono::FixedOffset>,
+) -> io::Result<()> {
+    use chrono::Timelike;
+
+    const SECS_IN_MIN: u32 = 60;
+    const MINS_IN_HOUR: u32 = 60;
+
+    // reuse chrono `Debug` impls which already print ISO 8601 format.
+    let date = datetime.date_naive();
+    let time = datetime.time();
+    let h = time.hour();
+    let m = time.minute();
+    let s = time.second();
+    let micros = time.nanosecond().min(NANOS_IN_SEC - 1) / MICRO_IN_NANO;
+    let offset_secs = datetime.offset().local_minus_utc();
+    let sign = if offset_secs < 0 { '-' } else { '+' };
+    let offset_mins = offset_secs.unsigned_abs() / SECS_IN_MIN;
+    let offset_hour = offset_mins / MINS_IN_HOUR;
+    let offset_min = offset_mins % MINS_IN_HOUR;
+
+    write!(
+        w,
+        "{date:?}T{h:02}:{m:02}:{s:02}.{micros:06}{sign}{offset_hour:02}:{offset_min:02}"
+    )
+}
+
+/// Write a `chrono::DateTime<Utc>` as a UTC timestamp with microsecond precision and the `Z` designator,
+/// e.g. `2003-10-11T22:14:15.003000Z`.
+///
+/// A leap second is written as the last microsecond of the preceding second.
+#[cfg(feature = "chrono")]
+pub fn write_chrono_utc_datetime<W: io::Write>(
+    w: &mut W,
+    datetime: &ChronoUtcTime,
+) -> io::Result<()> {
+    let nanos = datetime.timestamp_subsec_nanos().min(NANOS_IN_SEC - 1);
+    write_unix_datetime(w, datetime.timestamp(), nanos)
+}
+
+/// Write a `time::OffsetDateTime` in the same format as [write_chrono_datetime],
+/// with microsecond precision and a numeric offset, e.g. `2003-10-11T22:14:15.003000+02:00`.
+#[cfg(feature = "time")]
+pub fn write_time_datetime<W: io::Write>(
+    w: &mut W,
+    datetime: &time::OffsetDateTime,
+) -> io::Result<()> {
+    let year = datetime.year();
+    let month = u8::from(datetime.month());
+    let day = datetime.day();
+    let (h, m, s, micros) = datetime.to_hms_micro();
+    let offset = datetime.offset();
+    let sign = if offset.is_negative() { '-' } else { '+' };
+    let offset_hour = offset.whole_hours().unsigned_abs();
+    let offset_min = offset.minutes_past_hour().unsigned_abs();
+
+    write!(
+        w,
+        "{year:04}-{month:02}-{day:02}T{h:02}:{m:02}:{s:02}.{micros:06}{sign}{offset_hour:02}:{offset_min:02}"
+    )
+}
+
+/// Write a `SystemTime` as a UTC timestamp with microsecond precision, e.g. `1985-04-12T23:20:50.520000Z`.
+///
+/// The date is calculated using only `std`, no heap allocations are performed.
+#[cfg(feature = "std")]
+fn write_system_time<W: io::Write>(w: &mut W, time: SystemTime) -> io::Result<()> {
+    let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => (since_epoch.as_secs() as i64, since_epoch.subsec_nanos()),
+        Err(e) => {
+            // the time is before the epoch
+            let before_epoch = e.duration();
+            let secs = -(before_epoch.as_secs() as i64);
+
+            match before_epoch.subsec_nanos() {
+                0 => (secs, 0),
+                nanos => (secs - 1, NANOS_IN_SEC - nanos),
+            }
+        }
+    };
+
+    write_unix_datetime(w, secs, nanos)
+}
+
+/// Write seconds and nanoseconds since the Unix epoch as a UTC timestamp with microsecond precision.
+fn write_unix_datetime<W: io::Write>(w: &mut W, secs: i64, nanos: u32) -> io::Result<()> {
+    const SECS_IN_DAY: i64 = 86_400;
+    const SECS_IN_HOUR: i64 = 3600;
+    const SECS_IN_MIN: i64 = 60;
+
+    let (year, month, day) = civil_from_days(secs.div_euclid(SECS_IN_DAY));
+    let secs_of_day = secs.rem_euclid(SECS_IN_DAY);
+    let h = secs_of_day / SECS_IN_HOUR;
+    let m = secs_of_day % SECS_IN_HOUR / SECS_IN_MIN;
+    let s = secs_of_day % SECS_IN_MIN;
+    let micros = nanos / MICRO_IN_NANO;
+
+    write!(
+        w,
+        "{year:04}-{month:02}-{day:02}T{h:02}:{m:02}:{s:02}.{micros:06}Z"
+    )
+}
+
+/// Write an offset from UTC in minutes as `Z` or `+HH:MM`/`-HH:MM`.
+fn write_utc_offset<W: io::Write>(w: &mut W, offset_minutes: i16) -> io::Result<()> {
+    const MINS_IN_HOUR: u16 = 60;
+
+    if offset_minutes == 0 {
+        return w.write_all(b"Z");
+    }
+
+    let sign = if offset_minutes > 0 { '+' } else { '-' };
+    let offset = offset_minutes.unsigned_abs();
+    let h = offset / MINS_IN_HOUR;
+    let m = offset % MINS_IN_HOUR;
+
+    write!(w, "{sign}{h:02}:{m:02}")
+}
+
+const NANOS_IN_SEC: u32 = 1_000_000_000;
+const MICRO_IN_NANO: u32 = 1000;
+
+/// Convert the days since the Unix epoch into a `(year, month, day)` date of the proleptic Gregorian calendar.
+///
+/// Negative days are dates before the epoch.
+///
+/// See: <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    const DAYS_IN_ERA: i64 = 146_097;
+    // the days from 0000-03-01 to 1970-01-01
+    const EPOCH_OFFSET: i64 = 719_468;
+
+    // the era is a 400 year cycle that starts on March 1st, so the leap day is the last day of the year
+    let z = days + EPOCH_OFFSET;
+    let era = z.div_euclid(DAYS_IN_ERA);
+    let day_of_era = z.rem_euclid(DAYS_IN_ERA);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // the month starting from March
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Write a UTF8 BOM prefixed by a space
+#[cfg(feature = "std")]
+pub fn write_utf8_bom<W: io::Write>(w: &mut W) -> io::Result<()> {
+    w.write_all(&SPACED_BOM)
+}
+
+/// The UTF8 BOM prefixed by an ASCII space
+const SPACED_BOM: [u8; 4] = [SPACE_BYTE, 0xEF, 0xBB, 0xBF];
+
+/// Write a UTF8 string with the given prefix, a space and a BOM as stated in the spec
+fn write_str_msg<W: io::Write>(w: &mut W, s: &str, prefix: &'static [u8]) -> io::Result<()> {
+    if !s.is_empty() {
+        w.write_all(prefix)?;
+        w.write_all(s.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// What [Formatter::write_to_buffer] does when a message doesn't fit in the buffer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FullBuffer {
+    /// Write as much of the message as fits and return a `WriteZero` error
+    #[default]
+    Truncate,
+    /// Write nothing and return a `WriteZero` error
+    Error,
+    /// Grow the buffer if it supports growing, otherwise behave like [FullBuffer::Error]
+    Grow,
+}
+
+/// A buffer with a known remaining capacity, see [Formatter::write_to_buffer].
+///
+/// Implemented for `Vec<u8>`, `io::Cursor<&mut [u8]>` and, with the `arrayvec` feature, `ArrayVec<u8, CAP>`.
+#[cfg(feature = "std")]
+pub trait Buffer: io::Write {
+    /// The number of bytes that can be written before the buffer is full
+    fn remaining_capacity(&self) -> usize;
+
+    /// Grow the buffer so at least `additional` more bytes fit.
+    /// Returns false if the buffer can't grow.
+    fn try_grow(&mut self, additional: usize) -> bool {
+        let _ = additional;
+        false
+    }
+}
+
+/// The capacity of a `Vec` is the limit, it only grows beyond it with [FullBuffer::Grow].
+#[cfg(feature = "std")]
+impl Buffer for Vec<u8> {
+    fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    fn try_grow(&mut self, additional: usize) -> bool {
+        self.try_reserve(additional).is_ok()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Buffer for io::Cursor<&mut [u8]> {
+    fn remaining_capacity(&self) -> usize {
+        let len = self.get_ref().len();
+        let position = usize::try_from(self.position()).unwrap_or(usize::MAX);
+        len.saturating_sub(position)
+    }
+}
+
+#[cfg(feature = "arrayvec")]
+impl<const CAP: usize> Buffer for arrayvec::ArrayVec<u8, CAP> {
+    fn remaining_capacity(&self) -> usize {
+        arrayvec::ArrayVec::remaining_capacity(self)
+    }
+}
+
+/// The 64-bit FNV-1a hash, see [Formatter::format_with_content_hash]
+///
+/// See: <http://www.isthe.com/chongo/tech/comp/fnv/index.html>
+#[cfg(feature = "std")]
+struct Fnv1a(u64);
+
+#[cfg(feature = "std")]
+impl Default for Fnv1a {
+    fn default() -> Self {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        Self(OFFSET_BASIS)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+}
+
+/// Write a message that is formatted up front, see [Formatter::write_with_data_async]
+#[cfg(feature = "tokio")]
+async fn write_formatted_async<W>(w: &mut W, formatted: io::Result<Vec<u8>>) -> io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt as _;
+
+    w.write_all(&formatted?).await
+}
+
+/// A writer that drops the bytes beyond a limit, without splitting a UTF-8 char.
+///
+/// The bytes that are dropped are reported as written, so the formatting continues.
+struct LimitWriter<'w, W> {
+    inner: &'w mut W,
+    remaining: usize,
+    truncated: bool,
+}
+
+impl<'w, W: io::Write> io::Write for LimitWriter<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() <= self.remaining {
+            self.inner.write_all(buf)?;
+            self.remaining -= buf.len();
+            return Ok(buf.len());
+        }
+
+        // a continuation byte of a multi-byte UTF-8 char starts with the bits 10
+        let mut end = self.remaining;
+        while end > 0 && buf[end] & 0b1100_0000 == 0b1000_0000 {
+            end -= 1;
+        }
+
+        self.inner.write_all(&buf[..end])?;
+        self.remaining = 0;
+        self.truncated = true;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that writes at most `remaining` bytes to the inner writer, see [Formatter::write_to_buffer].
+///
+/// A write beyond the limit is short, so a `write_all` fails with `WriteZero` once the limit is reached.
+#[cfg(feature = "std")]
+struct BoundedWriter<'w, W> {
+    inner: &'w mut W,
+    remaining: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'w, W: io::Write> io::Write for BoundedWriter<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.remaining);
+        self.inner.write_all(&buf[..len])?;
+        self.remaining -= len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that counts the bytes written to the inner writer.
+///
+/// It optionally records the byte range of each field of a message, see [Formatter::format_annotated].
+struct CountingWriter<'w, W> {
+    inner: &'w mut W,
+    len: usize,
+    /// The recorded fields, `None` if the fields aren't recorded
+    fields: Option<Vec<(Field, Range<usize>)>>,
+}
+
+impl<'w, W> CountingWriter<'w, W> {
+    fn new(inner: &'w mut W) -> Self {
+        Self {
+            inner,
+            len: 0,
+            fields: None,
+        }
+    }
+
+    /// A writer that records the byte range of each field
+    #[cfg(feature = "std")]
+    fn recording_fields(inner: &'w mut W) -> Self {
+        Self {
+            fields: Some(vec![]),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Record the byte range of a field, if the fields are recorded
+    fn record_field(&mut self, field: Field, range: Range<usize>) {
+        if let Some(fields) = &mut self.fields {
+            fields.push((field, range));
+        }
+    }
+}
+
+impl<'w, W: io::Write> io::Write for CountingWriter<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that discards the bytes written to it and only counts them.
+#[derive(Default)]
+struct ByteCounter {
+    len: usize,
+}
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write a value implementing `fmt::Display` with the given prefix, a space and a BOM as stated in the spec.
+///
+/// Nothing is written if the value formats to an empty string.
+fn write_display_msg<W: io::Write>(
+    w: &mut W,
+    value: &dyn fmt::Display,
+    prefix: &'static [u8],
+) -> io::Result<()> {
+    write_fmt_msg(w, format_args!("{value}"), prefix)
+}
+
+/// Write formatting arguments with the given prefix, a space and a BOM as stated in the spec.
+///
+/// Nothing is written if the arguments format to an empty string.
+fn write_fmt_msg<W: io::Write>(
+    w: &mut W,
+    args: fmt::Arguments<'_>,
+    prefix: &'static [u8],
+) -> io::Result<()> {
+    let mut bridge = FmtBridge {
+        w,
+        prefix,
+        result: Ok(()),
+    };
+
+    match fmt::write(&mut bridge, args) {
+        Ok(()) => Ok(()),
+        Err(_) => bridge
+            .result
+            .and(Err(io::Error::new(io::ErrorKind::Other, "formatter error"))),
+    }
+}
+
+/// Bridges an `io::Write` to a `fmt::Write` so a message can be written into a `String`.
+///
+/// The formatter writes each field in one or more complete UTF-8 pieces, e.g. the BOM is written
+/// as a whole, so each write is expected to be valid UTF-8 on its own.
+struct IoBridge<'w, W> {
+    w: &'w mut W,
+}
+
+impl<'w, W: fmt::Write> io::Write for IoBridge<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = core::str::from_utf8(buf).map_err(|_err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Only UTF-8 can be written to a fmt::Write",
+            )
+        })?;
+
+        self.w
+            .write_str(s)
+            .map_err(|_err| io::Error::new(io::ErrorKind::Other, "formatter error"))?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Bridges a `fmt::Write` to an `io::Write` so a `fmt::Display` can be written without an intermediate `String`.
+///
+/// The prefix is written before the first non empty `str`.
+struct FmtBridge<'w, W> {
+    w: &'w mut W,
+    prefix: &'static [u8],
+    /// `fmt::Error` carries no information, so the cause of a failed write is kept here
+    result: io::Result<()>,
+}
+
+impl<'w, W: io::Write> fmt::Write for FmtBridge<'w, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.is_empty() {
+            return Ok(());
+        }
+
+        let prefix = core::mem::take(&mut self.prefix);
+
+        let res = self
+            .w
+            .write_all(prefix)
+            .and_then(|()| self.w.write_all(s.as_bytes()));
+
+        res.map_err(|e| {
+            self.result = Err(e);
+            fmt::Error
+        })
+    }
+}
+
+pub(crate) const NILVALUE: &str = "-";
+
+/// The VERSION field denotes the version of the syslog protocol
+/// specification. The version number MUST be incremented for any new
+/// syslog protocol specification that changes any part of the HEADER
+/// format.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.2)
+const VERSION: &str = "1";
+
+#[cfg(feature = "chrono")]
+type ChronoLocalTime = chrono::DateTime<chrono::Local>;
+
+#[cfg(feature = "chrono")]
+type ChronoUtcTime = chrono::DateTime<chrono::Utc>;
+
+/// The TIMESTAMP field is a formalized timestamp derived from [RFC3339](https://datatracker.ietf.org/doc/html/rfc3339).
+///
+/// Whereas [RFC3339](https://datatracker.ietf.org/doc/html/rfc3339) makes allowances for multiple syntaxes,
+/// the Syslog 5424 spec imposes further restrictions. The TIMESTAMP value MUST
+/// follow these restrictions:
+///
+/// *  The "T" and "Z" characters in this syntax MUST be upper case.
+/// *  Usage of the "T" character is REQUIRED.
+/// *  Leap seconds MUST NOT be used.
+///
+/// A syslog application MUST use the NILVALUE as TIMESTAMP if the syslog
+/// application is incapable of obtaining system time.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.3)
+pub enum Timestamp<'a> {
+    /// Provide a datatime to be formatted.
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "chrono")]
+    Chrono(&'a ChronoLocalTime),
+    /// The formatter will create a new `chrono::DateTime<Local>`
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "chrono")]
+    CreateChronoLocal,
+    /// Provide a `chrono::DateTime<Utc>` to be formatted as a UTC timestamp with the `Z` designator.
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "chrono")]
+    ChronoUtc(&'a ChronoUtcTime),
+    /// The formatter will create a new `chrono::DateTime<Utc>`, written with the `Z` designator.
+    /// Unlike the local time this doesn't depend on the time zone data of the system.
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "chrono")]
+    CreateChronoUtc,
+    /// Provide a `time::OffsetDateTime` to be formatted.
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "time")]
+    Time(&'a time::OffsetDateTime),
+    /// The formatter will create a new `time::OffsetDateTime` with the local offset.
+    /// The UTC time is used if the local offset can't be determined.
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "time")]
+    CreateTimeLocal,
+    /// The formatter will create a new `time::OffsetDateTime` in UTC
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "time")]
+    CreateTimeUtc,
+    /// The formatter will create a UTC timestamp from `std::time::SystemTime::now()`.
+    /// This is available with the default `std` feature.
+    /// A custom formatter is used that does not perform any heap allcations
+    #[cfg(feature = "std")]
+    CreateSystemNow,
+    /// Provide a `std::time::SystemTime` to be formatted as a UTC timestamp with the `Z` designator.
+    /// This is available with the default `std` feature.
+    /// A custom formatter is used that does not perform any heap allcations
+    ///
+    /// ```rust
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use syslog_fmt::v5424::{self, Timestamp};
+    ///
+    /// let time = UNIX_EPOCH + Duration::from_micros(1_065_910_455_003_000);
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// v5424::write_timestamp(&mut buf, Timestamp::SystemTime(time)).unwrap();
+    /// assert_eq!(buf, b"2003-10-11T22:14:15.003000Z");
+    /// ```
+    #[cfg(feature = "std")]
+    SystemTime(SystemTime),
+    /// The formatter will ask the clock for the time when the timestamp is written, as a UTC timestamp.
+    /// This is meant for a message that is queued and written later, the timestamp then reflects
+    /// the time the message is written instead of the time it was queued.
+    /// A custom formatter is used that does not perform any heap allcations
+    ///
+    /// ```rust
+    /// use syslog_fmt::v5424::{self, SystemClock, Timestamp};
+    ///
+    /// let timestamp = Timestamp::Deferred(&SystemClock);
+    ///
+    /// // later, e.g. on a transport thread
+    /// let mut buf = Vec::<u8>::new();
+    /// v5424::write_timestamp(&mut buf, timestamp).unwrap();
+    /// assert!(buf.ends_with(b"Z"));
+    /// ```
+    #[cfg(feature = "std")]
+    Deferred(&'a dyn Clock),
+    /// Provide the seconds and nanoseconds since the Unix epoch, to be formatted as a UTC timestamp
+    /// with the `Z` designator. Negative seconds are a time before the epoch.
+    /// This is available without any optional features.
+    /// A custom formatter is used that does not perform any heap allcations
+    ///
+    /// ```rust
+    /// use syslog_fmt::v5424::{self, Timestamp};
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// v5424::write_timestamp(&mut buf, Timestamp::Epoch { secs: 1_065_910_455, nanos: 3_000_000 }).unwrap();
+    /// assert_eq!(buf, b"2003-10-11T22:14:15.003000Z");
+    /// ```
+    Epoch {
+        secs: i64,
+        /// The fraction of the second, written with microsecond precision.
+        /// A value of a second or more is written as the last microsecond of the second.
+        nanos: u32,
+    },
+    /// Provide the date and time as separate components, e.g. as read from a hardware RTC.
+    /// The timestamp is formatted without a date library and the components are not validated.
+    /// A custom formatter is used that does not perform any heap allcations
+    ///
+    /// ```rust
+    /// use syslog_fmt::v5424::{self, Timestamp};
+    ///
+    /// let timestamp = Timestamp::Components {
+    ///     year: 2003,
+    ///     month: 8,
+    ///     day: 24,
+    ///     hour: 5,
+    ///     min: 14,
+    ///     sec: 15,
+    ///     nanos: 3_000,
+    ///     offset_minutes: -7 * 60,
+    /// };
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// v5424::write_timestamp(&mut buf, timestamp).unwrap();
+    /// assert_eq!(buf, b"2003-08-24T05:14:15.000003-07:00");
+    /// ```
+    Components {
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        min: u8,
+        sec: u8,
+        /// The fraction of the second, written with microsecond precision
+        nanos: u32,
+        /// The offset from UTC in minutes, an offset of 0 is written as `Z`
+        offset_minutes: i16,
+    },
+    /// Provide a preformatted timestamp.
+    /// This string is not validated. The onus is on the provider to verify it as an RFC3339 timestamp
+    /// See the [Timestamp] docs above for details on how to format a timestamp.
+    PreformattedStr(&'a str),
+    /// Provide a preformatted timestamp.
+    /// This string is not validated. The onus is on the provider to verify it as an RFC3339 timestamp
+    /// See the [Timestamp] docs above for details on how to format a timestamp.
+    PreformattedString(String),
+    /// No timestamp can be provided.
+    None,
+}
+
+/// A source of the current time, see [Timestamp::Deferred]
+#[cfg(feature = "std")]
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+/// The clock of the system, see `std::time::SystemTime::now()`
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<'a> Timestamp<'a> {
+    /// Borrow the timestamp so it can be written more than once
+    fn reborrow(&self) -> Timestamp<'_> {
+        match self {
+            #[cfg(feature = "chrono")]
+            Timestamp::Chrono(datetime) => Timestamp::Chrono(datetime),
+            #[cfg(feature = "chrono")]
+            Timestamp::CreateChronoLocal => Timestamp::CreateChronoLocal,
+            #[cfg(feature = "chrono")]
+            Timestamp::ChronoUtc(datetime) => Timestamp::ChronoUtc(datetime),
+            #[cfg(feature = "chrono")]
+            Timestamp::CreateChronoUtc => Timestamp::CreateChronoUtc,
+            #[cfg(feature = "time")]
+            Timestamp::Time(datetime) => Timestamp::Time(datetime),
+            #[cfg(feature = "time")]
+            Timestamp::CreateTimeLocal => Timestamp::CreateTimeLocal,
+            #[cfg(feature = "time")]
+            Timestamp::CreateTimeUtc => Timestamp::CreateTimeUtc,
+            #[cfg(feature = "std")]
+            Timestamp::CreateSystemNow => Timestamp::CreateSystemNow,
+            #[cfg(feature = "std")]
+            Timestamp::SystemTime(time) => Timestamp::SystemTime(*time),
+            #[cfg(feature = "std")]
+            Timestamp::Deferred(clock) => Timestamp::Deferred(*clock),
+            Timestamp::Epoch { secs, nanos } => Timestamp::Epoch {
+                secs: *secs,
+                nanos: *nanos,
+            },
+            Timestamp::Components {
+                year,
+                month,
+                day,
+                hour,
+                min,
+                sec,
+                nanos,
+                offset_minutes,
+            } => Timestamp::Components {
+                year: *year,
+                month: *month,
+                day: *day,
+                hour: *hour,
+                min: *min,
+                sec: *sec,
+                nanos: *nanos,
+                offset_minutes: *offset_minutes,
+            },
+            Timestamp::PreformattedStr(s) => Timestamp::PreformattedStr(s),
+            Timestamp::PreformattedString(s) => Timestamp::PreformattedStr(s),
+            Timestamp::None => Timestamp::None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Timestamp<'a> {
+    fn from(s: &'a str) -> Self {
+        Self::PreformattedStr(s)
+    }
+}
+
+impl<'a> From<String> for Timestamp<'a> {
+    fn from(s: String) -> Self {
+        Self::PreformattedString(s)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'a> From<&'a ChronoLocalTime> for Timestamp<'a> {
+    fn from(datetime: &'a ChronoLocalTime) -> Self {
+        Self::Chrono(datetime)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'a> From<&'a ChronoUtcTime> for Timestamp<'a> {
+    fn from(datetime: &'a ChronoUtcTime) -> Self {
+        Self::ChronoUtc(datetime)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<SystemTime> for Timestamp<'a> {
+    fn from(time: SystemTime) -> Self {
+        Self::SystemTime(time)
+    }
+}
+
+#[cfg(feature = "time")]
+impl<'a> From<&'a time::OffsetDateTime> for Timestamp<'a> {
+    fn from(datetime: &'a time::OffsetDateTime) -> Self {
+        Self::Time(datetime)
+    }
+}
+
+/// The HOSTNAME field identifies the machine that originally sent the syslog message.
+///
+/// The HOSTNAME field SHOULD contain the hostname and the domain name of
+/// the originator in the format specified in STD 13 [RFC1034](https://datatracker.ietf.org/doc/html/rfc1034).
+/// This format is called a Fully Qualified Domain Name (FQDN).
+///
+/// In practice, not all syslog applications are able to provide an FQDN.
+/// As such, other values MAY also be present in HOSTNAME. Below are
+/// provisions for using other values in such situations. A syslog
+/// application SHOULD provide the most specific available value first.
+/// The order of preference for the contents of the HOSTNAME field is as
+/// follows:
+///
+/// 1. FQDN
+/// 2. Static IP address
+/// 3. hostname
+/// 4. Dynamic IP address
+/// 5. the NILVALUE
+///
+/// If an IPv4 address is used, it MUST be in the format of the dotted
+/// decimal notation as used in STD 13 [RFC1035](https://datatracker.ietf.org/doc/html/rfc1035).
+/// If an IPv6 address is used, a valid textual representation as described in
+/// [RFC4291, Section 2.2](https://datatracker.ietf.org/doc/html/rfc4291#section-2.2), MUST be used.
+///
+/// Syslog applications SHOULD consistently use the same value in the
+/// HOSTNAME field for as long as possible.
+///
+/// The NILVALUE SHOULD only be used when the syslog application has no
+/// way to obtain its real hostname. This situation is considered highly
+/// unlikely.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.4)
+type Hostname = str;
+
+/// The APP-NAME field SHOULD identify the device or application that
+/// originated the message. It is a string without further semantics.
+/// It is intended for filtering messages on a relay or collector.
+///
+/// The NILVALUE MAY be used when the syslog application has no idea of
+/// its APP-NAME or cannot provide that information. It may be that a
+/// device is unable to provide that information either because of a
+/// local policy decision, or because the information is not available,
+/// or not applicable, on the device.
+///
+/// This field MAY be operator-assigned.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.5)
+type AppName = str;
+
+/// PROCID is a value that is included in the message, having no
+/// interoperable meaning, except that a change in the value indicates
+/// there has been a discontinuity in syslog reporting. The field does
+/// not have any specific syntax or semantics; the value is
+/// implementation-dependent and/or operator-assigned. The NILVALUE MAY
+/// be used when no value is provided.
+///
+/// The PROCID field is often used to provide the process name or process
+/// ID associated with a syslog system. The NILVALUE might be used when
+/// a process ID is not available. On an embedded system without any
+/// operating system process ID, PROCID might be a reboot ID.
+///
+/// PROCID can enable log analyzers to detect discontinuities in syslog
+/// reporting by detecting a change in the syslog process ID. However,
+/// PROCID is not a reliable identification of a restarted process since
+/// the restarted syslog process might be assigned the same process ID as
+/// the previous syslog process.
+///
+/// PROCID can also be used to identify which messages belong to a group
+/// of messages. For example, an SMTP mail transfer agent might put its
+/// SMTP transaction ID into PROCID, which would allow the collector or
+/// relay to group messages based on the SMTP transaction.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.6)
+type ProcId = str;
+
+/// The MSGID SHOULD identify the type of message. For example, a
+/// firewall might use the MSGID "TCPIN" for incoming TCP traffic and the
+/// MSGID "TCPOUT" for outgoing TCP traffic. Messages with the same
+/// MSGID should reflect events of the same semantics. The MSGID itself
+/// is a string without further semantics. It is intended for filtering
+/// messages on a relay or collector.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.7)
+type MsgId = str;
+
+/// The MSG part contains a free-form message that provides information
+/// about the event.
+///
+/// The character set used in MSG SHOULD be UNICODE, encoded using UTF-8
+/// as specified in [RFC3629](https://datatracker.ietf.org/doc/html/rfc3629).
+/// If the syslog application cannot encode
+/// the MSG in Unicode, it MAY use any other encoding.
+///
+/// The syslog application SHOULD avoid octet values below 32 (the
+/// traditional US-ASCII control character range except DEL). These
+/// values are legal, but a syslog application MAY modify these
+/// characters upon reception. For example, it might change them into an
+/// escape sequence (e.g., value 0 may be changed to "\0"). A syslog
+/// application SHOULD NOT modify any other octet values.
+///
+/// If a syslog application encodes MSG in UTF-8, the string MUST start
+/// with the Unicode byte order mask (BOM), which for UTF-8 is ABNF
+/// %xEF.BB.BF. The syslog application MUST encode in the "shortest
+/// form" and MAY use any valid UTF-8 sequence.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.4)
+pub enum Msg<'a> {
+    /// A BOM will be prefixed to UTF8 encoded strings
+    Utf8Str(&'a str),
+    /// A BOM will be prefixed to UTF8 encoded strings
+    Utf8String(String),
+    /// Bytes not encoded as Unicode not be prefixed by a BOM
+    NonUnicodeBytes(&'a [u8]),
+    /// Accepting fmt::Arguments can make life easier when working with logging frameworks.
+    /// A BOM will be prefixed to the formatted arguments
+    FmtArguments(fmt::Arguments<'a>),
+    /// Accepting fmt::Arguments can make life easier when working with logging frameworks.
+    /// A BOM will be prefixed to the formatted arguments
+    FmtArgumentsRef(&'a fmt::Arguments<'a>),
+    /// Any type that implements `fmt::Display` is formatted directly into the writer.
+    /// A BOM will be prefixed to the formatted value
+    ///
+    /// ```rust
+    /// use std::fmt;
+    ///
+    /// use syslog_fmt::{Severity, v5424::{Formatter, Msg}};
+    ///
+    /// struct Order(u32);
+    ///
+    /// impl fmt::Display for Order {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         write!(f, "order {} shipped", self.0)
+    ///     }
+    /// }
+    ///
+    /// let mut buf = Vec::<u8>::new();
+    /// Formatter::default().write_without_data(
+    ///     &mut buf,
+    ///     Severity::Info,
+    ///     "2003-10-11T22:14:15.003Z",
+    ///     Msg::Display(&Order(7)),
+    ///     None,
+    /// );
+    /// ```
+    Display(&'a dyn fmt::Display),
+}
+
+impl<'a> Msg<'a> {
+    /// The length of the MSG including the BOM, or 0 if it isn't known without formatting it
+    #[cfg(feature = "bytes")]
+    fn len_hint(&self) -> usize {
+        match self {
+            Msg::Utf8Str(s) => SPACED_BOM.len() + s.len(),
+            Msg::Utf8String(s) => SPACED_BOM.len() + s.len(),
+            Msg::NonUnicodeBytes(bytes) => 1 + bytes.len(),
+            Msg::FmtArguments(_) | Msg::FmtArgumentsRef(_) | Msg::Display(_) => 0,
+        }
+    }
+
+    /// Borrow the message so it can be written more than once
+    fn reborrow(&self) -> Msg<'_> {
+        match self {
+            Msg::Utf8Str(s) => Msg::Utf8Str(s),
+            Msg::Utf8String(s) => Msg::Utf8Str(s),
+            Msg::NonUnicodeBytes(bytes) => Msg::NonUnicodeBytes(bytes),
+            Msg::FmtArguments(args) => Msg::FmtArgumentsRef(args),
+            Msg::FmtArgumentsRef(args) => Msg::FmtArgumentsRef(args),
+            Msg::Display(value) => Msg::Display(*value),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Msg<'a> {
+    fn from(s: &'a str) -> Self {
+        Self::Utf8Str(s)
+    }
+}
+
+impl<'a> From<String> for Msg<'a> {
+    fn from(s: String) -> Self {
+        Self::Utf8String(s)
+    }
+}
+
+impl<'a> From<&'a [u8]> for Msg<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Self::NonUnicodeBytes(bytes)
+    }
+}
+
+impl<'a> From<fmt::Arguments<'a>> for Msg<'a> {
+    fn from(args: fmt::Arguments<'a>) -> Self {
+        Self::FmtArguments(args)
+    }
+}
+
+impl<'a> From<&'a fmt::Arguments<'a>> for Msg<'a> {
+    fn from(args: &'a fmt::Arguments<'a>) -> Self {
+        Self::FmtArgumentsRef(args)
+    }
+}
+
+/// [SdId]s are case-sensitive and uniquely identify the type and purpose
+/// of the SD-ELEMENT. The same [SdId] MUST NOT exist more than once in a
+/// message.
+///
+/// There are two formats for [SdId] names:
+///
+/// - Names that do not contain an at-sign ("@", ABNF %d64) are reserved
+///   to be assigned by IETF Review as described in BCP26 [RFC5226](https://datatracker.ietf.org/doc/html/rfc5226).
+///   Currently, these are the names defined in Section 7. Names of
+///   this format are only valid if they are first registered with the
+///   IANA. Registered names MUST NOT contain an at-sign ('@', ABNF %d64),
+///   an equal-sign ('=', ABNF %d61), a closing brace (']', ABNF
+///   %d93), a quote-character ('"', ABNF %d34), whitespace, or control
+///   characters (ASCII code 127 and codes 32 or less).
+///
+/// - Anyone can define additional SD-IDs using names in the format
+///   `name@<private enterprise number>`, e.g., "ourSDID@32473". The
+///   format of the part preceding the at-sign is not specified;
+///   however, these names MUST be printable US-ASCII strings, and MUST
+///   NOT contain an at-sign ('@', ABNF %d64), an equal-sign ('=', ABNF
+///   %d61), a closing brace (']', ABNF %d93), a quote-character ('"',
+///   ABNF %d34), whitespace, or control characters. The part following
+///   the at-sign MUST be a private enterprise number as specified in
+///   Section 7.2.2. Please note that throughout this document the
+///   value of 32473 is used for all private enterprise numbers. This
+///   value has been reserved by IANA to be used as an example number in
+///   documentation. Implementors will need to use their own private
+///   enterprise number for the enterpriseId parameter, and when
+///   creating locally extensible SD-ID names.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2)
+type SdId = str;
+
+/// An [SD-ID](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2) that has been validated once,
+/// so it can be reused for many messages without validating it again.
+///
+/// ```rust
+/// use syslog_fmt::{Severity, v5424::{Formatter, ValidatedSdId}};
+///
+/// let id = ValidatedSdId::new("exampleSDID@32473").unwrap();
+/// let formatter = Formatter::default();
+/// let mut buf = Vec::<u8>::new();
+///
+/// for event_id in ["1011", "1012"] {
+///     formatter.write_with_data(
+///         &mut buf,
+///         Severity::Info,
+///         "2003-10-11T22:14:15.003Z",
+///         "",
+///         None,
+///         [(id.as_str(), [("eventID", event_id)])],
+///     );
+/// }
+///
+/// assert!(ValidatedSdId::new("example SDID").is_err());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ValidatedSdId<'a>(&'a SdId);
+
+impl<'a> ValidatedSdId<'a> {
+    /// Validate the given SD-ID
+    ///
+    /// An SD-ID may contain a single at-sign, which separates the name from a private enterprise number,
+    /// e.g. `ourSDID@32473`.
+    pub fn new(id: &'a str) -> Result<Self, SdError> {
+        validate_sd_id(id)?;
+        Ok(Self(id))
+    }
+
+    pub fn as_str(&self) -> &'a SdId {
+        self.0
+    }
+}
+
+impl<'a> AsRef<str> for ValidatedSdId<'a> {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl<'a> fmt::Display for ValidatedSdId<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// Error returned if an SD-ID or PARAM-NAME is not valid
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SdError {
+    /// The name is empty
+    Empty,
+    /// The name is longer than 32 bytes, the `len` is in bytes
+    TooLong { len: usize },
+    /// The name contains a character that is not allowed.
+    /// The index is the byte offset of the character within the name.
+    IllegalChar { ch: char, index: usize },
+    /// The part of an SD-ID following the at-sign is not a private enterprise number
+    InvalidEnterpriseId,
+}
+
+impl fmt::Display for SdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdError::Empty => f.write_str("The name is empty"),
+            SdError::TooLong { len } => write!(
+                f,
+                "The name is {len} bytes long, the maximum is {SD_NAME_MAX_LEN}"
+            ),
+            SdError::IllegalChar { ch, index } => {
+                write!(f, "The name contains the illegal char {ch:?} at {index}")
+            }
+            SdError::InvalidEnterpriseId => {
+                f.write_str("The part following the '@' is not a private enterprise number")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SdError {}
+
+/// Validate each SD-ID and PARAM-NAME of structured data, the first invalid name is returned as the error.
+///
+/// An SD-ID may contain a single at-sign, which must be followed by a private enterprise number.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2)
+///
+/// ```rust
+/// use syslog_fmt::v5424::{self, SdError};
+///
+/// assert!(v5424::validate_data([("exampleSDID@32473", [("iut", "3")])]).is_ok());
+///
+/// let err = v5424::validate_data([("exampleSDID@32473", [("i]t", "3")])]).unwrap_err();
+/// assert_eq!(err.param_name.as_deref(), Some("i]t"));
+/// assert_eq!(err.error, SdError::IllegalChar { ch: ']', index: 1 });
+/// ```
+pub fn validate_data<'a, I, P>(data: I) -> Result<(), InvalidSdName>
+where
+    I: IntoIterator<Item = (&'a SdId, P)>,
+    P: IntoIterator<Item = SdParam<'a>>,
+{
+    for (id, params) in data {
+        validate_sd_id(id).map_err(|error| InvalidSdName {
+            sd_id: id.to_owned(),
+            param_name: None,
+            error,
+        })?;
+
+        for (name, _) in params {
+            validate_sd_name(name).map_err(|error| InvalidSdName {
+                sd_id: id.to_owned(),
+                param_name: Some(name.to_owned()),
+                error,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Error returned by [validate_data] if an SD-ID or PARAM-NAME is not valid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidSdName {
+    /// The SD-ID of the element with the invalid name
+    pub sd_id: String,
+    /// The invalid PARAM-NAME, `None` if the SD-ID is invalid
+    pub param_name: Option<String>,
+    pub error: SdError,
+}
+
+impl fmt::Display for InvalidSdName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            sd_id,
+            param_name,
+            error,
+        } = self;
+
+        match param_name {
+            Some(name) => write!(
+                f,
+                "The PARAM-NAME {name:?} of the SD-ID {sd_id:?} is invalid: {error}"
+            ),
+            None => write!(f, "The SD-ID {sd_id:?} is invalid: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidSdName {}
+
+/// The maximum length of an SD-NAME
+const SD_NAME_MAX_LEN: usize = 32;
+
+/// Validate an SD-NAME, which is the format of both the SD-ID and PARAM-NAME.
+///
+/// SD-NAME = 1*32PRINTUSASCII ; except '=', SP, ']', %d34 (")
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6)
+fn validate_sd_name(name: &str) -> Result<(), SdError> {
+    if name.is_empty() {
+        return Err(SdError::Empty);
+    }
+
+    if let Some((index, ch)) = name
+        .char_indices()
+        .find(|(_, ch)| !is_print_us_ascii(*ch) || matches!(ch, '=' | ' ' | ']' | '"'))
+    {
+        return Err(SdError::IllegalChar { ch, index });
+    }
+
+    if name.len() > SD_NAME_MAX_LEN {
+        return Err(SdError::TooLong { len: name.len() });
+    }
+
+    Ok(())
+}
+
+/// Validate an SD-ID, which is an SD-NAME with at most one at-sign.
+///
+/// The part preceding the at-sign MUST NOT be empty and the part following it
+/// MUST be a private enterprise number.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2)
+fn validate_sd_id(id: &str) -> Result<(), SdError> {
+    validate_sd_name(id)?;
+
+    let Some((name, enterprise_id)) = id.split_once('@') else {
+        return Ok(());
+    };
+
+    if name.is_empty() {
+        return Err(SdError::Empty);
+    }
+
+    if let Some(index) = enterprise_id.find('@') {
+        return Err(SdError::IllegalChar {
+            ch: '@',
+            index: name.len() + 1 + index,
+        });
+    }
+
+    if !is_enterprise_id(enterprise_id) {
+        return Err(SdError::InvalidEnterpriseId);
+    }
+
+    Ok(())
+}
+
+/// enterpriseId = 1*DIGIT *("." 1*DIGIT)
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-7.2.2)
+fn is_enterprise_id(s: &str) -> bool {
+    s.split('.')
+        .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// PRINTUSASCII = %d33-126
+fn is_print_us_ascii(ch: char) -> bool {
+    matches!(ch, '!'..='~')
+}
+
+/// Each SD-PARAM consists of a name, referred to as PARAM-NAME, and a
+/// value, referred to as PARAM-VALUE.
+///
+/// PARAM-NAME is case-sensitive. IANA controls all PARAM-NAMEs, with
+/// the exception of those in SD-IDs whose names contain an at-sign. The
+/// PARAM-NAME scope is within a specific SD-ID. Thus, equally named
+/// PARAM-NAME values contained in two different SD-IDs are not the same.
+///
+/// To support international characters, the PARAM-VALUE field MUST be
+/// encoded using UTF-8. A syslog application MAY issue any valid UTF-8
+/// sequence. A syslog application MUST accept any valid UTF-8 sequence
+/// in the "shortest form". It MUST NOT fail if control characters are
+/// present in PARAM-VALUE. The syslog application MAY modify messages
+/// containing control characters (e.g., by changing an octet with value
+/// 0 (USASCII NUL) to the four characters "#000"). For the reasons
+/// outlined in UNICODE TR36 [UNICODE-TR36, section 3.1](https://datatracker.ietf.org/doc/html/rfc5424#ref-UNICODE-TR36),
+/// an originator MUST encode messages in the "shortest form" and a collector or relay
+/// MUST NOT interpret messages in the "non-shortest form".
+///
+/// Inside PARAM-VALUE, the characters '"' (ABNF %d34), '\' (ABNF %d92),
+/// and ']' (ABNF %d93) MUST be escaped. This is necessary to avoid
+/// parsing errors. Escaping ']' would not strictly be necessary but is
+/// REQUIRED by this specification to avoid syslog application
+/// implementation errors. Each of these three characters MUST be
+/// escaped as '\"', '\\', and '\]' respectively. The backslash is used
+/// for control character escaping for consistency with its use for
+/// escaping in other parts of the syslog message as well as in
+/// traditional syslog.
+///
+/// A backslash ('\') followed by none of the three described characters
+/// is considered an invalid escape sequence. In this case, the
+/// backslash MUST be treated as a regular backslash and the following
+/// character as a regular character. Thus, the invalid sequence MUST
+/// not be altered.
+///
+/// An SD-PARAM MAY be repeated multiple times inside an SD-ELEMENT.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.3)
+type SdParam<'a> = (ParamName<'a>, ParamValue<'a>);
+type ParamName<'a> = &'a str;
+type ParamValue<'a> = &'a str;
+
+/// Create the "language" SD-PARAM of the "meta" SD-ID.
+///
+/// The "language" parameter MAY be specified by the originator. It is
+/// used to convey information about the natural language used inside
+/// MSG. If it is specified, it MUST contain a language identifier as
+/// defined in BCP 47 [RFC4646](https://datatracker.ietf.org/doc/html/rfc4646).
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-7.3.3)
+///
+/// Only the syntax of the tag is validated, the subtags are not checked against the IANA registry.
+///
+/// ```rust
+/// use syslog_fmt::v5424;
+///
+/// let mut buf = Vec::<u8>::new();
+/// let language = v5424::language_param("en-US").unwrap();
+/// v5424::write_data(&mut buf, [("meta", [language])]).unwrap();
+///
+/// assert!(v5424::language_param("en_US").is_err());
+/// ```
+pub fn language_param(tag: &str) -> Result<SdParam<'_>, InvalidLanguageTag> {
+    if is_well_formed_language_tag(tag) {
+        Ok(("language", tag))
+    } else {
+        Err(InvalidLanguageTag)
+    }
+}
+
+/// Error returned if a language tag is not a well formed BCP 47 tag
+#[derive(Debug)]
+pub struct InvalidLanguageTag;
+
+impl fmt::Display for InvalidLanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The language tag is not a well formed BCP 47 tag")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidLanguageTag {}
+
+/// Create an SD-ELEMENT with the IDs of a distributed trace, e.g. `[trace@32473 traceId="..." spanId="..."]`,
+/// to correlate a message with a trace.
+///
+/// The IDs must be non-empty printable US-ASCII, the reserved characters are escaped when the element is written.
+///
+/// ```rust
+/// use syslog_fmt::v5424;
+///
+/// let mut buf = Vec::<u8>::new();
+/// let trace = v5424::trace_element("trace@32473", "4bf92f3577b34da6a3ce929d0e0e4736", "00f067aa0ba902b7").unwrap();
+/// v5424::write_data(&mut buf, [trace]).unwrap();
+///
+/// assert_eq!(
+///     buf,
+///     br#" [trace@32473 traceId="4bf92f3577b34da6a3ce929d0e0e4736" spanId="00f067aa0ba902b7"]"#
+/// );
+/// assert!(v5424::trace_element("trace@32473", "", "00f067aa0ba902b7").is_err());
+/// ```
+pub fn trace_element<'a>(
+    sd_id: &'a SdId,
+    trace_id: &'a str,
+    span_id: &'a str,
+) -> Result<(&'a SdId, [SdParam<'a>; 2]), InvalidTraceId> {
+    let is_valid = |id: &str| !id.is_empty() && id.chars().all(is_print_us_ascii);
+
+    if !is_valid(trace_id) || !is_valid(span_id) {
+        return Err(InvalidTraceId);
+    }
+
+    Ok((sd_id, [("traceId", trace_id), ("spanId", span_id)]))
+}
+
+/// Error returned if a trace or span ID is empty or not printable US-ASCII
+#[derive(Debug)]
+pub struct InvalidTraceId;
+
+impl fmt::Display for InvalidTraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The trace or span ID is empty or not printable US-ASCII")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidTraceId {}
+
+/// Check the syntax of a BCP 47 language tag.
+///
+/// A tag consists of subtags of one to eight alphanumeric characters separated by a hyphen.
+/// The primary language subtag is alphabetic, except for a private use tag that starts with `x`.
+/// A single character subtag introduces an extension or the private use section and
+/// must be followed by at least one subtag.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5646#section-2.1)
+fn is_well_formed_language_tag(tag: &str) -> bool {
+    const PRIVATE_USE: &str = "x";
+
+    let mut subtags = tag.split('-');
+
+    let Some(language) = subtags.next() else {
+        return false;
+    };
+
+    let is_subtag =
+        |s: &str| (1..=8).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric());
+
+    let is_language = language.len() >= 2
+        && is_subtag(language)
+        && language.bytes().all(|b| b.is_ascii_alphabetic());
+    let mut private_use = language.eq_ignore_ascii_case(PRIVATE_USE);
+
+    if !(is_language || private_use) {
+        return false;
+    }
+
+    // a singleton must be followed by at least one subtag
+    let mut expect_subtag = private_use;
+
+    for subtag in subtags {
+        if !is_subtag(subtag) {
+            return false;
+        }
+
+        if private_use {
+            // everything following the private use singleton is private use
+            expect_subtag = false;
+        } else if subtag.len() == 1 {
+            if expect_subtag {
+                return false;
+            }
+            expect_subtag = true;
+            private_use = subtag.eq_ignore_ascii_case(PRIVATE_USE);
+        } else {
+            expect_subtag = false;
+        }
+    }
+
+    !expect_subtag
+}
+
+/// Split a PRI value into the facility and severity it's encoded from.
+///
+/// An error is returned if the facility number isn't known, e.g. 12 to 15 or a PRI value above 191.
+///
+/// ```rust
+/// use syslog_fmt::{v5424::decode_priority, Facility, Severity};
+///
+/// let (facility, severity) = decode_priority(165).unwrap();
+/// assert_eq!(facility, Facility::Local4);
+/// assert_eq!(severity, Severity::Notice);
+/// ```
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.2.1)
+pub fn decode_priority(priority: u8) -> Result<(Facility, Severity), IntToEnumError<Facility>> {
+    let facility = Facility::try_from(i32::from(priority / 8))?;
+    // the severity is in the low 3 bits, so it's always a known severity
+    let severity = Priority(priority & 0b111).severity();
+
+    Ok((facility, severity))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io::ErrorKind;
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_format_date_like_chrono() {
+        let datetime = chrono::Local::now();
+        let use_z = false;
+        let chrono_s = datetime.to_rfc3339_opts(chrono::SecondsFormat::Micros, use_z);
+
+        let mut buf = Vec::with_capacity(32);
+        write_chrono_datetime(&mut buf, &datetime).unwrap();
+        let s = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            chrono_s, s,
+            "syslog-fmt date formatter should be char for char equal to Chrono"
+        );
+    }
+
+    #[test]
+    fn should_format_unix_datetime() {
+        let format = |secs, nanos| {
+            let mut buf = vec![];
+            write_unix_datetime(&mut buf, secs, nanos).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(format(0, 0), "1970-01-01T00:00:00.000000Z");
+        assert_eq!(format(951_868_800, 1_000), "2000-03-01T00:00:00.000001Z");
+        assert_eq!(format(1_709_208_000, 0), "2024-02-29T12:00:00.000000Z");
+        assert_eq!(format(-1, 999_999_999), "1969-12-31T23:59:59.999999Z");
+        assert_eq!(format(-86_400 * 366, 0), "1968-12-31T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn should_format_system_time_before_epoch() {
+        use std::time::Duration;
+
+        let mut buf = vec![];
+        let time = UNIX_EPOCH - Duration::new(1, 500_000_000);
+        write_system_time(&mut buf, time).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "1969-12-31T23:59:58.500000Z"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_calculate_dates_like_chrono() {
+        use chrono::{Datelike, NaiveDate};
+
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+
+        // every day from 1600 up to 2400 covers the leap year exceptions
+        for days in -135_140..157_000 {
+            let date = epoch + chrono::Duration::days(days);
+
+            assert_eq!(
+                civil_from_days(days),
+                (i64::from(date.year()), date.month(), date.day()),
+                "{days} days since the epoch"
+            );
+        }
+    }
+
+    #[test]
+    fn should_create_timestamp_without_chrono() {
+        let mut buf = vec![];
+
+        Formatter::default()
+            .write_without_data(
+                &mut buf,
+                Severity::Info,
+                Timestamp::CreateSystemNow,
+                "msg",
+                None,
+            )
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+
+        assert_eq!(parts.timestamp.len(), "1970-01-01T00:00:00.000000Z".len());
+        assert!(parts.timestamp.ends_with('Z'));
+    }
+
+    #[test]
+    fn should_write_pri_without_leading_zeros() {
+        let pri = |facility, severity| {
+            let mut buf = vec![];
+            Config {
+                facility,
+                ..Default::default()
+            }
+            .into_formatter()
+            .write_header(&mut buf, severity, Timestamp::None, None)
+            .unwrap();
+
+            let end = buf.iter().position(|b| *b == b'>').unwrap();
+            String::from_utf8(buf[..=end].to_vec()).unwrap()
+        };
+
+        assert_eq!(pri(Facility::Kern, Severity::Emerg), "<0>");
+        assert_eq!(pri(Facility::Kern, Severity::Debug), "<7>");
+        assert_eq!(pri(Facility::User, Severity::Emerg), "<8>");
+        assert_eq!(pri(Facility::Mail, Severity::Alert), "<17>");
+        assert_eq!(pri(Facility::Local4, Severity::Notice), "<165>");
+        assert_eq!(pri(Facility::Local7, Severity::Debug), "<191>");
+
+        let mut buf = vec![];
+        Formatter::default()
+            .write_header_with_priority(
+                &mut buf,
+                Priority::try_from(0).unwrap(),
+                Timestamp::None,
+                None,
+            )
+            .unwrap();
+        assert!(buf.starts_with(b"<0>1 "));
+    }
+
+    #[test]
+    fn should_format_single_element_from_flat_params() {
+        let fmt = Formatter::default();
+        let params = [
+            ("iut", "3"),
+            ("eventSource", "Application"),
+            ("eventID", "1011"),
+        ];
+
+        let mut buf = vec![];
+        fmt.write_with_params(
+            &mut buf,
+            Severity::Notice,
+            "2003-10-11T22:14:15.003Z",
+            "a message",
+            Some("ID47"),
+            "exampleSDID@32473",
+            &params,
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                msg_id: "ID47",
+                data: r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#,
+                msg: "a message",
+                ..
+            }
+        );
+
+        let mut nested = vec![];
+        fmt.write_with_data(
+            &mut nested,
+            Severity::Notice,
+            "2003-10-11T22:14:15.003Z",
+            "a message",
+            Some("ID47"),
+            [("exampleSDID@32473", params)],
+        )
+        .unwrap();
+        assert_eq!(buf, nested);
+    }
+
+    #[test]
+    fn should_format_message_with_raw_priority() {
+        let fmt = Config {
+            facility: Facility::Kern,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let mut buf = vec![];
+        fmt.write_with_priority(
+            &mut buf,
+            Priority::try_from(165).unwrap(),
+            "2003-10-11T22:14:15.003Z",
+            "An application event log entry...",
+            Some("ID47"),
+            [("exampleSDID@32473", [("iut", "3")])],
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<165>1",
+                hostname: "mymachine.example.com",
+                msg_id: "ID47",
+                data: r#"[exampleSDID@32473 iut="3"]"#,
+                msg: "An application event log entry...",
+                ..
+            }
+        );
+
+        let mut from_severity = vec![];
+        fmt.write_header(&mut from_severity, Severity::Info, Timestamp::None, None)
+            .unwrap();
+        let mut from_priority = vec![];
+        fmt.write_header_with_priority(
+            &mut from_priority,
+            Priority::try_from(6).unwrap(),
+            Timestamp::None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(from_severity, from_priority);
+    }
+
+    #[test]
+    fn should_override_hostname_for_a_single_message() {
+        let fmt = Config {
+            hostname: Some("relay.example.com"),
+            app_name: Some("evntslog"),
+            proc_id: Some("8710"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+
+        let mut buf = vec![];
+        fmt.write_with_hostname(
+            &mut buf,
+            Severity::Notice,
+            timestamp,
+            "mymachine.example.com",
+            "a relayed message",
+            Some("ID47"),
+            [("exampleSDID@32473", [("iut", "3")])],
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                hostname: "mymachine.example.com",
+                app_name: "evntslog",
+                proc_id: "8710",
+                msg_id: "ID47",
+                data: r#"[exampleSDID@32473 iut="3"]"#,
+                msg: "a relayed message",
+                ..
+            }
+        );
+
+        // the formatter keeps its own hostname for other messages
+        buf.clear();
+        fmt.write_without_data(&mut buf, Severity::Notice, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                hostname: "relay.example.com",
+                app_name: "evntslog",
+                proc_id: "8710",
+                ..
+            }
+        );
+
+        // the NILVALUE hostname can be overridden as well
+        buf.clear();
+        Formatter::default()
+            .write_header_with_hostname(&mut buf, Severity::Notice, timestamp, "origin", None)
+            .unwrap();
+        assert!(buf.ends_with(b" origin - - -"));
+    }
+
+    #[test]
+    fn should_reject_invalid_hostname_override() {
+        let fmt = Config {
+            collect_stats: true,
+            ..Default::default()
+        }
+        .into_formatter();
+        let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+
+        let write = |hostname: &str| {
+            let mut buf = vec![];
+            let res = fmt.write_with_hostname(
+                &mut buf,
+                Severity::Info,
+                "-",
+                hostname,
+                "msg",
+                None,
+                no_data,
+            );
+            (buf, res)
+        };
+
+        let too_long = "a".repeat(256);
+        let (buf, res) = write(&too_long);
+        let err = res.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<ConfigError>(),
+            Some(&ConfigError::TooLong {
+                field: Field::Hostname,
+                len: 256,
+                max_len: 255
+            })
+        );
+        assert!(buf.is_empty());
+
+        let (buf, res) = write("my machine");
+        let err = res.unwrap_err();
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<ConfigError>(),
+            Some(&ConfigError::IllegalChar {
+                field: Field::Hostname,
+                ch: ' ',
+                index: 2
+            })
+        );
+        assert!(buf.is_empty());
+
+        let (buf, res) = write(&too_long[..255]);
+        res.unwrap();
+        assert_eq!(parse_syslog_message(&buf).hostname, &too_long[..255]);
+
+        // only the written message is counted
+        assert_eq!(fmt.stats().unwrap().total_messages(), 1);
+    }
+
+    #[test]
+    fn should_strip_trailing_dot_of_hostname_when_configured() {
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let mut buf = vec![];
+
+        let fmt = Config {
+            hostname: Some("mymachine.example.com."),
+            ..Default::default()
+        }
+        .into_formatter();
+        fmt.write_without_data(&mut buf, Severity::Notice, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.hostname, "mymachine.example.com.");
+
+        let fmt = Config {
+            hostname: Some("mymachine.example.com."),
+            hostname_trailing_dot: TrailingDot::Strip,
+            ..Default::default()
+        }
+        .into_formatter();
+        buf.clear();
+        fmt.write_without_data(&mut buf, Severity::Notice, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.hostname, "mymachine.example.com");
+
+        // a hostname given per message is stripped as well
+        buf.clear();
+        fmt.write_header_with_hostname(&mut buf, Severity::Notice, timestamp, "origin.", None)
+            .unwrap();
+        assert!(buf.ends_with(b" origin - - -"));
+
+        // a hostname without a trailing dot is written as is
+        let fmt = Config {
+            hostname: Some("mymachine"),
+            hostname_trailing_dot: TrailingDot::Strip,
+            ..Default::default()
+        }
+        .into_formatter();
+        buf.clear();
+        fmt.write_without_data(&mut buf, Severity::Notice, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.hostname, "mymachine");
+    }
+
+    #[test]
+    fn should_annotate_the_byte_range_of_each_field() {
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        let annotated = fmt
+            .format_annotated(
+                Severity::Notice,
+                timestamp,
+                "An application event log entry...",
+                Some("ID47"),
+                data,
+            )
+            .unwrap();
+
+        let mut buf = vec![];
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Notice,
+            timestamp,
+            "An application event log entry...",
+            Some("ID47"),
+            data,
+        )
+        .unwrap();
+        assert_eq!(annotated.bytes, buf);
+
+        let fields: Vec<(Field, &str)> = annotated
+            .fields
+            .iter()
+            .map(|(field, range)| (*field, std::str::from_utf8(&buf[range.clone()]).unwrap()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (Field::Pri, "<165>"),
+                (Field::Version, "1"),
+                (Field::Timestamp, timestamp),
+                (Field::Hostname, "mymachine.example.com"),
+                (Field::AppName, "evntslog"),
+                (Field::ProcId, NILVALUE),
+                (Field::MsgId, "ID47"),
+                (Field::StructuredData, r#"[exampleSDID@32473 iut="3"]"#),
+                (Field::Msg, "\u{feff}An application event log entry..."),
+            ]
+        );
+
+        let annotated = Formatter::default()
+            .format_annotated(Severity::Notice, Timestamp::None, "", None, data)
+            .unwrap();
+        assert_eq!(annotated.get(Field::Timestamp), Some(&b"-"[..]));
+        assert_eq!(annotated.get(Field::Msg), Some(&b""[..]));
+        assert_eq!(
+            annotated.fields.last(),
+            Some(&(Field::Msg, annotated.bytes.len()..annotated.bytes.len()))
+        );
+    }
+
+    #[test]
+    fn should_annotate_the_bytes_of_write_with_data_for_any_config() {
+        let configs = [
+            Config::default(),
+            Config {
+                hostname: Some("mymachine.example.com"),
+                app_name: Some("evntslog"),
+                proc_id: Some("8710"),
+                omit_version: true,
+                trailing_nul: true,
+                ..Default::default()
+            },
+            Config {
+                pri_sd_id: Some("pri@32473"),
+                max_len: Some(80),
+                ..Default::default()
+            },
+        ];
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        for config in configs {
+            let hostname = config.hostname.unwrap_or(NILVALUE);
+            let app_name = config.app_name.unwrap_or(NILVALUE);
+            let fmt = config.into_formatter();
+            let msg = "An application event log entry...";
+
+            let annotated = fmt
+                .format_annotated(Severity::Notice, "-", msg, Some("ID47"), data)
+                .unwrap();
+
+            let mut buf = vec![];
+            fmt.write_with_data(&mut buf, Severity::Notice, "-", msg, Some("ID47"), data)
+                .unwrap();
+            assert_eq!(annotated.bytes, buf);
+
+            for pair in annotated.fields.windows(2) {
+                let [(_, prev), (field, range)] = pair else {
+                    unreachable!()
+                };
+                assert!(prev.end <= range.start, "{field:?} overlaps");
+            }
+            assert_eq!(annotated.get(Field::Hostname), Some(hostname.as_bytes()));
+            assert_eq!(annotated.get(Field::AppName), Some(app_name.as_bytes()));
+            assert_eq!(annotated.get(Field::MsgId), Some(&b"ID47"[..]));
+        }
+    }
+
+    #[test]
+    fn should_estimate_upper_bound_of_len() {
+        struct Order(u32);
+
+        impl fmt::Display for Order {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "order {} shipped", self.0)
+            }
+        }
+
+        let plain = Formatter::default();
+        let configured = Config {
+            facility: Facility::Auth,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            proc_id: Some("8710"),
+            pri_sd_id: Some("pri@32473"),
+            trailing_nul: true,
+            ..Default::default()
+        }
+        .into_formatter();
+        let limited = Config {
+            max_len: Some(64),
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let long_msg = "x".repeat(200);
+        let order = Order(7);
+        let msgs = [
+            Msg::Utf8Str(""),
+            Msg::Utf8Str("a message"),
+            Msg::Utf8Str(&long_msg),
+            Msg::NonUnicodeBytes(b"\xff\xfe"),
+            Msg::Display(&order),
+        ];
+        let data = [
+            (
+                "exampleSDID@32473",
+                vec![("iut", "3"), ("path", r#"C:\ "x" [y]"#)],
+            ),
+            ("origin", vec![]),
+        ];
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        let timestamps = [
+            Timestamp::from("2003-08-24T05:14:15.000003-07:00"),
+            Timestamp::None,
+            // longer than any timestamp the formatter creates
+            Timestamp::from("2003-08-24T05:14:15.000000003-07:00 (preformatted)"),
+            // a year beyond 9999 has more than 4 digits
+            Timestamp::Epoch {
+                secs: 400_000_000_000,
+                nanos: 0,
+            },
+        ];
+
+        for fmt in [&plain, &configured, &limited] {
+            for msg in &msgs {
+                for msg_id in [None, Some("ID47")] {
+                    for severity in Severity::all() {
+                        let mut buf = vec![];
+                        fmt.write_with_data(
+                            &mut buf,
+                            severity,
+                            Timestamp::CreateSystemNow,
+                            msg.reborrow(),
+                            msg_id,
+                            data.clone(),
+                        )
+                        .unwrap();
+                        let estimate = fmt.estimated_len(
+                            &Timestamp::CreateSystemNow,
+                            msg,
+                            msg_id,
+                            data.clone(),
+                        );
+                        assert!(buf.len() <= estimate, "{} > {estimate}", buf.len());
+                        assert!(estimate - buf.len() <= 32, "{estimate} isn't tight");
+
+                        for timestamp in &timestamps {
+                            buf.clear();
+                            fmt.write_with_data(
+                                &mut buf,
+                                severity,
+                                timestamp.reborrow(),
+                                msg.reborrow(),
+                                msg_id,
+                                no_data,
+                            )
+                            .unwrap();
+                            let estimate = fmt.estimated_len(timestamp, msg, msg_id, no_data);
+                            assert!(buf.len() <= estimate, "{} > {estimate}", buf.len());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn should_write_facility_and_severity_names_when_configured() {
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: Some("mymachine.example.com"),
+            pri_sd_id: Some("pri@32473"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Crit, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<34>1",
+                data: r#"[pri@32473 facility="Auth" severity="Crit"]"#,
+                msg: "a message",
+                ..
+            }
+        );
+
+        buf.clear();
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Notice,
+            timestamp,
+            "a message",
+            None,
+            [("exampleSDID@32473", [("iut", "3")])],
+        )
+        .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<37>1",
+                data: r#"[pri@32473 facility="Auth" severity="Notice"][exampleSDID@32473 iut="3"]"#,
+                ..
+            }
+        );
+
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        buf.clear();
+        fmt.write_with_priority(
+            &mut buf,
+            Priority::try_from(165).unwrap(),
+            timestamp,
+            "a message",
+            None,
+            no_data,
+        )
+        .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                data: r#"[pri@32473 facility="Local4" severity="Notice"]"#,
+                ..
+            }
+        );
+
+        // the element is opt-in
+        buf.clear();
+        Formatter::default()
+            .write_without_data(&mut buf, Severity::Crit, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(parts, Parts { data: NILVALUE, .. });
+    }
+
+    #[test]
+    fn should_track_max_severity_when_configured() {
+        let fmt = Config {
+            track_max_severity: true,
+            ..Default::default()
+        }
+        .into_formatter();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        assert_eq!(fmt.max_severity(), None);
+
+        let mut buf = vec![];
+        for (severity, max_severity) in [
+            (Severity::Debug, Severity::Debug),
+            (Severity::Warning, Severity::Warning),
+            (Severity::Info, Severity::Warning),
+            (Severity::Crit, Severity::Crit),
+            (Severity::Err, Severity::Crit),
+        ] {
+            fmt.write_without_data(&mut buf, severity, timestamp, "a message", None)
+                .unwrap();
+            assert_eq!(fmt.max_severity(), Some(max_severity));
+        }
+
+        // a raw PRI value is tracked as well
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        let priority = Priority::new(Facility::Kern, Severity::Alert);
+        fmt.write_with_priority(&mut buf, priority, timestamp, "a message", None, no_data)
+            .unwrap();
+        assert_eq!(fmt.max_severity(), Some(Severity::Alert));
+
+        buf.clear();
+        fmt.write_max_severity_summary(&mut buf, timestamp, "session@32473")
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<134>1",
+                data: r#"[session@32473 maxSeverity="alert"]"#,
+                msg: "",
+                ..
+            }
+        );
+
+        // the summary resets the tracked severity
+        assert_eq!(fmt.max_severity(), None);
+        buf.clear();
+        fmt.write_max_severity_summary(&mut buf, timestamp, "session@32473")
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.data, "[session@32473]");
+
+        // each severity is written as its lowercase keyword
+        for severity in Severity::all() {
+            fmt.write_without_data(&mut buf, severity, timestamp, "a message", None)
+                .unwrap();
+            buf.clear();
+            fmt.write_max_severity_summary(&mut buf, timestamp, "session@32473")
+                .unwrap();
+            let keyword = severity.to_string().to_lowercase();
+            let parts = parse_syslog_message(&buf);
+            assert_eq!(
+                parts.data,
+                format!(r#"[session@32473 maxSeverity="{keyword}"]"#)
+            );
+        }
+
+        // tracking is opt-in
+        let fmt = Formatter::default();
+        fmt.write_without_data(&mut buf, Severity::Emerg, timestamp, "a message", None)
+            .unwrap();
+        assert_eq!(fmt.max_severity(), None);
+    }
+
+    #[test]
+    fn should_write_schema_version_when_configured() {
+        let schema_version = Some(SchemaVersion {
+            sd_id: "schema@32473",
+            version: "2",
+        });
+        let fmt = Config {
+            schema_version,
+            ..Default::default()
+        }
+        .into_formatter();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                data: r#"[schema@32473 version="2"]"#,
+                msg: "a message",
+                ..
+            }
+        );
+
+        buf.clear();
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            timestamp,
+            "a message",
+            None,
+            [("exampleSDID@32473", [("iut", "3")])],
+        )
+        .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            r#"[schema@32473 version="2"][exampleSDID@32473 iut="3"]"#
+        );
+
+        // the schema element follows the PRI element
+        let fmt = Config {
+            facility: Facility::Auth,
+            pri_sd_id: Some("pri@32473"),
+            schema_version,
+            ..Default::default()
+        }
+        .into_formatter();
+        buf.clear();
+        fmt.write_without_data(&mut buf, Severity::Crit, timestamp, "a message", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            r#"[pri@32473 facility="Auth" severity="Crit"][schema@32473 version="2"]"#
+        );
+    }
+
+    #[test]
+    fn should_warn_about_deviations_from_the_spec() {
+        let fmt = Config {
+            hostname: None,
+            app_name: Some("app\tname"),
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let (bytes, warnings) = fmt
+            .format_and_validate(
+                Severity::Info,
+                "2003-10-11T22:14:15.003Z",
+                Msg::NonUnicodeBytes(b"\xFFbytes"),
+                Some("a very long message id that is too long"),
+                [("na@me@32473", [("iut", "3")])],
+            )
+            .unwrap();
+
+        let mut buf = vec![];
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            "2003-10-11T22:14:15.003Z",
+            Msg::NonUnicodeBytes(b"\xFFbytes"),
+            Some("a very long message id that is too long"),
+            [("na@me@32473", [("iut", "3")])],
+        )
+        .unwrap();
+        assert_eq!(bytes, buf);
+
+        assert_eq!(
+            warnings,
+            [
+                Warning::Field(ConfigError::MissingHostname),
+                Warning::Field(ConfigError::IllegalChar {
+                    field: Field::AppName,
+                    ch: '\t',
+                    index: 3
+                }),
+                Warning::Field(ConfigError::IllegalChar {
+                    field: Field::MsgId,
+                    ch: ' ',
+                    index: 1
+                }),
+                Warning::Field(ConfigError::TooLong {
+                    field: Field::MsgId,
+                    len: 39,
+                    max_len: 32
+                }),
+                Warning::SdId(SdError::IllegalChar { ch: '@', index: 5 }),
+                Warning::BomOmitted,
+                Warning::NonUtf8Msg,
+            ]
+        );
+
+        let fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let (_, warnings) = fmt
+            .format_and_validate(
+                Severity::Info,
+                Timestamp::CreateSystemNow,
+                "a message",
+                Some("ID47"),
+                [("exampleSDID@32473", [("iut", "3")])],
+            )
+            .unwrap();
+        assert_eq!(warnings, []);
+    }
+
+    #[test]
+    fn should_write_each_timestamp_variant() {
+        fn timestamp_to_string<'a>(timestamp: impl Into<Timestamp<'a>>) -> String {
+            let mut buf = vec![];
+            write_timestamp(&mut buf, timestamp).unwrap();
+            String::from_utf8(buf).unwrap()
+        }
+
+        assert_eq!(
+            timestamp_to_string("2003-10-11T22:14:15.003Z"),
+            "2003-10-11T22:14:15.003Z"
+        );
+        assert_eq!(
+            timestamp_to_string("2003-08-24T05:14:15.000003-07:00".to_owned()),
+            "2003-08-24T05:14:15.000003-07:00"
+        );
+        assert_eq!(timestamp_to_string(Timestamp::None), "-");
+
+        let now = timestamp_to_string(Timestamp::CreateSystemNow);
+        assert_eq!(now.len(), "1970-01-01T00:00:00.000000Z".len());
+        assert!(now.ends_with('Z'));
+
+        #[cfg(feature = "chrono")]
+        {
+            use chrono::TimeZone;
+
+            let datetime = chrono::Local
+                .timestamp_opt(1_065_910_455, 3_000_000)
+                .unwrap();
+            let s = timestamp_to_string(&datetime);
+            assert!(s.contains("T"));
+            assert!(s.contains(".003000"));
+
+            let s = timestamp_to_string(Timestamp::CreateChronoLocal);
+            assert_eq!(s.len(), "1970-01-01T00:00:00.000000+00:00".len());
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn should_format_chrono_utc_timestamp_with_z() {
+        use chrono::TimeZone;
+
+        let timestamp_to_string = |timestamp: Timestamp<'_>| {
+            let mut buf = vec![];
+            write_timestamp(&mut buf, timestamp).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let datetime = chrono::Utc.timestamp_opt(1_065_910_455, 3_000).unwrap();
+        assert_eq!(
+            timestamp_to_string((&datetime).into()),
+            "2003-10-11T22:14:15.000003Z"
+        );
+
+        // a leap second
+        let datetime = chrono::Utc
+            .timestamp_opt(1_483_228_799, 1_000_000_000)
+            .unwrap();
+        assert_eq!(
+            timestamp_to_string(Timestamp::ChronoUtc(&datetime)),
+            "2016-12-31T23:59:59.999999Z"
+        );
+
+        let now = timestamp_to_string(Timestamp::CreateChronoUtc);
+        assert_eq!(now.len(), "1970-01-01T00:00:00.000000Z".len());
+        assert!(now.ends_with('Z'));
+        assert_eq!(now.find('.'), Some(19));
+    }
+
+    #[test]
+    fn should_format_epoch_timestamp() {
+        let timestamp_to_string = |secs, nanos| {
+            let mut buf = vec![];
+            write_timestamp(&mut buf, Timestamp::Epoch { secs, nanos }).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(timestamp_to_string(0, 0), "1970-01-01T00:00:00.000000Z");
+        assert_eq!(
+            timestamp_to_string(1_760_572_800, 123_456_789),
+            "2025-10-16T00:00:00.123456Z"
+        );
+        assert_eq!(
+            timestamp_to_string(-1, 999_999_000),
+            "1969-12-31T23:59:59.999999Z"
+        );
+        assert_eq!(
+            timestamp_to_string(-14_182_940, 0),
+            "1969-07-20T20:17:40.000000Z"
+        );
+        assert_eq!(
+            timestamp_to_string(0, NANOS_IN_SEC),
+            "1970-01-01T00:00:00.999999Z"
+        );
+    }
+
+    #[test]
+    fn should_format_system_time_timestamp() {
+        use std::time::Duration;
+
+        let timestamp_to_string = |time: SystemTime| {
+            let mut buf = vec![];
+            write_timestamp(&mut buf, time).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(
+            timestamp_to_string(UNIX_EPOCH),
+            "1970-01-01T00:00:00.000000Z"
+        );
+        assert_eq!(
+            timestamp_to_string(UNIX_EPOCH + Duration::from_secs(1_065_910_455)),
+            "2003-10-11T22:14:15.000000Z"
+        );
+        assert_eq!(
+            timestamp_to_string(UNIX_EPOCH + Duration::new(482_196_050, 520_000_000)),
+            "1985-04-12T23:20:50.520000Z"
+        );
+        assert_eq!(
+            timestamp_to_string(UNIX_EPOCH + Duration::new(951_782_400, 1_999)),
+            "2000-02-29T00:00:00.000001Z"
+        );
+        assert_eq!(
+            timestamp_to_string(UNIX_EPOCH - Duration::from_micros(1)),
+            "1969-12-31T23:59:59.999999Z"
+        );
+
+        let mut buf = vec![];
+        Formatter::default()
+            .write_without_data(
+                &mut buf,
+                Severity::Info,
+                Timestamp::SystemTime(UNIX_EPOCH),
+                "msg",
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            parse_syslog_message(&buf).timestamp,
+            "1970-01-01T00:00:00.000000Z"
+        );
+    }
+
+    #[test]
+    fn should_resolve_deferred_timestamp_at_write_time() {
+        use std::{cell::Cell, time::Duration};
+
+        struct FixedClock(Cell<SystemTime>);
+
+        impl Clock for FixedClock {
+            fn now(&self) -> SystemTime {
+                self.0.get()
+            }
+        }
+
+        let clock = FixedClock(Cell::new(UNIX_EPOCH + Duration::from_secs(1_065_910_455)));
+        let fmt = Formatter::default();
+
+        // the message is queued, the clock advances before it's written
+        let queued = (Timestamp::Deferred(&clock), "a queued message");
+        let queued_at = clock.now();
+        clock.0.set(queued_at + Duration::from_millis(1_500));
+
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, queued.0, queued.1, None)
+            .unwrap();
+        assert_eq!(
+            parse_syslog_message(&buf).timestamp,
+            "2003-10-11T22:14:16.500000Z"
+        );
+
+        let mut at_queue_time = vec![];
+        write_timestamp(&mut at_queue_time, queued_at).unwrap();
+        assert_eq!(at_queue_time, b"2003-10-11T22:14:15.000000Z");
+    }
+
+    #[test]
+    fn should_format_timestamp_from_components() {
+        let components = |offset_minutes| Timestamp::Components {
+            year: 2003,
+            month: 8,
+            day: 4,
+            hour: 5,
+            min: 4,
+            sec: 3,
+            nanos: 3_000,
+            offset_minutes,
+        };
+
+        let timestamp_to_string = |timestamp: Timestamp<'_>| {
+            let mut buf = vec![];
+            write_timestamp(&mut buf, timestamp).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(
+            timestamp_to_string(components(0)),
+            "2003-08-04T05:04:03.000003Z"
+        );
+        assert_eq!(
+            timestamp_to_string(components(-7 * 60)),
+            "2003-08-04T05:04:03.000003-07:00"
+        );
+        assert_eq!(
+            timestamp_to_string(components(5 * 60 + 30)),
+            "2003-08-04T05:04:03.000003+05:30"
+        );
+        assert_eq!(
+            timestamp_to_string(components(-45)),
+            "2003-08-04T05:04:03.000003-00:45"
+        );
+
+        let timestamp = Timestamp::Components {
+            year: 1985,
+            month: 12,
+            day: 31,
+            hour: 23,
+            min: 59,
+            sec: 59,
+            nanos: 999_999_999,
+            offset_minutes: 14 * 60,
+        };
+        assert_eq!(
+            timestamp_to_string(timestamp),
+            "1985-12-31T23:59:59.999999+14:00"
+        );
+    }
+
+    #[cfg(all(feature = "chrono", feature = "time"))]
+    #[test]
+    fn should_format_time_like_chrono() {
+        use chrono::TimeZone;
+
+        let chrono_datetime = chrono::Local
+            .timestamp_opt(1_065_910_455, 3_000_000)
+            .unwrap();
+        let offset =
+            time::UtcOffset::from_whole_seconds(chrono_datetime.offset().local_minus_utc())
+                .unwrap();
+        let time_datetime =
+            time::OffsetDateTime::from_unix_timestamp_nanos(1_065_910_455_003_000_000)
+                .unwrap()
+                .to_offset(offset);
+
+        let mut chrono_buf = vec![];
+        write_timestamp(&mut chrono_buf, &chrono_datetime).unwrap();
+        let mut time_buf = vec![];
+        write_timestamp(&mut time_buf, &time_datetime).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&time_buf).unwrap(),
+            std::str::from_utf8(&chrono_buf).unwrap()
+        );
+    }
+
+    #[cfg(all(feature = "chrono", feature = "time"))]
+    #[test]
+    fn should_format_time_like_chrono_for_any_offset_and_subsec() {
+        use chrono::TimeZone;
+
+        let instants = [
+            (1_065_910_455, 0),
+            (1_065_910_455, 3_000),
+            (1_065_910_455, 3_000_000),
+            (1_061_702_055, 999_999_999),
+            (-1, 500_000_000),
+            (0, 1),
+        ];
+        let offsets_mins = [
+            0,
+            2 * 60,
+            -7 * 60,
+            -30,
+            5 * 60 + 45,
+            -(9 * 60 + 30),
+            14 * 60,
+        ];
+
+        for (secs, nanos) in instants {
+            for offset_mins in offsets_mins {
+                let offset_secs = offset_mins * 60;
+                let chrono_datetime = chrono::FixedOffset::east_opt(offset_secs)
+                    .unwrap()
+                    .timestamp_opt(secs, nanos)
+                    .unwrap();
+                let time_datetime = time::OffsetDateTime::from_unix_timestamp_nanos(
+                    i128::from(secs) * 1_000_000_000 + i128::from(nanos),
+                )
+                .unwrap()
+                .to_offset(time::UtcOffset::from_whole_seconds(offset_secs).unwrap());
+
+                let mut chrono_buf = vec![];
+                write_chrono_fixed_datetime(&mut chrono_buf, &chrono_datetime).unwrap();
+                let mut time_buf = vec![];
+                write_timestamp(&mut time_buf, &time_datetime).unwrap();
+
+                assert_eq!(
+                    std::str::from_utf8(&time_buf).unwrap(),
+                    std::str::from_utf8(&chrono_buf).unwrap(),
+                    "the chrono and time timestamps of {secs}.{nanos:09} at {offset_mins} minutes differ"
+                );
+                // both match the RFC 3339 format of chrono
+                assert_eq!(
+                    std::str::from_utf8(&chrono_buf).unwrap(),
+                    chrono_datetime.to_rfc3339_opts(chrono::SecondsFormat::Micros, false)
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn should_format_time_datetime() {
+        use time::{Date, Month, PrimitiveDateTime, Time, UtcOffset};
+
+        let timestamp_to_string = |day, micros, (offset_hour, offset_min)| {
+            let date = Date::from_calendar_date(2003, Month::August, day).unwrap();
+            let time = Time::from_hms_micro(5, 14, 15, micros).unwrap();
+            let offset = UtcOffset::from_hms(offset_hour, offset_min, 0).unwrap();
+            let datetime = PrimitiveDateTime::new(date, time).assume_offset(offset);
+
+            let mut buf = vec![];
+            write_timestamp(&mut buf, &datetime).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(
+            timestamp_to_string(24, 3_000, (0, 0)),
+            "2003-08-24T05:14:15.003000+00:00"
+        );
+        assert_eq!(
+            timestamp_to_string(24, 3, (-7, 0)),
+            "2003-08-24T05:14:15.000003-07:00"
+        );
+        assert_eq!(
+            timestamp_to_string(4, 3, (0, -30)),
+            "2003-08-04T05:14:15.000003-00:30"
+        );
+        assert_eq!(
+            timestamp_to_string(4, 0, (5, 45)),
+            "2003-08-04T05:14:15.000000+05:45"
+        );
+
+        for timestamp in [Timestamp::CreateTimeUtc, Timestamp::CreateTimeLocal] {
+            let mut buf = vec![];
+            write_timestamp(&mut buf, timestamp).unwrap();
+            assert_eq!(buf.len(), "1970-01-01T00:00:00.000000+00:00".len());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_write_message_in_sections() {
+        let hostname = "mymachine.example.com";
+        let app_name = "su";
+        let severity = Severity::Crit;
+        let msg_id = "ID47";
+        let msg = "'su root' failed for lonvick on /dev/pts/8";
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+
+        fmt.write_header(
+            &mut buf,
+            severity,
+            Timestamp::CreateChronoLocal,
+            Some(msg_id),
+        )
+        .unwrap();
+
+        // we are not using any structured data
+        write_nil_value(&mut buf).unwrap();
+        write_msg(&mut buf, msg).unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<34>1",
+                timestamp: _,
+                hostname: "mymachine.example.com",
+                app_name: "su",
+                proc_id: NILVALUE,
+                msg_id,
+                data: NILVALUE,
+                msg
+            } if msg_id == msg_id && msg == msg
+        );
+    }
+
+    #[test]
+    fn should_format_message_with_system_time_now() {
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        let mut buf = vec![];
+        fmt.write_header(
+            &mut buf,
+            Severity::Crit,
+            Timestamp::CreateSystemNow,
+            Some("ID47"),
+        )
+        .unwrap();
+        write_nil_value(&mut buf).unwrap();
+        write_msg(&mut buf, "in sections").unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.prio, "<162>1");
+        assert!(parts.timestamp.ends_with('Z'));
+        assert_eq!(parts.msg_id, "ID47");
+        assert_eq!(parts.msg, "in sections");
+
+        let mut buf = vec![];
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Notice,
+            Timestamp::CreateSystemNow,
+            "with data",
+            Some("ID47"),
+            data,
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.prio, "<165>1");
+        assert!(parts.timestamp.ends_with('Z'));
+        assert_eq!(parts.hostname, "mymachine.example.com");
+        assert_eq!(parts.app_name, "evntslog");
+        assert_eq!(parts.data, r#"[exampleSDID@32473 iut="3"]"#);
+        assert_eq!(parts.msg, "with data");
+    }
+
+    #[test]
+    fn should_write_line_with_a_single_write() {
+        /// Records each write separately
+        #[derive(Default)]
+        struct Writes(Vec<Vec<u8>>);
+
+        impl io::Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let fmt = Formatter::default();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+
+        let mut writes = Writes::default();
+        fmt.write_line(&mut writes, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+
+        let mut expected = vec![];
+        fmt.write_without_data(&mut expected, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        expected.push(b'\n');
+
+        assert_eq!(writes.0, [expected]);
+    }
+
+    #[test]
+    fn should_soft_wrap_msg_at_column() {
+        let fmt = Formatter::default();
+        let header = "<134>1 - - - - - - ";
+        let msg = "0123456789abcdefghijklmnopqrstuvwxyzäöü";
+
+        let mut buf = vec![];
+        fmt.write_wrapped(&mut buf, Severity::Info, Timestamp::None, msg, None, 24)
+            .unwrap();
+        let s = String::from_utf8(buf).unwrap();
+
+        assert_eq!(
+            s,
+            format!(
+                "{header}\u{feff}01234\\\n\
+                56789abcdefghijklmnopqrs\\\n\
+                tuvwxyzäöü"
+            )
+        );
+        for line in s.lines() {
+            let width = line
+                .trim_end_matches('\\')
+                .replace('\u{feff}', "")
+                .chars()
+                .count();
+            assert!(width <= 24, "{line:?} is {width} chars wide");
+        }
+
+        // a short message isn't wrapped
+        let mut buf = vec![];
+        fmt.write_wrapped(&mut buf, Severity::Info, Timestamp::None, "short", None, 80)
+            .unwrap();
+        let mut unwrapped = vec![];
+        fmt.write_without_data(
+            &mut unwrapped,
+            Severity::Info,
+            Timestamp::None,
+            "short",
+            None,
+        )
+        .unwrap();
+        assert_eq!(buf, unwrapped);
+    }
+
+    #[test]
+    fn should_format_from_many_threads_with_pooled_buffers() {
+        let pool = FormatterPool::new(
+            Config {
+                hostname: Some("mymachine.example.com"),
+                app_name: Some("evntslog"),
+                ..Default::default()
+            }
+            .into_formatter(),
+        );
+
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    (0..16)
+                        .map(|i| {
+                            let mut pooled = pool.get();
+                            let (formatter, buf) = pooled.split();
+                            assert!(buf.is_empty());
+                            formatter
+                                .write_without_data(
+                                    buf,
+                                    Severity::Info,
+                                    "2003-10-11T22:14:15.003Z",
+                                    format!("message {i} of thread {thread}"),
+                                    None,
+                                )
+                                .unwrap();
+                            buf.clone()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for (thread, handle) in handles.into_iter().enumerate() {
+            for (i, msg) in handle.join().unwrap().iter().enumerate() {
+                let parts = parse_syslog_message(msg);
+                assert_matches!(
+                    parts,
+                    Parts {
+                        prio: "<134>1",
+                        hostname: "mymachine.example.com",
+                        app_name: "evntslog",
+                        ..
+                    }
+                );
+                assert_eq!(parts.msg, format!("message {i} of thread {thread}"));
+            }
+        }
+
+        // the buffers are reused, at most one per thread is created
+        let buffers = pool.inner.buffers.lock().unwrap().len();
+        assert!((1..=8).contains(&buffers));
+    }
+
+    #[cfg(feature = "hostname")]
+    #[test]
+    fn should_detect_hostname() {
+        let hostname = detect_hostname();
+        assert_matches!(hostname.as_deref(), Some(hostname) if !hostname.is_empty());
+    }
+
+    #[test]
+    fn should_replace_proc_id_of_copy() {
+        let fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("worker"),
+            proc_id: Some("8710"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let write = |fmt: &Formatter| {
+            let mut buf = vec![];
+            fmt.write_without_data(&mut buf, Severity::Info, "-", "msg", None)
+                .unwrap();
+            buf
+        };
+
+        let restarted = fmt.with_proc_id(Some("8711")).unwrap();
+        let buf = write(&restarted);
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.hostname, "mymachine.example.com");
+        assert_eq!(parts.app_name, "worker");
+        assert_eq!(parts.proc_id, "8711");
+        assert_eq!(parse_syslog_message(&write(&fmt)).proc_id, "8710");
+
+        let reset = restarted.with_proc_id(None).unwrap();
+        assert_eq!(parse_syslog_message(&write(&reset)).proc_id, NILVALUE);
+        let reset = restarted.with_proc_id(Some("")).unwrap();
+        assert_eq!(parse_syslog_message(&write(&reset)).proc_id, NILVALUE);
+
+        // the hostname can still be replaced per message
+        let mut buf = vec![];
+        reset
+            .write_with_hostname(
+                &mut buf,
+                Severity::Info,
+                "-",
+                "other",
+                "msg",
+                None,
+                [("a@32473", [])],
+            )
+            .unwrap();
+        assert!(buf.starts_with(b"<134>1 - other worker - -"));
+
+        let mut session = Config {
+            session_proc_id: true,
+            ..Default::default()
+        }
+        .into_formatter()
+        .with_proc_id(Some("8710"))
+        .unwrap();
+        assert_eq!(session.new_session(), None);
+        assert_eq!(parse_syslog_message(&write(&session)).proc_id, "8710");
+    }
+
+    #[test]
+    fn should_check_proc_id_of_copied_formatter() {
+        let long = "a".repeat(PROC_ID_MAX_LEN + 72);
+
+        let fmt = Config::default().into_formatter();
+        assert_eq!(
+            fmt.with_proc_id(Some("pid 8710")).unwrap_err(),
+            ConfigError::IllegalChar {
+                field: Field::ProcId,
+                ch: ' ',
+                index: 3
+            }
+        );
+        assert_eq!(
+            fmt.with_proc_id(Some(&long)).unwrap_err(),
+            ConfigError::TooLong {
+                field: Field::ProcId,
+                len: long.len(),
+                max_len: PROC_ID_MAX_LEN
+            }
+        );
+
+        let fmt = Config {
+            proc_id_overflow: ProcIdOverflow::Truncate,
+            ..Default::default()
+        }
+        .into_formatter();
+        let truncated = fmt.with_proc_id(Some(&long)).unwrap();
+        let buf = truncated
+            .format_to_vec(Severity::Info, "-", "msg", None)
+            .unwrap();
+        assert_eq!(parse_syslog_message(&buf).proc_id, &long[..PROC_ID_MAX_LEN]);
+        assert!(fmt.with_proc_id(Some("pid 8710")).is_err());
+    }
+
+    #[test]
+    fn should_write_process_id_as_proc_id_when_configured() {
+        let fmt = Config {
+            proc_id: Some("ignored"),
+            process_proc_id: true,
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let buf = fmt.format_to_vec(Severity::Info, "-", "msg", None).unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.proc_id, std::process::id().to_string());
+
+        let fmt = Config {
+            process_proc_id: true,
+            session_proc_id: true,
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let buf = fmt.format_to_vec(Severity::Info, "-", "msg", None).unwrap();
+        assert_eq!(parse_syslog_message(&buf).proc_id, "1");
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn should_write_to_bytes_mut() {
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        let mut expected = vec![];
+        for msg in ["first", "second"] {
+            fmt.write_with_data(
+                &mut expected,
+                Severity::Notice,
+                timestamp,
+                msg,
+                Some("ID47"),
+                data,
+            )
+            .unwrap();
+        }
+
+        let mut buf = bytes::BytesMut::new();
+        for msg in ["first", "second"] {
+            fmt.write_to_bytes_mut(
+                &mut buf,
+                Severity::Notice,
+                timestamp,
+                msg,
+                Some("ID47"),
+                data,
+            )
+            .unwrap();
+        }
+        assert_eq!(&buf[..], &expected[..]);
+
+        // the reserved capacity fits a message without structured data
+        let mut buf = bytes::BytesMut::new();
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        fmt.write_to_bytes_mut(
+            &mut buf,
+            Severity::Notice,
+            "2003-08-24T05:14:15.000003-07:00",
+            "a message",
+            Some("ID47"),
+            no_data,
+        )
+        .unwrap();
+        let capacity = buf.capacity();
+        assert!(buf.len() <= capacity);
+        buf.clear();
+        fmt.write_to_bytes_mut(
+            &mut buf,
+            Severity::Notice,
+            timestamp,
+            "a message",
+            None,
+            no_data,
+        )
+        .unwrap();
+        assert_eq!(buf.capacity(), capacity);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn should_normalize_msg_and_param_values_to_nfc() {
+        let mut buf = Vec::<u8>::new();
+        Formatter::default()
+            .write_nfc(
+                &mut buf,
+                Severity::Info,
+                "2003-10-11T22:14:15.003Z",
+                format_args!("cafe\u{301} {}", "A\u{30a}"),
+                None,
+                [("exampleSDID@32473", [("name", "Rene\u{301}")])],
+            )
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.data, "[exampleSDID@32473 name=\"Ren\u{e9}\"]");
+        assert_eq!(parts.msg, "caf\u{e9} \u{c5}");
+
+        let mut buf = Vec::<u8>::new();
+        Formatter::default()
+            .write_nfc(
+                &mut buf,
+                Severity::Info,
+                "2003-10-11T22:14:15.003Z",
+                Msg::NonUnicodeBytes("cafe\u{301}".as_bytes()),
+                None,
+                [("exampleSDID@32473", [])],
+            )
+            .unwrap();
+
+        assert!(buf.ends_with("cafe\u{301}".as_bytes()));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn should_pass_self_check_with_valid_data() {
+        let mut buf = Vec::<u8>::new();
+        Formatter::default()
+            .write_checked(
+                &mut buf,
+                Severity::Info,
+                "2003-10-11T22:14:15.003Z",
+                "a message",
+                None,
+                [
+                    ("exampleSDID@32473", vec![("iut", "3"), ("eventID", "1011")]),
+                    ("examplePriority@32473", vec![]),
+                ],
+            )
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            r#"[exampleSDID@32473 iut="3" eventID="1011"][examplePriority@32473]"#
+        );
+        assert_eq!(parts.msg, "a message");
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn should_pass_self_check_with_escaped_chars_in_value() {
+        let mut buf = Vec::<u8>::new();
+        Formatter::default()
+            .write_checked(
+                &mut buf,
+                Severity::Info,
+                "2003-10-11T22:14:15.003Z",
+                "a message",
+                None,
+                [("exampleSDID@32473", [("note", r#"he said "hi" \ ]"#)])],
+            )
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            r#"[exampleSDID@32473 note="he said \"hi\" \\ \]"]"#
+        );
+        assert_eq!(parts.msg, "a message");
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn should_fail_self_check_with_unescaped_chars_in_value() {
+        assert_eq!(
+            check_structured_data(r#"[exampleSDID@32473 note="a ] b"]"#),
+            Err("a PARAM-VALUE contains an unescaped ']'")
+        );
+        assert_eq!(
+            check_structured_data(r#"[exampleSDID@32473 note="he said "hi""]"#),
+            Err("an SD-PARAM isn't preceded by a space")
+        );
+        assert_eq!(
+            check_structured_data(r#"[exampleSDID@32473 note="end\"]"#),
+            Err("the structured data doesn't end with a complete SD-ELEMENT")
+        );
+    }
+
+    #[test]
+    fn should_write_sd_elements_without_separator() {
+        let data = [
+            ("a@32473", [("iut", "3")]),
+            ("b@32473", [("iut", "4")]),
+            ("c@32473", [("iut", "5")]),
+        ];
+        let expected = r#"[a@32473 iut="3"][b@32473 iut="4"][c@32473 iut="5"]"#;
+
+        let mut buf = vec![];
+        write_data(&mut buf, data).unwrap();
+        assert_eq!(buf, format!(" {expected}").as_bytes());
+
+        let fmt = Formatter::default();
+        buf.clear();
+        fmt.write_with_data(&mut buf, Severity::Info, "-", "a message", None, data)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.data, expected);
+
+        // the elements built from an iterator are adjacent as well
+        buf.clear();
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            "-",
+            "a message",
+            None,
+            data.iter()
+                .map(|(id, params)| (*id, params.iter().copied())),
+        )
+        .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.data, expected);
+
+        // the configured PRI element is directly followed by the other elements
+        let fmt = Config {
+            pri_sd_id: Some("pri@32473"),
+            ..Default::default()
+        }
+        .into_formatter();
+        buf.clear();
+        fmt.write_with_data(&mut buf, Severity::Info, "-", "a message", None, data)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            format!(r#"[pri@32473 facility="Local0" severity="Info"]{expected}"#)
+        );
+
+        #[cfg(debug_assertions)]
+        {
+            assert_eq!(check_structured_data(expected), Ok(()));
+            assert_eq!(
+                check_structured_data(r#"[a@32473 iut="3"] [b@32473 iut="4"]"#),
+                Err("an SD-ELEMENT is preceded by whitespace")
+            );
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "a PARAM-NAME is invalid")]
+    fn should_fail_self_check_with_illegal_param_name() {
+        let mut buf = Vec::<u8>::new();
+        Formatter::default()
+            .write_checked(
+                &mut buf,
+                Severity::Info,
+                "2003-10-11T22:14:15.003Z",
+                "a message",
+                None,
+                [("exampleSDID@32473", [("my note", "3")])],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn should_summarize_elements_beyond_the_max() {
+        let mut fmt = Config {
+            data: DataConfig {
+                max_elements: Some(MaxElements {
+                    max: 2,
+                    summary_sd_id: "truncated@32473",
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let data = [
+            ("a@32473", [("n", "1")]),
+            ("b@32473", [("n", "2")]),
+            ("c@32473", [("n", "3")]),
+            ("d@32473", [("n", "4")]),
+            ("e@32473", [("n", "5")]),
+        ];
+
+        let mut buf = Vec::<u8>::new();
+        fmt.write_with_data(&mut buf, Severity::Info, "-", "msg", None, data)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            r#"[a@32473 n="1"][b@32473 n="2"][truncated@32473 dropped="3"]"#
+        );
+        assert_eq!(parts.msg, "msg");
+
+        let mut buf = Vec::<u8>::new();
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            "-",
+            "msg",
+            None,
+            data[..2].iter().copied(),
+        )
+        .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.data, r#"[a@32473 n="1"][b@32473 n="2"]"#);
+
+        fmt.data.max_elements = Some(MaxElements {
+            max: 0,
+            summary_sd_id: "truncated@32473",
+        });
+        let mut buf = Vec::<u8>::new();
+        fmt.write_with_data(&mut buf, Severity::Info, "-", "msg", None, data)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.data, r#"[truncated@32473 dropped="5"]"#);
+    }
+
+    #[test]
+    fn should_separate_header_fields_by_a_single_space() {
+        let options = [None, Some(""), Some("value")];
+
+        for hostname in options {
+            for app_name in options {
+                for proc_id in options {
+                    for msg_id in options {
+                        let fmt = Config {
+                            hostname,
+                            app_name,
+                            proc_id,
+                            ..Default::default()
+                        }
+                        .into_formatter();
+
+                        let mut buf = vec![];
+                        fmt.write_without_data(
+                            &mut buf,
+                            Severity::Info,
+                            "2003-10-11T22:14:15.003Z",
+                            "a message",
+                            msg_id,
+                        )
+                        .unwrap();
+
+                        let expected_field = |field: Option<&'static str>| match field {
+                            None | Some("") => NILVALUE,
+                            Some(value) => value,
+                        };
+                        let expected = format!(
+                            "<134>1 2003-10-11T22:14:15.003Z {} {} {} {} - \u{feff}a message",
+                            expected_field(hostname),
+                            expected_field(app_name),
+                            expected_field(proc_id),
+                            expected_field(msg_id),
+                        );
+                        assert_eq!(
+                            String::from_utf8(buf).unwrap(),
+                            expected,
+                            "hostname: {hostname:?}, app_name: {app_name:?}, proc_id: {proc_id:?}, msg_id: {msg_id:?}"
+                        );
+
+                        let mut buf = vec![];
+                        fmt.write_header_with_hostname(
+                            &mut buf,
+                            Severity::Info,
+                            "2003-10-11T22:14:15.003Z",
+                            "",
+                            msg_id,
+                        )
+                        .unwrap();
+
+                        let expected = format!(
+                            "<134>1 2003-10-11T22:14:15.003Z - {} {} {}",
+                            expected_field(app_name),
+                            expected_field(proc_id),
+                            expected_field(msg_id),
+                        );
+                        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn should_override_proc_id_for_a_single_message() {
+        let fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            proc_id: Some("8710"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        let mut buf = vec![];
+        fmt.write_with_proc_id(
+            &mut buf,
+            Severity::Info,
+            "-",
+            "1234",
+            "msg",
+            Some("ID47"),
+            data,
+        )
+        .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.hostname, "mymachine.example.com");
+        assert_eq!(parts.app_name, "evntslog");
+        assert_eq!(parts.proc_id, "1234");
+        assert_eq!(parts.msg_id, "ID47");
+        assert_eq!(parts.data, r#"[exampleSDID@32473 iut="3"]"#);
+        assert_eq!(parts.msg, "msg");
+
+        let mut buf = vec![];
+        fmt.write_with_proc_id(&mut buf, Severity::Info, "-", "", "msg", None, data)
+            .unwrap();
+        assert_eq!(parse_syslog_message(&buf).proc_id, "-");
+
+        // the formatter is unchanged
+        let mut buf = vec![];
+        fmt.write_with_data(&mut buf, Severity::Info, "-", "msg", None, data)
+            .unwrap();
+        assert_eq!(parse_syslog_message(&buf).proc_id, "8710");
+
+        let mut buf = vec![];
+        fmt.write_with_hostname(&mut buf, Severity::Info, "-", "origin", "msg", None, data)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.hostname, "origin");
+        assert_eq!(parts.app_name, "evntslog");
+        assert_eq!(parts.proc_id, "8710");
+    }
+
+    #[test]
+    fn should_reject_pre_epoch_timestamp() {
+        use std::time::Duration;
+
+        let fmt = Config {
+            pre_epoch: PreEpoch::Reject,
+            ..Default::default()
+        }
+        .into_formatter();
+        let before_epoch = UNIX_EPOCH - Duration::from_nanos(1);
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+
+        for timestamp in [
+            Timestamp::Epoch { secs: -1, nanos: 0 },
+            Timestamp::Epoch {
+                secs: -1_000_000_000,
+                nanos: 0,
+            },
+            Timestamp::SystemTime(before_epoch),
+        ] {
+            let mut buf = vec![];
+            let err = fmt
+                .write_without_data(&mut buf, Severity::Info, timestamp.reborrow(), "", None)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert_eq!(err.to_string(), "The timestamp is before the Unix epoch");
+            // nothing is written
+            assert!(buf.is_empty());
+
+            let err = fmt
+                .format_annotated(Severity::Info, timestamp.reborrow(), "", None, no_data)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+            // by default the timestamp is written
+            let buf = Formatter::default()
+                .format_to_vec(Severity::Info, timestamp, "", None)
+                .unwrap();
+            assert!(buf.starts_with(b"<134>1 19"));
+        }
+
+        for timestamp in [
+            Timestamp::Epoch { secs: 0, nanos: 0 },
+            Timestamp::SystemTime(UNIX_EPOCH),
+            Timestamp::CreateSystemNow,
+            Timestamp::Deferred(&SystemClock),
+            Timestamp::from("1969-12-31T23:59:59.000000Z"),
+        ] {
+            assert!(fmt
+                .format_to_vec(Severity::Info, timestamp, "", None)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn should_apply_proc_id_overflow_to_proc_id_override() {
+        let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+        let write = |fmt: &Formatter, proc_id: &str| {
+            let mut buf = vec![];
+            let res = fmt.write_with_proc_id(
+                &mut buf,
+                Severity::Info,
+                "-",
+                proc_id,
+                "msg",
+                None,
+                no_data,
+            );
+            (buf, res)
+        };
+        let too_long = "f".repeat(129);
+
+        let reject = Formatter::default();
+        let (buf, res) = write(&reject, &too_long);
+        let err = res.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<ConfigError>(),
+            Some(&ConfigError::TooLong {
+                field: Field::ProcId,
+                len: 129,
+                max_len: 128
+            })
+        );
+        assert!(buf.is_empty());
+
+        let truncate = Config {
+            proc_id_overflow: ProcIdOverflow::Truncate,
+            ..Default::default()
+        }
+        .into_formatter();
+        let (buf, res) = write(&truncate, &too_long);
+        res.unwrap();
+        assert_eq!(parse_syslog_message(&buf).proc_id, &too_long[..128]);
+
+        // an illegal char is rejected by either policy
+        for fmt in [&reject, &truncate] {
+            let (buf, res) = write(fmt, "pid 8710");
+            let err = res.unwrap_err();
+            assert_eq!(
+                err.into_inner().unwrap().downcast_ref::<ConfigError>(),
+                Some(&ConfigError::IllegalChar {
+                    field: Field::ProcId,
+                    ch: ' ',
+                    index: 3
+                })
+            );
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn should_change_proc_id_on_new_session() {
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let mut fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            proc_id: Some("8710"),
+            session_proc_id: true,
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let proc_id = |fmt: &Formatter| {
+            let mut buf = vec![];
+            fmt.write_without_data(&mut buf, Severity::Info, timestamp, "a message", None)
+                .unwrap();
+            parse_syslog_message(&buf).proc_id.to_owned()
+        };
+
+        assert_eq!(proc_id(&fmt), "1");
+        assert_eq!(fmt.new_session(), Some(2));
+        assert_eq!(proc_id(&fmt), "2");
+        assert_eq!(fmt.new_session(), Some(3));
+        assert_eq!(proc_id(&fmt), "3");
+
+        let mut fmt = Config {
+            proc_id: Some("8710"),
+            ..Default::default()
+        }
+        .into_formatter();
+        assert_eq!(fmt.new_session(), None);
+        assert_eq!(proc_id(&fmt), "8710");
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_write_message_with_custom_formatting() {
+        use std::io::Write;
+
+        let hostname = "mymachine.example.com";
+        let app_name = "su";
+        let severity = Severity::Crit;
+        let msg_id = "ID47";
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+
+        fmt.write_header(
+            &mut buf,
+            severity,
+            Timestamp::CreateChronoLocal,
+            Some(msg_id),
+        )
+        .unwrap();
+
+        // we are not using any structured data
+        write_nil_value(&mut buf).unwrap();
+        write_utf8_bom(&mut buf).unwrap();
+
+        let msg = "'su root' failed for lonvick on /dev/pts/8";
+        let module = "app::connection";
+        let lineno = "101";
+        write!(&mut buf, "{module} l:{lineno} {msg}").unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        let comp = format!("{module} l:{lineno} {msg}");
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<34>1",
+                timestamp: _,
+                hostname: "mymachine.example.com",
+                app_name: "su",
+                proc_id: NILVALUE,
+                msg_id,
+                data: NILVALUE,
+                msg
+            } if msg_id == msg_id && msg == comp
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_format_message_without_msg_id() {
+        let hostname = "mymachine.example.com";
+        let app_name = "su";
+        let severity = Severity::Crit;
+        let msg = "'su root' failed for lonvick on /dev/pts/8";
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, severity, Timestamp::CreateChronoLocal, msg, None)
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<34>1",
+                timestamp: _,
+                hostname,
+                app_name,
+                proc_id: NILVALUE,
+                msg_id: NILVALUE,
+                data: NILVALUE,
+                msg
+            } if hostname == hostname && app_name == app_name && msg == msg
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_format_message_with_msg_id() {
+        let hostname = "mymachine.example.com";
+        let app_name = "su";
+        let severity = Severity::Crit;
+        let msg_id = "ID47";
+        let msg = "'su root' failed for lonvick on /dev/pts/8";
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+
+        fmt.write_without_data(
+            &mut buf,
+            severity,
+            Timestamp::CreateChronoLocal,
+            msg,
+            Some(msg_id),
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<34>1",
+                timestamp: _,
+                hostname: "mymachine.example.com",
+                app_name: "su",
+                proc_id: NILVALUE,
+                msg_id,
+                data: NILVALUE,
+                msg
+            } if msg_id == msg_id && msg == msg
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_format_message_with_structured_data_and_message() {
+        let hostname = "mymachine.example.com";
+        let app_name = "evntslog";
+        let severity = Severity::Notice;
+        let msg_id = "ID47";
+        let msg = "An application event log entry...";
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+
+        fmt.write_with_data(
+            &mut buf,
+            severity,
+            Timestamp::CreateChronoLocal,
+            msg,
+            Some(msg_id),
+            vec![(
+                "exampleSDID@32473",
+                vec![
+                    ("iut", "3"),
+                    ("eventSource", "Application"),
+                    ("eventID", "1011"),
+                ],
+            )],
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<165>1",
+                timestamp: _,
+                hostname: "mymachine.example.com",
+                app_name: "evntslog",
+                proc_id: NILVALUE,
+                msg_id,
+                data: r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#,
+                msg
+            } if hostname == hostname && app_name == app_name && msg_id == msg_id && msg == msg
+        );
+    }
+
+    #[test]
+    fn should_format_message_with_structured_data_from_map() {
+        use std::collections::BTreeMap;
+
+        let fmt = Config::default().into_formatter();
+        let data = BTreeMap::from([
+            (
+                "exampleSDID@32473",
+                BTreeMap::from([("iut", "3"), ("eventSource", "Application")]),
+            ),
+            ("examplePriority@32473", BTreeMap::from([("class", "high")])),
+        ]);
+        let mut buf = vec![];
+
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Notice,
+            Timestamp::CreateSystemNow,
+            "msg",
+            None,
+            data,
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+
+        assert_eq!(
+            parts.data,
+            r#"[examplePriority@32473 class="high"][exampleSDID@32473 eventSource="Application" iut="3"]"#
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn should_format_message_with_structured_data_and_no_message() {
+        let hostname = "mymachine.example.com";
+        let app_name = "evntslog";
+        let severity = Severity::Notice;
+        let msg_id = "ID47";
+        let msg = "";
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+
+        fmt.write_with_data(
+            &mut buf,
+            severity,
+            Timestamp::CreateChronoLocal,
+            msg,
+            Some(msg_id),
+            vec![(
+                "exampleSDID@32473",
+                vec![
+                    ("iut", "3"),
+                    ("eventSource", "Application"),
+                    ("eventID", "1011"),
+                ],
+            )],
+        )
+        .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<165>1",
+                timestamp: _,
+                hostname,
+                app_name,
+                proc_id: NILVALUE,
+                msg_id,
+                data: r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#,
+                msg
+            } if hostname == hostname && app_name == app_name && msg_id == msg_id && msg == msg
+        );
+    }
+
+    #[test]
+    fn should_format_example_4_without_bom_when_msg_is_empty() {
+        const EXAMPLE_4: &[u8] = br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"]"#;
+
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let data = vec![
+            (
+                "exampleSDID@32473",
+                vec![
+                    ("iut", "3"),
+                    ("eventSource", "Application"),
+                    ("eventID", "1011"),
+                ],
+            ),
+            ("examplePriority@32473", vec![("class", "high")]),
+        ];
+
+        struct Empty;
+
+        impl fmt::Display for Empty {
+            fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                Ok(())
+            }
+        }
+
+        let empty_msgs = [
+            Msg::Utf8Str(""),
+            Msg::Utf8String(String::new()),
+            Msg::NonUnicodeBytes(b""),
+            Msg::Display(&Empty),
+        ];
+
+        for msg in empty_msgs {
+            let mut buf = vec![];
+            fmt.write_with_data(
+                &mut buf,
+                Severity::Notice,
+                "2003-10-11T22:14:15.003Z",
+                msg,
+                Some("ID47"),
+                data.clone(),
+            )
+            .unwrap();
+
+            assert_eq!(buf, EXAMPLE_4, "{}", String::from_utf8_lossy(&buf));
+        }
+    }
+
+    #[test]
+    fn should_separate_msg_by_a_single_space_for_each_msg_variant() {
+        struct Shown(&'static str);
+
+        impl fmt::Display for Shown {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.0)
+            }
+        }
+
+        fn variants<'a>(
+            s: &'static str,
+            args: &'a fmt::Arguments<'a>,
+            shown: &'a Shown,
+        ) -> [Msg<'a>; 6] {
+            [
+                Msg::Utf8Str(s),
+                Msg::Utf8String(s.to_owned()),
+                Msg::NonUnicodeBytes(s.as_bytes()),
+                Msg::FmtArguments(*args),
+                Msg::FmtArgumentsRef(args),
+                Msg::Display(shown),
+            ]
+        }
+
+        let fmt = Formatter::default();
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+
+        let empty = "";
+        let empty_args = format_args!("{empty}");
+        let empty_shown = Shown("");
+        for msg in variants("", &empty_args, &empty_shown) {
+            let mut buf = vec![];
+            fmt.write_with_data(
+                &mut buf,
+                Severity::Info,
+                timestamp,
+                msg.reborrow(),
+                None,
+                no_data,
+            )
+            .unwrap();
+            assert_eq!(buf, b"<134>1 2003-10-11T22:14:15.003Z - - - - -");
+
+            buf.clear();
+            fmt.write_with_data(&mut buf, Severity::Info, timestamp, msg, None, data)
+                .unwrap();
+            assert!(buf.ends_with(br#" - [exampleSDID@32473 iut="3"]"#));
+        }
+
+        let word = "msg";
+        let args = format_args!("{word}");
+        let shown = Shown("msg");
+        for msg in variants("msg", &args, &shown) {
+            let sep: &[u8] = match msg {
+                Msg::NonUnicodeBytes(_) => b" ",
+                _ => &SPACED_BOM,
+            };
+
+            let mut buf = vec![];
+            fmt.write_with_data(
+                &mut buf,
+                Severity::Info,
+                timestamp,
+                msg.reborrow(),
+                None,
+                no_data,
+            )
+            .unwrap();
+            assert_eq!(
+                buf,
+                [
+                    &b"<134>1 2003-10-11T22:14:15.003Z - - - - -"[..],
+                    sep,
+                    b"msg"
+                ]
+                .concat()
+            );
+
+            buf.clear();
+            fmt.write_with_data(&mut buf, Severity::Info, timestamp, msg, None, data)
+                .unwrap();
+            assert!(buf.ends_with(&[&br#"[exampleSDID@32473 iut="3"]"#[..], sep, b"msg"].concat()));
+        }
+    }
+
+    #[test]
+    fn should_append_trailing_nul_when_configured() {
+        let timestamp = "2003-10-11T22:14:15.003Z";
+
+        let mut plain = vec![];
+        Formatter::default()
+            .write_without_data(&mut plain, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert!(plain.ends_with(b"a message"));
+
+        let fmt = Config {
+            trailing_nul: true,
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert_eq!(buf, [&plain[..], b"\0"].concat());
+
+        buf.clear();
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            timestamp,
+            "",
+            None,
+            [("exampleSDID@32473", [("iut", "3")])],
+        )
+        .unwrap();
+        assert!(buf.ends_with(b"[exampleSDID@32473 iut=\"3\"]\0"));
+
+        let vec = fmt
+            .format_to_vec(Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert_eq!(vec, [&plain[..], b"\0"].concat());
+        assert_eq!(vec.len(), vec.capacity());
+    }
+
+    #[test]
+    fn should_omit_version_when_configured() {
+        let timestamp = "2003-10-11T22:14:15.003Z";
+
+        let mut buf = vec![];
+        Formatter::default()
+            .write_without_data(&mut buf, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert!(buf.starts_with(b"<134>1 2003-10-11T22:14:15.003Z - - - - - "));
+
+        let fmt = Config {
+            omit_version: true,
+            ..Default::default()
+        }
+        .into_formatter();
+
+        buf.clear();
+        fmt.write_without_data(&mut buf, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert!(buf.starts_with(b"<134>2003-10-11T22:14:15.003Z - - - - - "));
+
+        let no_data: [(&str, [(&str, &str); 0]); 0] = [];
+        let annotated = fmt
+            .format_annotated(Severity::Info, timestamp, "a message", None, no_data)
+            .unwrap();
+        assert_eq!(annotated.bytes, buf);
+        assert_eq!(annotated.get(Field::Version), Some(&b""[..]));
+        assert_eq!(annotated.get(Field::Timestamp), Some(timestamp.as_bytes()));
+    }
+
+    #[test]
+    fn should_separate_consecutive_nil_values_by_a_single_space() {
+        fn to_string(buf: Vec<u8>) -> String {
+            String::from_utf8(buf).unwrap()
+        }
+
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        let fmt = Formatter::default();
+
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, Timestamp::None, "", None)
+            .unwrap();
+        assert_eq!(to_string(buf), "<134>1 - - - - - -");
+
+        let mut buf = vec![];
+        fmt.write_with_data(&mut buf, Severity::Info, Timestamp::None, "", None, no_data)
+            .unwrap();
+        assert_eq!(to_string(buf), "<134>1 - - - - - -");
+
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, Timestamp::None, "msg", None)
+            .unwrap();
+        assert_eq!(to_string(buf), "<134>1 - - - - - - \u{feff}msg");
+
+        let mut buf = vec![];
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            Timestamp::None,
+            "",
+            None,
+            [("exampleSDID@32473", [("iut", "3")])],
+        )
+        .unwrap();
+        assert_eq!(
+            to_string(buf),
+            r#"<134>1 - - - - - [exampleSDID@32473 iut="3"]"#
+        );
+
+        let fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, Timestamp::None, "", Some("ID47"))
+            .unwrap();
+        assert_eq!(to_string(buf), "<134>1 - mymachine.example.com - - ID47 -");
+    }
+
+    #[test]
+    fn should_truncate_msg_to_max_len_at_char_boundary() {
+        let timestamp = "1985-04-12T23:20:50.52Z";
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("su"),
+            max_len: Some(100),
+            collect_stats: true,
+            ..Default::default()
+        }
+        .into_formatter();
+        // each char is 3 bytes, so the limit falls within a char for some header lengths
+        let msg = "日本語".repeat(20);
+
+        for msg_id in [None, Some("a"), Some("ab"), Some("abc")] {
+            let mut buf = vec![];
+            fmt.write_without_data(&mut buf, Severity::Crit, timestamp, msg.as_str(), msg_id)
+                .unwrap();
+
+            assert!(buf.len() <= 100 && buf.len() > 97, "{}", buf.len());
+            let parts = parse_syslog_message(&buf);
+            assert!(msg.starts_with(parts.msg));
+            assert!(!parts.msg.is_empty());
+
+            let vec = fmt
+                .format_to_vec(Severity::Crit, timestamp, msg.as_str(), msg_id)
+                .unwrap();
+            assert_eq!(vec, buf);
+        }
+        assert_eq!(fmt.stats().unwrap().truncations, 8);
+
+        // a MSG that fits isn't changed
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Crit, timestamp, "日本語", None)
+            .unwrap();
+        assert!(buf.ends_with("\u{feff}日本語".as_bytes()));
+        assert_eq!(fmt.stats().unwrap().truncations, 8);
+
+        // the MSG is left out if the header leaves no room for it
+        let fmt = Config {
+            max_len: Some(20),
+            trailing_nul: true,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Crit, "-", msg.as_str(), None)
+            .unwrap();
+        assert_eq!(buf, b"<130>1 - - - - - -\0");
+    }
+
+    #[test]
+    fn should_truncate_message_to_buffer_size() {
+        use arrayvec::ArrayVec;
+
+        let timestamp = "1985-04-12T23:20:50.52Z";
+        let hostname = "mymachine.example.com";
+        let app_name = "su";
+        let severity = Severity::Crit;
+        let msg = "'su root' failed for lonvick on /dev/pts/8";
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: hostname.into(),
+            app_name: app_name.into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = ArrayVec::<u8, 100>::new();
+
+        let err = fmt
+            .write_without_data(&mut buf, severity, timestamp, msg, None)
+            .unwrap_err();
+
+        assert_eq!(
+            err.kind(),
+            ErrorKind::WriteZero,
+            "The given buffer is too small for the message. But the formatter should write as much as possible"
+        );
+
+        let parts = parse_syslog_message(&buf);
+
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<34>1",
+                timestamp,
+                hostname,
+                app_name,
+                proc_id: NILVALUE,
+                msg_id: NILVALUE,
+                data: NILVALUE,
+                msg: "'su root' failed for lonvick on /dev"
+            } if timestamp == timestamp && hostname == hostname && app_name == app_name
+        );
+    }
+
+    #[test]
+    fn should_append_messages_to_a_single_buffer() {
+        use arrayvec::ArrayVec;
+
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: "mymachine.example.com".into(),
+            app_name: "evntslog".into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = ArrayVec::<u8, 512>::new();
+
+        let first_end = fmt
+            .append_without_data(&mut buf, Severity::Crit, timestamp, "first", Some("ID47"))
+            .unwrap();
+        let second_end = fmt
+            .append_with_data(
+                &mut buf,
+                Severity::Notice,
+                timestamp,
+                "second",
+                None,
+                [("exampleSDID@32473", [("iut", "3")])],
+            )
+            .unwrap();
+
+        assert_eq!(second_end, buf.len());
+
+        let first = parse_syslog_message(&buf[..first_end]);
+        assert_matches!(
+            first,
+            Parts {
+                prio: "<162>1",
+                msg_id: "ID47",
+                data: NILVALUE,
+                msg: "first",
+                ..
+            }
+        );
+
+        let second = parse_syslog_message(&buf[first_end..second_end]);
+        assert_matches!(
+            second,
+            Parts {
+                prio: "<165>1",
+                msg_id: NILVALUE,
+                data: r#"[exampleSDID@32473 iut="3"]"#,
+                msg: "second",
+                ..
+            }
+        );
+    }
+
+    #[test]
+    fn should_write_lifecycle_markers() {
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: "mymachine.example.com".into(),
+            app_name: "evntslog".into(),
+            proc_id: None,
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+
+        fmt.write_startup_marker(&mut buf, timestamp, "lifecycle@32473")
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<166>1",
+                data: r#"[lifecycle@32473 event="startup"]"#,
+                msg: "",
+                ..
+            }
+        );
+
+        buf.clear();
+        fmt.write_shutdown_marker(&mut buf, timestamp, "lifecycle@32473")
+            .unwrap();
+
+        let parts = parse_syslog_message(&buf);
+        assert_matches!(
+            parts,
+            Parts {
+                prio: "<166>1",
+                data: r#"[lifecycle@32473 event="shutdown"]"#,
+                msg: "",
+                ..
+            }
+        );
+    }
+
+    #[test]
+    fn should_decode_priority_into_facility_and_severity() {
+        // the PRI values of the RFC examples
+        assert_eq!(
+            decode_priority(34).unwrap(),
+            (Facility::Auth, Severity::Crit)
+        );
+        assert_eq!(
+            decode_priority(165).unwrap(),
+            (Facility::Local4, Severity::Notice)
+        );
+        assert_eq!(
+            decode_priority(0).unwrap(),
+            (Facility::Kern, Severity::Emerg)
+        );
+        assert_eq!(
+            decode_priority(191).unwrap(),
+            (Facility::Local7, Severity::Debug)
+        );
+
+        for facility in [Facility::User, Facility::Ftp, Facility::Local0] {
+            for severity in [Severity::Emerg, Severity::Warning, Severity::Debug] {
+                let priority = Priority::new(facility, severity);
+                assert_eq!(
+                    decode_priority(priority.value()).unwrap(),
+                    (facility, severity)
+                );
+            }
+        }
+
+        // the facility numbers 12 to 15 aren't known
+        assert!(decode_priority(12 * 8).is_err());
+        assert!(decode_priority(15 * 8 + 7).is_err());
+        assert!(decode_priority(192).is_err());
+    }
+
+    #[test]
+    fn should_write_minimal_keep_alive() {
+        let mut expected = vec![];
+        Formatter::default()
+            .write_without_data(&mut expected, Severity::Debug, "-", "", None)
+            .unwrap();
+
+        let mut buf = vec![];
+        Formatter::default().write_keep_alive(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+        assert_eq!(buf, b"<135>1 - - - - - -");
+        assert_eq!(buf.len(), 18);
+
+        // the configured header fields are left out
+        let fmt = Config {
+            facility: Facility::Local4,
+            hostname: "mymachine.example.com".into(),
+            app_name: "evntslog".into(),
+            proc_id: "8710".into(),
+            ..Default::default()
+        }
+        .into_formatter();
+
+        buf.clear();
+        fmt.write_keep_alive(&mut buf).unwrap();
+        assert_eq!(buf, b"<167>1 - - - - - -");
+    }
+
+    #[test]
+    fn should_apply_config_to_keep_alive() {
+        let fmt = Config {
+            hostname: "mymachine.example.com".into(),
+            omit_version: true,
+            trailing_nul: true,
+            pri_sd_id: Some("pri@32473"),
+            collect_stats: true,
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let mut buf = vec![];
+        fmt.write_keep_alive(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            b"<135>- - - - - [pri@32473 facility=\"Local0\" severity=\"Debug\"]\0"
+        );
+
+        let stats = fmt.stats().unwrap();
+        assert_eq!(stats.messages(Severity::Debug), 1);
+        assert_eq!(stats.bytes, buf.len() as u64);
+    }
+
+    #[test]
+    fn should_write_display_msg_with_bom() {
+        struct Order(u32);
+
+        impl fmt::Display for Order {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "order {} shipped", self.0)
+            }
+        }
+
+        struct Empty;
+
+        impl fmt::Display for Empty {
+            fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                Ok(())
+            }
+        }
+
+        let mut buf = vec![];
+        write_msg(&mut buf, Msg::Display(&Order(7))).unwrap();
+        assert_eq!(buf, b" \xEF\xBB\xBForder 7 shipped");
+
+        let mut str_buf = vec![];
+        write_msg(&mut str_buf, "order 7 shipped").unwrap();
+        assert_eq!(buf, str_buf);
+
+        buf.clear();
+        write_msg(&mut buf, Msg::Display(&Empty)).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn should_write_fmt_arguments_msg_with_bom() {
+        let id = 7;
+
+        let mut str_buf = vec![];
+        write_msg(&mut str_buf, "order 7 shipped").unwrap();
+
+        let mut buf = vec![];
+        write_msg(&mut buf, format_args!("order {id} shipped")).unwrap();
+        assert_eq!(buf, b" \xEF\xBB\xBForder 7 shipped");
+        assert_eq!(buf, str_buf);
+
+        buf.clear();
+        write_msg(
+            &mut buf,
+            Msg::FmtArgumentsRef(&format_args!("order {id} shipped")),
+        )
+        .unwrap();
+        assert_eq!(buf, str_buf);
+
+        buf.clear();
+        write_msg(&mut buf, format_args!("")).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn should_report_io_error_when_writing_display_msg() {
+        use arrayvec::ArrayVec;
+
+        let mut buf = ArrayVec::<u8, 8>::new();
+        let err = write_msg(&mut buf, Msg::Display(&"a message that is too long")).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(&buf[..], b" \xEF\xBB\xBFa me");
+    }
+
+    #[test]
+    fn should_report_io_error_when_writing_non_unicode_msg() {
+        use arrayvec::ArrayVec;
+
+        let mut buf = ArrayVec::<u8, 4>::new();
+        let err = write_msg(&mut buf, Msg::NonUnicodeBytes(b"\xFFbytes")).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(&buf[..], b" \xFFby");
+
+        let mut buf = ArrayVec::<u8, 32>::new();
+        let err = Formatter::default()
+            .write_without_data(
+                &mut buf,
+                Severity::Info,
+                "-",
+                Msg::NonUnicodeBytes(b"\xFF raw bytes that don't fit"),
+                None,
+            )
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(&buf[..], b"<134>1 - - - - - - \xFF raw bytes t");
+    }
+
+    #[test]
+    fn should_format_to_vec_with_exact_capacity() {
+        let fmt = Config {
+            facility: Facility::Auth,
+            hostname: "mymachine.example.com".into(),
+            app_name: "su".into(),
+            ..Default::default()
+        }
+        .into_formatter();
+
+        let buf = fmt
+            .format_to_vec(
+                Severity::Crit,
+                Timestamp::CreateSystemNow,
+                format!("'su root' failed for lonvick on {}", "/dev/pts/8"),
+                Some("ID47"),
+            )
+            .unwrap();
+
+        assert_eq!(buf.capacity(), buf.len());
+        assert_matches!(
+            parse_syslog_message(&buf),
+            Parts {
+                prio: "<34>1",
+                msg_id: "ID47",
+                data: NILVALUE,
+                msg: "'su root' failed for lonvick on /dev/pts/8",
+                ..
+            }
+        );
+
+        let buf = fmt
+            .format_to_vec_with_data(
+                Severity::Crit,
+                "2003-10-11T22:14:15.003Z".to_owned(),
+                format_args!("An application event log entry..."),
+                None,
+                [("exampleSDID@32473", [("iut", "3")])],
+            )
+            .unwrap();
+
+        assert_eq!(buf.capacity(), buf.len());
+        assert_matches!(
+            parse_syslog_message(&buf),
+            Parts {
+                timestamp: "2003-10-11T22:14:15.003Z",
+                data: r#"[exampleSDID@32473 iut="3"]"#,
+                msg: "An application event log entry...",
+                ..
+            }
+        );
+    }
+
+    #[test]
+    fn should_fmt_structured_data() {
+        use arrayvec::ArrayVec;
+
+        let mut buf = ArrayVec::<u8, 100>::new();
+
+        buf.clear();
+
+        write_data::<_, [(&str, [(&str, &str); 0]); 0], _>(&mut buf, []).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), " -");
+
+        buf.clear();
+        write_data(&mut buf, [("first", [])]).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), " [first]");
+
+        buf.clear();
+        write_data(&mut buf, [("first", []), ("second", [])]).unwrap();
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), " [first][second]");
+
+        buf.clear();
+        write_data(&mut buf, [("first", [("p-one", "pv-one")])]).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [first p-one="pv-one"]"#
+        );
+
+        buf.clear();
+        write_data(
+            &mut buf,
+            [("first", [("p-one", "pv-one"), ("p-two", "pv-two")])],
+        )
+        .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [first p-one="pv-one" p-two="pv-two"]"#
+        );
+
+        buf.clear();
+        write_data(
+            &mut buf,
+            [
+                ("first", [("p-one", "pv-one"), ("p-two", "pv-two")]),
+                ("second", [("p-one", "pv-one"), ("p-two", "pv-two")]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [first p-one="pv-one" p-two="pv-two"][second p-one="pv-one" p-two="pv-two"]"#
+        );
+    }
+
+    #[cfg(feature = "arrayvec")]
+    #[test]
+    fn should_handle_full_buffer_according_to_policy() {
+        use arrayvec::ArrayVec;
+
+        let fmt = Formatter::default();
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+        let write = |buf: &mut ArrayVec<u8, 48>, policy| {
+            fmt.write_to_buffer(buf, policy, Severity::Info, "-", "a message", None, data)
+        };
+
+        let mut expected = vec![];
+        fmt.write_with_data(&mut expected, Severity::Info, "-", "a message", None, data)
+            .unwrap();
+        assert!(expected.len() > 48);
+
+        let mut buf = ArrayVec::<u8, 48>::new();
+        let err = write(&mut buf, FullBuffer::Truncate).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(&buf[..], &expected[..48]);
+
+        let mut buf = ArrayVec::<u8, 48>::new();
+        let err = write(&mut buf, FullBuffer::Error).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert!(buf.is_empty());
+
+        // an ArrayVec can't grow
+        let mut buf = ArrayVec::<u8, 48>::new();
+        let err = write(&mut buf, FullBuffer::Grow).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert!(buf.is_empty());
+
+        let mut buf = ArrayVec::<u8, 48>::new();
+        let fits = [("exampleSDID@32473", [])];
+        fmt.write_to_buffer(
+            &mut buf,
+            FullBuffer::Error,
+            Severity::Info,
+            "-",
+            "",
+            None,
+            fits,
+        )
+        .unwrap();
+        assert_eq!(&buf[..], b"<134>1 - - - - - [exampleSDID@32473]");
+    }
+
+    #[test]
+    fn should_hash_content_regardless_of_timestamp() {
+        let fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let hash = |timestamp, severity, msg, data: &[(&str, [(&str, &str); 1])]| {
+            fmt.format_with_content_hash(severity, timestamp, msg, None, data.iter().copied())
+                .unwrap()
+        };
+
+        let first = [("a@32473", [("n", "1")]), ("b@32473", [("n", "2")])];
+        let second = [("b@32473", [("n", "2")]), ("a@32473", [("n", "1")])];
+
+        let (bytes, expected) = hash("2003-10-11T22:14:15.003Z", Severity::Info, "msg", &first);
+        assert_eq!(
+            parse_syslog_message(&bytes).timestamp,
+            "2003-10-11T22:14:15.003Z"
+        );
+
+        let (bytes, content_hash) = hash("2003-10-11T22:14:16.003Z", Severity::Info, "msg", &first);
+        assert_eq!(
+            parse_syslog_message(&bytes).timestamp,
+            "2003-10-11T22:14:16.003Z"
+        );
+        assert_eq!(content_hash, expected);
+
+        let (_, content_hash) = hash("-", Severity::Info, "msg", &second);
+        assert_eq!(content_hash, expected);
+
+        let (_, content_hash) = hash("-", Severity::Notice, "msg", &first);
+        assert_ne!(content_hash, expected);
+        let (_, content_hash) = hash("-", Severity::Info, "other msg", &first);
+        assert_ne!(content_hash, expected);
+        let (_, content_hash) = hash("-", Severity::Info, "msg", &first[..1]);
+        assert_ne!(content_hash, expected);
+    }
+
+    #[test]
+    fn should_frame_message_with_octet_counting() {
+        let mut stream = vec![];
+        frame_octet_counting(&mut stream, b"").unwrap();
+        assert_eq!(stream, b"0 ");
+
+        let fmt = Formatter::default();
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+        let mut msg = vec![];
+        fmt.write_with_data(&mut msg, Severity::Info, "-", "caf\u{e9}", None, data)
+            .unwrap();
+
+        let mut stream = vec![];
+        fmt.write_framed(&mut stream, Severity::Info, "-", "caf\u{e9}", None, data)
+            .unwrap();
+        fmt.write_framed(&mut stream, Severity::Info, "-", "caf\u{e9}", None, data)
+            .unwrap();
+
+        // the length counts the bytes of the BOM and the multi-byte char
+        let expected_len = r#"<134>1 - - - - - [exampleSDID@32473 iut="3"] "#.len() + 3 + 5;
+        assert_eq!(msg.len(), expected_len);
+
+        let frame = [format!("{expected_len} ").as_bytes(), &msg].concat();
+        assert_eq!(stream, [&frame[..], &frame[..]].concat());
+    }
+
+    #[test]
+    fn should_frame_message_with_lf() {
+        let mut stream = vec![];
+        frame_lf(&mut stream, b"", EmbeddedLf::Reject).unwrap();
+        assert_eq!(stream, b"\n");
+
+        let fmt = Formatter::default();
+        let data = [("exampleSDID@32473", [("lines", "a\nb")])];
+
+        // a LF within the message is rejected by default
+        let mut stream = vec![];
+        let mut buf = vec![];
+        fmt.write_with_data(&mut buf, Severity::Info, "-", "first", None, data)
+            .unwrap();
+        let err = frame_lf(&mut stream, &buf, EmbeddedLf::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(stream.is_empty());
+
+        for msg in ["first", "multi\nline\n"] {
+            let mut buf = vec![];
+            fmt.write_with_data(&mut buf, Severity::Info, "-", msg, None, data)
+                .unwrap();
+            frame_lf(&mut stream, &buf, EmbeddedLf::Escape).unwrap();
+        }
+
+        let frames: Vec<&[u8]> = stream.split(|b| *b == b'\n').collect();
+        assert_eq!(frames.len(), 3);
+        assert!(frames[2].is_empty());
+
+        let first = parse_syslog_message(frames[0]);
+        assert_eq!(first.data, r#"[exampleSDID@32473 lines="a#010b"]"#);
+        assert_eq!(first.msg, "first");
+
+        let second = parse_syslog_message(frames[1]);
+        assert_eq!(second.data, r#"[exampleSDID@32473 lines="a#010b"]"#);
+        assert_eq!(second.msg, "multi#010line#010");
+    }
+
+    #[test]
+    fn should_grow_vec_beyond_its_capacity() {
+        let fmt = Formatter::default();
+        let data = [("exampleSDID@32473", [("iut", "3")])];
+
+        let mut expected = vec![];
+        fmt.write_with_data(&mut expected, Severity::Info, "-", "a message", None, data)
+            .unwrap();
+
+        let mut buf = Vec::with_capacity(16);
+        let err = fmt
+            .write_to_buffer(
+                &mut buf,
+                FullBuffer::Error,
+                Severity::Info,
+                "-",
+                "a message",
+                None,
+                data,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert!(buf.is_empty());
+
+        fmt.write_to_buffer(
+            &mut buf,
+            FullBuffer::Grow,
+            Severity::Info,
+            "-",
+            "a message",
+            None,
+            data,
+        )
+        .unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn should_escape_reserved_chars_in_param_values() {
+        use arrayvec::ArrayVec;
+
+        let mut buf = ArrayVec::<u8, 100>::new();
+
+        for (value, expected) in [
+            (r#"he said "hi""#, r#" [first p-one="he said \"hi\""]"#),
+            (r"C:\dir", r#" [first p-one="C:\\dir"]"#),
+            ("a ] b", r#" [first p-one="a \] b"]"#),
+            (r"end\", r#" [first p-one="end\\"]"#),
+            (r#"\""#, r#" [first p-one="\\\""]"#),
+            (r"\]", r#" [first p-one="\\\]"]"#),
+            (r"\\", r#" [first p-one="\\\\"]"#),
+            (r"\\n", r#" [first p-one="\\\\n"]"#),
+        ] {
+            buf.clear();
+            write_data(&mut buf, [("first", [("p-one", value)])]).unwrap();
+            assert_eq!(std::str::from_utf8(&buf).unwrap(), expected, "{value}");
+        }
+
+        buf.clear();
+        write_data(
+            &mut buf,
+            [
+                ("first", [("p-one", "]"), ("p-two", r#"""#)]),
+                ("second", [("p-one", r"\"), ("p-two", "pv-two")]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [first p-one="\]" p-two="\""][second p-one="\\" p-two="pv-two"]"#
+        );
+
+        let config = DataConfig {
+            control_chars: ControlChars::Escape,
+            ..Default::default()
+        };
+        buf.clear();
+        write_data_with_config(&mut buf, [("first", [("p-one", "\"\n]")])], &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r##" [first p-one="\"#010\]"]"##
+        );
+    }
+
+    #[test]
+    fn should_handle_non_ascii_names_as_configured() {
+        let mut buf = vec![];
+
+        // the PARAM-VALUE is UTF-8
+        let config = DataConfig {
+            non_ascii_names: NonAsciiNames::Reject,
+            ..Default::default()
+        };
+        write_data_with_config(&mut buf, [("origin", [("city", "Zürich")])], &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [origin city="Zürich"]"#
+        );
+
+        buf.clear();
+        let err = write_data_with_config(&mut buf, [("café@32473", [("iut", "3")])], &config)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let err = write_data_with_config(
+            &mut buf,
+            [("origin", [("ciudad", "x"), ("año", "1")])],
+            &config,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let config = DataConfig {
+            non_ascii_names: NonAsciiNames::Replace,
+            ..Default::default()
+        };
+        buf.clear();
+        write_data_with_config(&mut buf, [("café@32473", [("año", "Zürich")])], &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [caf_@32473 a_o="Zürich"]"#
+        );
+
+        // the names are written as is by default
+        buf.clear();
+        write_data(&mut buf, [("café@32473", [("iut", "3")])]).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [café@32473 iut="3"]"#
+        );
+    }
+
+    #[test]
+    fn should_append_relay_data_without_duplicating_ids() {
+        let mut buf = vec![];
+
+        let data = [
+            ("exampleSDID@32473", vec![("iut", "3")]),
+            ("origin", vec![("ip", "192.0.2.1")]),
+            ("relay@32473", vec![("ip", "192.0.2.2")]),
+        ];
+        let relay = (
+            "relay@32473",
+            vec![("ip", "192.0.2.3"), ("software", "relay")],
+        );
+
+        write_data(&mut buf, append_relay_data(data, relay)).unwrap();
+
+        // the origin of the originator is kept, the element of the previous relay is replaced
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [exampleSDID@32473 iut="3"][origin ip="192.0.2.1"][relay@32473 ip="192.0.2.3" software="relay"]"#
+        );
+
+        buf.clear();
+        write_data(
+            &mut buf,
+            append_relay_data([], ("relay@32473", [("ip", "192.0.2.3")])),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [relay@32473 ip="192.0.2.3"]"#
+        );
+    }
+
+    #[test]
+    fn should_stream_structured_data_like_write_data() {
+        let data = [
+            (
+                "exampleSDID@32473",
+                vec![("iut", "3"), ("path", r#"C:\dir "x" [y]"#)],
+            ),
+            ("empty@32473", vec![]),
+            ("origin", vec![("ip", "192.0.2.1")]),
+        ];
+        let mut expected = vec![];
+        write_data(&mut expected, data.clone()).unwrap();
+
+        let mut buf = vec![];
+        let mut sd = SdWriter::new(&mut buf);
+        for (id, params) in data {
+            sd.begin_element(id).unwrap();
+            for (name, value) in params {
+                sd.param(name, value).unwrap();
+            }
+            sd.end_element().unwrap();
+        }
+        sd.finish().unwrap();
+
+        assert_eq!(std::str::from_utf8(&buf), std::str::from_utf8(&expected));
+
+        buf.clear();
+        SdWriter::new(&mut buf).finish().unwrap();
+        assert_eq!(buf, b" -");
+
+        let mut sd = SdWriter::new(&mut buf);
+        assert_matches!(sd.param("iut", "3"), Err(err) if err.kind() == ErrorKind::InvalidInput);
+        sd.begin_element("origin").unwrap();
+        assert_matches!(sd.finish(), Err(err) if err.kind() == ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn should_predict_structured_data_len() {
+        fn assert_len<'a, I, P>(data: I)
+        where
+            I: IntoIterator<Item = (&'a SdId, P)> + Clone + 'a,
+            P: IntoIterator<Item = SdParam<'a>> + 'a,
+        {
+            let mut buf = vec![];
+            write_data(&mut buf, data.clone()).unwrap();
+            assert_eq!(structured_data_len(data), buf.len());
+        }
+
+        assert_len::<[(&str, [(&str, &str); 0]); 0], _>([]);
+        assert_len([("first", [])]);
+        assert_len([("first", []), ("second", [])]);
+        assert_len([("first", [("p-one", "pv-one"), ("p-two", "pv-two")])]);
+        assert_len([
+            ("first", vec![("p-one", "pv-one")]),
+            ("second", vec![("p-one", "pv-one"), ("p-two", "pv-two")]),
+        ]);
+        assert_len([("utf8", [("p-one", "ĳsselmeer ünïcödé")])]);
+    }
+
+    #[test]
+    fn should_validate_language_tags() {
+        for tag in [
+            "en",
+            "en-US",
+            "zh-Hant-TW",
+            "de-CH-1996",
+            "en-a-bbb-x-a-ccc",
+            "x-whatever",
+        ] {
+            assert!(language_param(tag).is_ok(), "{tag} should be valid");
+        }
+
+        for tag in [
+            "",
+            "e",
+            "en_US",
+            "en-",
+            "-US",
+            "en--US",
+            "toolongtag",
+            "en-a",
+            "en-x",
+            "x",
+            "1en",
+        ] {
+            assert!(language_param(tag).is_err(), "{tag} should be invalid");
+        }
+    }
+
+    #[test]
+    fn should_write_language_param_in_meta_element() {
+        let mut buf = vec![];
+        let language = language_param("en-US").unwrap();
+
+        write_data(&mut buf, [("meta", [language])]).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [meta language="en-US"]"#
+        );
+    }
+
+    #[test]
+    fn should_write_trace_element_with_escaped_ids() {
+        let mut buf = vec![];
+        let trace = trace_element(
+            "trace@32473",
+            "4bf92f3577b34da6a3ce929d0e0e4736",
+            "00f067aa0ba902b7",
+        )
+        .unwrap();
+
+        Formatter::default()
+            .write_with_data(&mut buf, Severity::Info, "-", "a message", None, [trace])
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(
+            parts.data,
+            r#"[trace@32473 traceId="4bf92f3577b34da6a3ce929d0e0e4736" spanId="00f067aa0ba902b7"]"#
+        );
+
+        // the reserved characters are printable, so they're escaped
+        buf.clear();
+        let trace = trace_element("trace@32473", r#"a"b]"#, r"c\").unwrap();
+        write_data(&mut buf, [trace]).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [trace@32473 traceId="a\"b\]" spanId="c\\"]"#
+        );
+
+        assert!(trace_element("trace@32473", "", "00f067aa0ba902b7").is_err());
+        assert!(trace_element("trace@32473", "4bf9", "00 f0").is_err());
+        assert!(trace_element("trace@32473", "4bf9", "00f0\n").is_err());
+        assert!(trace_element("trace@32473", "4bf9ü", "00f0").is_err());
+    }
+
+    #[test]
+    fn should_validate_sd_id() {
+        assert_eq!(
+            ValidatedSdId::new("exampleSDID@32473").map(|id| id.as_str()),
+            Ok("exampleSDID@32473")
+        );
+        assert_eq!(ValidatedSdId::new(""), Err(SdError::Empty));
+        assert_eq!(
+            ValidatedSdId::new("example SDID"),
+            Err(SdError::IllegalChar { ch: ' ', index: 7 })
+        );
+        assert_eq!(
+            ValidatedSdId::new("example]"),
+            Err(SdError::IllegalChar { ch: ']', index: 7 })
+        );
+        assert_eq!(
+            ValidatedSdId::new("exampleSDIDthatIsTooLong@32473123"),
+            Err(SdError::TooLong { len: 33 })
+        );
+    }
+
+    #[test]
+    fn should_only_allow_at_sign_before_enterprise_id() {
+        assert!(ValidatedSdId::new("name@32473").is_ok());
+        assert!(ValidatedSdId::new("name@32473.1.2").is_ok());
+        assert!(ValidatedSdId::new("timeQuality").is_ok());
+        assert_eq!(
+            ValidatedSdId::new("na@me@32473"),
+            Err(SdError::IllegalChar { ch: '@', index: 5 })
+        );
+        assert_eq!(
+            ValidatedSdId::new("name@notanumber"),
+            Err(SdError::InvalidEnterpriseId)
+        );
+        assert_eq!(
+            ValidatedSdId::new("name@"),
+            Err(SdError::InvalidEnterpriseId)
+        );
+        assert_eq!(
+            ValidatedSdId::new("name@32473."),
+            Err(SdError::InvalidEnterpriseId)
+        );
+        assert_eq!(ValidatedSdId::new("@32473"), Err(SdError::Empty));
+    }
+
+    #[test]
+    fn should_write_validated_sd_id() {
+        let id = ValidatedSdId::new("exampleSDID@32473").unwrap();
+        let mut buf = vec![];
+
+        write_data(&mut buf, [(id.as_str(), [("iut", "3")])]).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [exampleSDID@32473 iut="3"]"#
+        );
+    }
+
+    #[test]
+    fn should_write_control_chars_in_param_value_as_configured() {
+        let data = [("first", [("p-one", "nul\0tab\tdel\x7f")])];
+        let mut buf = vec![];
+
+        write_data(&mut buf, data).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            " [first p-one=\"nul\0tab\tdel\x7f\"]"
+        );
+
+        buf.clear();
+        let config = DataConfig {
+            control_chars: ControlChars::Escape,
+            ..Default::default()
+        };
+        write_data_with_config(&mut buf, data, &config).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#" [first p-one="nul#000tab#009del#127"]"#
+        );
+    }
+
+    #[derive(Debug)]
+    struct Parts<'a> {
+        prio: &'a str,
+        timestamp: &'a str,
+        hostname: &'a str,
+        app_name: &'a str,
+        proc_id: &'a str,
+        msg_id: &'a str,
+        data: &'a str,
+        msg: &'a str,
+    }
+
+    #[test]
+    fn should_only_accept_local_facilities_in_local_only_mode() {
+        let config = |facility| Config {
+            facility,
+            hostname: Some("mymachine.example.com"),
+            ..Default::default()
+        };
+
+        let report = config(Facility::Auth).validate_local_only();
+        assert_eq!(
+            report.issues,
+            [ConfigError::NonLocalFacility {
+                facility: Facility::Auth
+            }]
+        );
+        assert_eq!(
+            report.issues[0].to_string(),
+            "The facility Auth is not a local use facility"
+        );
+        assert!(config(Facility::Auth).validate().is_ok());
 
-    use super::*;
+        assert!(config(Facility::Local3).validate_local_only().is_ok());
+        assert!(config(Facility::Local0).validate_local_only().is_ok());
+        assert!(!config(Facility::Kern).validate_local_only().is_ok());
+    }
 
     #[test]
-    #[cfg(feature = "chrono")]
-    fn should_format_date_like_chrono() {
-        let datetime = chrono::Local::now();
-        let use_z = false;
-        let chrono_s = datetime.to_rfc3339_opts(chrono::SecondsFormat::Micros, use_z);
+    fn should_only_create_formatter_from_valid_config() {
+        let hostname = "a".repeat(256);
+        let result = Config {
+            hostname: Some(&hostname),
+            ..Default::default()
+        }
+        .try_into_formatter();
+        assert_matches!(
+            result,
+            Err(ConfigError::TooLong {
+                field: Field::Hostname,
+                len: 256,
+                max_len: 255
+            })
+        );
 
-        let mut buf = Vec::with_capacity(32);
-        write_chrono_datetime(&mut buf, &datetime).unwrap();
-        let s = String::from_utf8(buf).unwrap();
+        let result = Config {
+            hostname: Some("my\u{e9}host"),
+            ..Default::default()
+        }
+        .try_into_formatter();
+        assert_matches!(
+            result,
+            Err(ConfigError::IllegalChar {
+                field: Field::Hostname,
+                ch: '\u{e9}',
+                index: 2
+            })
+        );
 
-        assert_eq!(
-            chrono_s, s,
-            "syslog-fmt date formatter should be char for char equal to Chrono"
+        let result = Config {
+            hostname: Some("my\thost"),
+            ..Default::default()
+        }
+        .try_into_formatter();
+        assert_matches!(
+            result,
+            Err(ConfigError::IllegalChar {
+                field: Field::Hostname,
+                ch: '\t',
+                index: 2
+            })
         );
+
+        let hostname = "a".repeat(255);
+        let fmt = Config {
+            hostname: Some(&hostname),
+            ..Default::default()
+        }
+        .try_into_formatter()
+        .unwrap();
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, "-", "msg", None)
+            .unwrap();
+        assert_eq!(parse_syslog_message(&buf).hostname, hostname);
+
+        assert!(Config::default().try_into_formatter().is_ok());
     }
 
     #[test]
-    fn should_write_message_in_sections() {
-        let hostname = "mymachine.example.com";
-        let app_name = "su";
-        let severity = Severity::Crit;
-        let msg_id = "ID47";
-        let msg = "'su root' failed for lonvick on /dev/pts/8";
+    fn should_format_into_fmt_write() {
         let fmt = Config {
-            facility: Facility::Auth,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
+            facility: Facility::Local4,
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("evntslog"),
+            ..Default::default()
         }
         .into_formatter();
-        let mut buf = vec![];
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let data = [(
+            "exampleSDID@32473",
+            [("iut", "3"), ("note", "a \"quoted\" ü")],
+        )];
 
-        fmt.write_header(
+        let mut s = String::new();
+        fmt.format_into(
+            &mut s,
+            Severity::Notice,
+            timestamp,
+            format_args!("order {} shipped", 7),
+            Some("ID47"),
+            data,
+        )
+        .unwrap();
+
+        let mut buf = vec![];
+        fmt.write_with_data(
             &mut buf,
-            severity,
-            Timestamp::CreateChronoLocal,
-            Some(msg_id),
+            Severity::Notice,
+            timestamp,
+            "order 7 shipped",
+            Some("ID47"),
+            data,
         )
         .unwrap();
+        assert_eq!(s.as_bytes(), buf);
+        assert!(s.ends_with("\u{feff}order 7 shipped"));
 
-        // we are not using any structured data
-        write_nil_value(&mut buf).unwrap();
-        write_msg(&mut buf, msg).unwrap();
+        // bytes that are valid UTF-8 can be written
+        s.clear();
+        let no_data: [(&SdId, [SdParam<'_>; 0]); 0] = [];
+        fmt.format_into(
+            &mut s,
+            Severity::Notice,
+            timestamp,
+            Msg::NonUnicodeBytes(b"bytes"),
+            None,
+            no_data,
+        )
+        .unwrap();
+        assert!(s.ends_with(" - bytes"));
 
-        let parts = parse_syslog_message(&buf);
-        assert_matches!(
-            parts,
-            Parts {
-                prio: "<34>1",
-                timestamp: _,
-                hostname: "mymachine.example.com",
-                app_name: "su",
-                proc_id: NILVALUE,
-                msg_id,
-                data: NILVALUE,
-                msg
-            } if msg_id == msg_id && msg == msg
+        s.clear();
+        let err = fmt
+            .format_into(
+                &mut s,
+                Severity::Notice,
+                timestamp,
+                Msg::NonUnicodeBytes(b"\xFFbytes"),
+                None,
+                no_data,
+            )
+            .unwrap_err();
+        assert_eq!(err, fmt::Error);
+
+        // the deprecated method keeps the kind of the error
+        s.clear();
+        #[allow(deprecated)]
+        let err = fmt
+            .write_to_fmt(
+                &mut s,
+                Severity::Notice,
+                timestamp,
+                Msg::NonUnicodeBytes(b"\xFFbytes"),
+                None,
+                no_data,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn should_keep_first_element_with_duplicate_sd_id() {
+        let mut builder = StructuredDataBuilder::new();
+        builder.add_element("a@32473", [("n", "1")]).unwrap();
+        builder.add_element("b@32473", [("n", "2")]).unwrap();
+        assert_eq!(
+            builder.add_element("a@32473", [("n", "3")]).unwrap_err(),
+            DuplicateSdId {
+                sd_id: "a@32473".to_owned()
+            }
+        );
+        builder.add_element("c@32473", []).unwrap();
+
+        let data = builder.finish();
+        assert_eq!(
+            data,
+            vec![
+                ("a@32473", vec![("n", "1")]),
+                ("b@32473", vec![("n", "2")]),
+                ("c@32473", vec![]),
+            ]
         );
+
+        let fmt = Formatter::default();
+        let mut buf = vec![];
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            "2003-10-11T22:14:15.003Z",
+            "msg",
+            None,
+            data,
+        )
+        .unwrap();
+        let message = parse_syslog_message(&buf);
+        assert_eq!(message.data, r#"[a@32473 n="1"][b@32473 n="2"][c@32473]"#);
     }
 
     #[test]
-    fn should_write_message_with_custom_formatting() {
-        use std::io::Write;
+    fn should_omit_bom_when_configured() {
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let header = b"<134>1 2003-10-11T22:14:15.003Z - - - - -";
+
+        let mut buf = vec![];
+        Formatter::default()
+            .write_without_data(&mut buf, Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert_eq!(buf, [&header[..], b" \xEF\xBB\xBFa message"].concat());
 
-        let hostname = "mymachine.example.com";
-        let app_name = "su";
-        let severity = Severity::Crit;
-        let msg_id = "ID47";
         let fmt = Config {
-            facility: Facility::Auth,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
+            bom: Bom::Omit,
+            ..Default::default()
         }
         .into_formatter();
-        let mut buf = vec![];
 
-        fmt.write_header(
+        let id = 7;
+        let msgs: [Msg<'_>; 4] = [
+            "a message".into(),
+            String::from("a message").into(),
+            format_args!("a message").into(),
+            Msg::Display(&"a message"),
+        ];
+        for msg in msgs {
+            buf.clear();
+            fmt.write_without_data(&mut buf, Severity::Info, timestamp, msg, None)
+                .unwrap();
+            assert_eq!(buf, [&header[..], b" a message"].concat());
+        }
+
+        buf.clear();
+        fmt.write_without_data(
             &mut buf,
-            severity,
-            Timestamp::CreateChronoLocal,
-            Some(msg_id),
+            Severity::Info,
+            timestamp,
+            format_args!("order {id} shipped"),
+            None,
         )
         .unwrap();
+        assert_eq!(buf, [&header[..], b" order 7 shipped"].concat());
 
-        // we are not using any structured data
-        write_nil_value(&mut buf).unwrap();
-        write_utf8_bom(&mut buf).unwrap();
-
-        let msg = "'su root' failed for lonvick on /dev/pts/8";
-        let module = "app::connection";
-        let lineno = "101";
-        write!(&mut buf, "{module} l:{lineno} {msg}").unwrap();
+        // the bytes are written as is
+        buf.clear();
+        fmt.write_without_data(
+            &mut buf,
+            Severity::Info,
+            timestamp,
+            Msg::NonUnicodeBytes(b"a message"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(buf, [&header[..], b" a message"].concat());
 
-        let parts = parse_syslog_message(&buf);
-        let comp = format!("{module} l:{lineno} {msg}");
-        assert_matches!(
-            parts,
-            Parts {
-                prio: "<34>1",
-                timestamp: _,
-                hostname: "mymachine.example.com",
-                app_name: "su",
-                proc_id: NILVALUE,
-                msg_id,
-                data: NILVALUE,
-                msg
-            } if msg_id == msg_id && msg == comp
-        );
+        let vec = fmt
+            .format_to_vec(Severity::Info, timestamp, "a message", None)
+            .unwrap();
+        assert_eq!(vec, [&header[..], b" a message"].concat());
     }
 
     #[test]
-    fn should_format_message_without_msg_id() {
-        let hostname = "mymachine.example.com";
-        let app_name = "su";
-        let severity = Severity::Crit;
-        let msg = "'su root' failed for lonvick on /dev/pts/8";
-        let fmt = Config {
-            facility: Facility::Auth,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
+    fn should_write_whitespace_msg_as_configured() {
+        let write = |fmt: &Formatter, msg: Msg<'_>| {
+            let mut buf = vec![];
+            fmt.write_without_data(&mut buf, Severity::Info, "-", msg, None)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let keep = Formatter::default();
+        assert_eq!(write(&keep, "  ".into()), "<134>1 - - - - - - \u{feff}  ");
+        assert_eq!(
+            write(&keep, "\t\n".into()),
+            "<134>1 - - - - - - \u{feff}\t\n"
+        );
+
+        let as_empty = Config {
+            whitespace_msg: WhitespaceMsg::AsEmpty,
+            ..Default::default()
         }
         .into_formatter();
-        let mut buf = vec![];
-        fmt.write_without_data(&mut buf, severity, Timestamp::CreateChronoLocal, msg, None)
+        assert_eq!(write(&as_empty, "  ".into()), "<134>1 - - - - - -");
+        assert_eq!(write(&as_empty, "\t\n".into()), "<134>1 - - - - - -");
+        assert_eq!(
+            write(&as_empty, Msg::Utf8String("  ".to_owned())),
+            "<134>1 - - - - - -"
+        );
+        assert_eq!(
+            write(&as_empty, format_args!("  ").into()),
+            "<134>1 - - - - - -"
+        );
+        assert_eq!(
+            write(&as_empty, " a ".into()),
+            "<134>1 - - - - - - \u{feff} a "
+        );
+        assert_eq!(
+            write(&as_empty, Msg::NonUnicodeBytes(b"  ")),
+            "<134>1 - - - - - -   "
+        );
+
+        let annotated = as_empty
+            .format_annotated(Severity::Info, "-", "  ", None, [("a@32473", [])])
             .unwrap();
+        assert_eq!(annotated.get(Field::Msg), Some(&b""[..]));
+    }
 
-        let parts = parse_syslog_message(&buf);
+    #[test]
+    fn should_validate_header_field_lengths_at_the_boundary() {
+        let at_limit = |len| "a".repeat(len);
+
+        let app_name = at_limit(48);
+        let proc_id = at_limit(128);
+        let result = Config {
+            app_name: Some(&app_name),
+            proc_id: Some(&proc_id),
+            ..Default::default()
+        }
+        .try_into_formatter();
+        assert!(result.is_ok());
 
+        let app_name = at_limit(49);
+        let result = Config {
+            app_name: Some(&app_name),
+            ..Default::default()
+        }
+        .try_into_formatter();
         assert_matches!(
-            parts,
-            Parts {
-                prio: "<34>1",
-                timestamp: _,
-                hostname,
-                app_name,
-                proc_id: NILVALUE,
-                msg_id: NILVALUE,
-                data: NILVALUE,
-                msg
-            } if hostname == hostname && app_name == app_name && msg == msg
+            result,
+            Err(ConfigError::TooLong {
+                field: Field::AppName,
+                len: 49,
+                max_len: 48
+            })
         );
-    }
 
-    #[test]
-    fn should_format_message_with_msg_id() {
-        let hostname = "mymachine.example.com";
-        let app_name = "su";
-        let severity = Severity::Crit;
-        let msg_id = "ID47";
-        let msg = "'su root' failed for lonvick on /dev/pts/8";
-        let fmt = Config {
-            facility: Facility::Auth,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
+        let proc_id = at_limit(129);
+        let result = Config {
+            proc_id: Some(&proc_id),
+            ..Default::default()
         }
-        .into_formatter();
-        let mut buf = vec![];
+        .try_into_formatter();
+        assert_matches!(
+            result,
+            Err(ConfigError::TooLong {
+                field: Field::ProcId,
+                len: 129,
+                max_len: 128
+            })
+        );
 
-        fmt.write_without_data(
-            &mut buf,
-            severity,
-            Timestamp::CreateChronoLocal,
-            msg,
-            Some(msg_id),
-        )
-        .unwrap();
+        assert_eq!(validate_msg_id(None), Ok(()));
+        assert_eq!(validate_msg_id(Some(&at_limit(32))), Ok(()));
+        assert_eq!(
+            validate_msg_id(Some(&at_limit(33))),
+            Err(ConfigError::TooLong {
+                field: Field::MsgId,
+                len: 33,
+                max_len: 32
+            })
+        );
+        assert_eq!(
+            validate_msg_id(Some("")),
+            Err(ConfigError::Empty {
+                field: Field::MsgId
+            })
+        );
+    }
 
-        let parts = parse_syslog_message(&buf);
+    #[test]
+    fn should_sanitize_msg_id() {
+        assert_matches!(sanitize_msg_id("ID47"), Cow::Borrowed("ID47"));
+        assert_matches!(sanitize_msg_id(""), Cow::Borrowed(NILVALUE));
+
+        let long = "my_crate::some_module::submodule::handler";
         assert_matches!(
-            parts,
-            Parts {
-                prio: "<34>1",
-                timestamp: _,
-                hostname: "mymachine.example.com",
-                app_name: "su",
-                proc_id: NILVALUE,
-                msg_id,
-                data: NILVALUE,
-                msg
-            } if msg_id == msg_id && msg == msg
+            sanitize_msg_id(long),
+            Cow::Borrowed(msg_id) if msg_id == &long[..32]
         );
+
+        assert_eq!(sanitize_msg_id("user login"), "user_login");
+        assert_eq!(sanitize_msg_id("a=\"b\"]\tc"), "a__b___c");
+        assert_eq!(sanitize_msg_id(&"é".repeat(40)), "_".repeat(32));
+
+        for raw in ["", "user login", long, "a=\"b\"]\tc", &"é".repeat(40)] {
+            assert_eq!(validate_msg_id(Some(&sanitize_msg_id(raw))), Ok(()));
+        }
     }
 
     #[test]
-    fn should_format_message_with_structured_data_and_message() {
-        let hostname = "mymachine.example.com";
-        let app_name = "evntslog";
-        let severity = Severity::Notice;
-        let msg_id = "ID47";
-        let msg = "An application event log entry...";
-        let fmt = Config {
-            facility: Facility::Local4,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
-        }
-        .into_formatter();
-        let mut buf = vec![];
+    fn should_sanitize_param_name() {
+        assert_matches!(sanitize_param_name("iut"), Cow::Borrowed("iut"));
+        assert_matches!(sanitize_param_name(""), Cow::Borrowed("_"));
+
+        let long = "a_very_long_field_name_of_an_event_in_a_span";
+        assert_matches!(
+            sanitize_param_name(long),
+            Cow::Borrowed(name) if name == &long[..32]
+        );
 
-        fmt.write_with_data(
-            &mut buf,
-            severity,
-            Timestamp::CreateChronoLocal,
-            msg,
-            Some(msg_id),
-            vec![(
-                "exampleSDID@32473",
-                vec![
-                    ("iut", "3"),
-                    ("eventSource", "Application"),
-                    ("eventID", "1011"),
-                ],
-            )],
-        )
-        .unwrap();
+        assert_eq!(sanitize_param_name("user name"), "user_name");
+        assert_eq!(sanitize_param_name("a=\"b\"]\tc"), "a__b___c");
+        assert_eq!(sanitize_param_name(&"é".repeat(40)), "_".repeat(32));
 
-        let parts = parse_syslog_message(&buf);
+        for raw in ["", "user name", long, "a=\"b\"]\tc", &"é".repeat(40)] {
+            assert_eq!(validate_sd_name(&sanitize_param_name(raw)), Ok(()));
+        }
+    }
 
+    #[test]
+    fn should_handle_long_proc_id_per_overflow_policy() {
+        let proc_id = "c".repeat(200);
+
+        let result = Config {
+            proc_id: Some(&proc_id),
+            proc_id_overflow: ProcIdOverflow::Reject,
+            ..Default::default()
+        }
+        .try_into_formatter();
         assert_matches!(
-            parts,
-            Parts {
-                prio: "<165>1",
-                timestamp: _,
-                hostname: "mymachine.example.com",
-                app_name: "evntslog",
-                proc_id: NILVALUE,
-                msg_id,
-                data: r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#,
-                msg
-            } if hostname == hostname && app_name == app_name && msg_id == msg_id && msg == msg
+            result,
+            Err(ConfigError::TooLong {
+                field: Field::ProcId,
+                len: 200,
+                max_len: 128
+            })
         );
+
+        let fmt = Config {
+            proc_id: Some(&proc_id),
+            proc_id_overflow: ProcIdOverflow::Truncate,
+            ..Default::default()
+        }
+        .try_into_formatter()
+        .unwrap();
+        let mut buf = vec![];
+        fmt.write_without_data(&mut buf, Severity::Info, "-", "msg", None)
+            .unwrap();
+        let parts = parse_syslog_message(&buf);
+        assert_eq!(parts.proc_id.len(), 128);
+        assert_eq!(parts.proc_id, &proc_id[..128]);
+
+        assert_eq!(truncate_str("aé", 2), "a");
+        assert_eq!(truncate_str("ab", 2), "ab");
     }
 
     #[test]
-    fn should_format_message_with_structured_data_and_no_message() {
-        let hostname = "mymachine.example.com";
-        let app_name = "evntslog";
-        let severity = Severity::Notice;
-        let msg_id = "ID47";
-        let msg = "";
+    fn should_collect_stats_when_configured() {
+        assert_eq!(Formatter::default().stats(), None);
+
         let fmt = Config {
-            facility: Facility::Local4,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
+            collect_stats: true,
+            ..Default::default()
         }
         .into_formatter();
-        let mut buf = vec![];
+        assert_eq!(fmt.stats(), Some(Stats::default()));
 
+        let timestamp = "2003-10-11T22:14:15.003Z";
+        let mut buf = vec![];
+        for severity in [Severity::Info, Severity::Err, Severity::Info] {
+            fmt.write_without_data(&mut buf, severity, timestamp, "msg", None)
+                .unwrap();
+        }
         fmt.write_with_data(
             &mut buf,
-            severity,
-            Timestamp::CreateChronoLocal,
-            msg,
-            Some(msg_id),
-            vec![(
-                "exampleSDID@32473",
-                vec![
-                    ("iut", "3"),
-                    ("eventSource", "Application"),
-                    ("eventID", "1011"),
-                ],
-            )],
+            Severity::Alert,
+            timestamp,
+            "msg",
+            None,
+            [("a@32473", [("n", "1")])],
         )
         .unwrap();
+        let vec = fmt
+            .format_to_vec(Severity::Info, timestamp, "msg", None)
+            .unwrap();
+        let annotated = fmt
+            .format_annotated(Severity::Debug, timestamp, "msg", None, [("a@32473", [])])
+            .unwrap();
 
-        let parts = parse_syslog_message(&buf);
-
-        assert_matches!(
-            parts,
-            Parts {
-                prio: "<165>1",
-                timestamp: _,
-                hostname,
-                app_name,
-                proc_id: NILVALUE,
-                msg_id,
-                data: r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#,
-                msg
-            } if hostname == hostname && app_name == app_name && msg_id == msg_id && msg == msg
+        let stats = fmt.stats().unwrap();
+        assert_eq!(stats.messages(Severity::Info), 3);
+        assert_eq!(stats.messages(Severity::Err), 1);
+        assert_eq!(stats.messages(Severity::Alert), 1);
+        assert_eq!(stats.messages(Severity::Debug), 1);
+        assert_eq!(stats.messages(Severity::Emerg), 0);
+        assert_eq!(stats.total_messages(), 6);
+        assert_eq!(
+            stats.bytes,
+            (buf.len() + vec.len() + annotated.bytes.len()) as u64
         );
+        assert_eq!(stats.truncations, 0);
+
+        let mut small = [0; 16];
+        fmt.write_to_buffer(
+            &mut io::Cursor::new(&mut small[..]),
+            FullBuffer::Truncate,
+            Severity::Info,
+            timestamp,
+            "msg",
+            None,
+            [("a@32473", [])],
+        )
+        .unwrap_err();
+        let truncated_stats = fmt.stats().unwrap();
+        assert_eq!(truncated_stats.truncations, 1);
+        assert_eq!(truncated_stats.messages(Severity::Info), 4);
+        // only the bytes that fit the buffer are counted
+        assert_eq!(truncated_stats.bytes, stats.bytes + small.len() as u64);
+        assert_eq!(fmt.clone().stats(), Some(truncated_stats));
+
+        // a message that isn't written isn't counted
+        fmt.write_to_buffer(
+            &mut io::Cursor::new(&mut small[..]),
+            FullBuffer::Error,
+            Severity::Info,
+            timestamp,
+            "msg",
+            None,
+            [("a@32473", [])],
+        )
+        .unwrap_err();
+        assert_eq!(fmt.stats(), Some(truncated_stats));
     }
 
     #[test]
-    fn should_truncate_message_to_buffer_size() {
-        use arrayvec::ArrayVec;
-
-        let timestamp = "1985-04-12T23:20:50.52Z";
-        let hostname = "mymachine.example.com";
-        let app_name = "su";
-        let severity = Severity::Crit;
-        let msg = "'su root' failed for lonvick on /dev/pts/8";
-        let fmt = Config {
-            facility: Facility::Auth,
-            hostname: hostname.into(),
-            app_name: app_name.into(),
-            proc_id: None,
-        }
-        .into_formatter();
-        let mut buf = ArrayVec::<u8, 100>::new();
+    fn should_reject_invalid_sd_names_when_checked() {
+        let fmt = Formatter::default();
+        let write = |data: &[(&SdId, &[SdParam<'_>])]| {
+            let mut buf = vec![];
+            let data = data
+                .iter()
+                .map(|(id, params)| (*id, params.iter().copied()));
+            let result =
+                fmt.write_with_checked_data(&mut buf, Severity::Info, "-", "msg", None, data);
+            (buf, result)
+        };
+        let invalid = |result: io::Result<()>| {
+            let err = result.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            err.into_inner()
+                .unwrap()
+                .downcast::<InvalidSdName>()
+                .map(|invalid| *invalid)
+                .unwrap()
+        };
 
-        let err = fmt
-            .write_without_data(&mut buf, severity, timestamp, msg, None)
-            .unwrap_err();
+        let (buf, result) = write(&[("ok@32473", &[]), ("bad id", &[("n", "1")])]);
+        assert!(buf.is_empty());
+        assert_eq!(
+            invalid(result),
+            InvalidSdName {
+                sd_id: "bad id".to_owned(),
+                param_name: None,
+                error: SdError::IllegalChar { ch: ' ', index: 3 },
+            }
+        );
 
+        let (buf, result) = write(&[("ok@32473", &[("n", "1"), ("a]b", "2")])]);
+        assert!(buf.is_empty());
         assert_eq!(
-            err.kind(),
-            ErrorKind::WriteZero,
-            "The given buffer is too small for the message. But the formatter should write as much as possible"
+            invalid(result),
+            InvalidSdName {
+                sd_id: "ok@32473".to_owned(),
+                param_name: Some("a]b".to_owned()),
+                error: SdError::IllegalChar { ch: ']', index: 1 },
+            }
         );
 
-        let parts = parse_syslog_message(&buf);
+        let (_, result) = write(&[("ok@enterprise", &[])]);
+        assert_eq!(invalid(result).error, SdError::InvalidEnterpriseId);
 
-        assert_matches!(
-            parts,
-            Parts {
-                prio: "<34>1",
-                timestamp,
-                hostname,
-                app_name,
-                proc_id: NILVALUE,
-                msg_id: NILVALUE,
-                data: NILVALUE,
-                msg: "'su root' failed for lonvick on /dev"
-            } if timestamp == timestamp && hostname == hostname && app_name == app_name
-        );
+        let (buf, result) = write(&[("ok@32473", &[("n", "1")])]);
+        result.unwrap();
+        assert_eq!(parse_syslog_message(&buf).data, r#"[ok@32473 n="1"]"#);
     }
 
     #[test]
-    fn should_fmt_structured_data() {
-        use arrayvec::ArrayVec;
+    fn should_report_all_config_issues() {
+        let long_proc_id = "p".repeat(129);
+        let config = Config {
+            hostname: None,
+            app_name: Some("app\tname"),
+            proc_id: Some(&long_proc_id),
+            ..Default::default()
+        };
 
-        let mut buf = ArrayVec::<u8, 100>::new();
+        let report = config.validate();
 
-        buf.clear();
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.issues,
+            [
+                ConfigError::MissingHostname,
+                ConfigError::IllegalChar {
+                    field: Field::AppName,
+                    ch: '\t',
+                    index: 3
+                },
+                ConfigError::TooLong {
+                    field: Field::ProcId,
+                    len: 129,
+                    max_len: 128
+                },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "The config has 3 issue(s):\n\
+            - The HOSTNAME is missing\n\
+            - The APP-NAME contains the illegal char '\\t' at 3\n\
+            - The PROCID is 129 characters long, the maximum is 128\n"
+        );
 
-        write_data::<_, [(&str, [(&str, &str); 0]); 0], _>(&mut buf, []).unwrap();
-        assert_eq!(std::str::from_utf8(&buf).unwrap(), " -");
+        let report = Config {
+            hostname: Some(""),
+            ..Default::default()
+        }
+        .validate();
+        assert_eq!(
+            report.issues,
+            [ConfigError::Empty {
+                field: Field::Hostname
+            }]
+        );
 
-        buf.clear();
-        write_data(&mut buf, [("first", [])]).unwrap();
-        assert_eq!(std::str::from_utf8(&buf).unwrap(), " [first]");
+        let report = Config {
+            hostname: Some("mymachine.example.com"),
+            app_name: Some("su"),
+            proc_id: Some("8710"),
+            ..Default::default()
+        }
+        .validate();
+        assert!(report.is_ok());
+        assert_eq!(report.to_string(), "The config is valid");
+    }
 
-        buf.clear();
-        write_data(&mut buf, [("first", []), ("second", [])]).unwrap();
-        assert_eq!(std::str::from_utf8(&buf).unwrap(), " [first][second]");
+    #[test]
+    fn should_keep_or_suppress_empty_elements() {
+        let data = || {
+            vec![
+                ("empty@32473", vec![]),
+                ("exampleSDID@32473", vec![("iut", "3")]),
+                ("examplePriority@32473", vec![]),
+            ]
+        };
+        let write = |data, empty_elements| {
+            let mut buf = vec![];
+            let config = DataConfig {
+                empty_elements,
+                ..Default::default()
+            };
+            write_data_with_config(&mut buf, data, &config).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
 
-        buf.clear();
-        write_data(&mut buf, [("first", [("p-one", "pv-one")])]).unwrap();
         assert_eq!(
-            std::str::from_utf8(&buf).unwrap(),
-            r#" [first p-one="pv-one"]"#
+            write(data(), EmptyElements::Keep),
+            r#" [empty@32473][exampleSDID@32473 iut="3"][examplePriority@32473]"#
+        );
+        assert_eq!(
+            write(data(), EmptyElements::Suppress),
+            r#" [exampleSDID@32473 iut="3"]"#
+        );
+        assert_eq!(
+            write(vec![("empty@32473", vec![])], EmptyElements::Suppress),
+            " -"
         );
 
-        buf.clear();
+        let fmt = Config {
+            pri_sd_id: Some("pri@32473"),
+            data: DataConfig {
+                empty_elements: EmptyElements::Suppress,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+        fmt.write_with_data(&mut buf, Severity::Info, Timestamp::None, "", None, data())
+            .unwrap();
+        assert!(buf.ends_with(
+            br#" [pri@32473 facility="Local0" severity="Info"][exampleSDID@32473 iut="3"]"#
+        ));
+    }
+
+    #[test]
+    fn should_cap_params_with_drop_policy() {
+        let params = [("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")];
+
+        let oldest: Vec<_> = cap_params(params, 2, DropPolicy::Oldest).collect();
+        assert_eq!(oldest, [("c", "3"), ("d", "4")]);
+
+        let newest: Vec<_> = cap_params(params, 2, DropPolicy::Newest).collect();
+        assert_eq!(newest, [("a", "1"), ("b", "2")]);
+
+        assert_eq!(cap_params(params, 8, DropPolicy::Oldest).len(), 4);
+        assert_eq!(cap_params(params, 0, DropPolicy::Newest).len(), 0);
+
+        let mut buf = vec![];
         write_data(
             &mut buf,
-            [("first", [("p-one", "pv-one"), ("p-two", "pv-two")])],
+            [(
+                "exampleSDID@32473",
+                cap_params(params.to_vec(), 3, DropPolicy::Oldest),
+            )],
         )
         .unwrap();
+        assert_eq!(buf, br#" [exampleSDID@32473 b="2" c="3" d="4"]"#);
+    }
+
+    #[test]
+    fn should_iterate_sd_elements_with_escaped_closing_bracket() {
+        let msg = br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" path="[a\]"][examplePriority@32473 class="high"] order [7] shipped"#;
+
+        let parts = parse_syslog_message(msg);
         assert_eq!(
-            std::str::from_utf8(&buf).unwrap(),
-            r#" [first p-one="pv-one" p-two="pv-two"]"#
+            parts.data,
+            r#"[exampleSDID@32473 iut="3" path="[a\]"][examplePriority@32473 class="high"]"#
         );
+        assert_eq!(parts.msg, "order [7] shipped");
 
-        buf.clear();
-        write_data(
-            &mut buf,
-            [
-                ("first", [("p-one", "pv-one"), ("p-two", "pv-two")]),
-                ("second", [("p-one", "pv-one"), ("p-two", "pv-two")]),
-            ],
-        )
-        .unwrap();
+        let elems: Vec<&str> = sd_elements(parts.data).collect();
         assert_eq!(
-            std::str::from_utf8(&buf).unwrap(),
-            r#" [first p-one="pv-one" p-two="pv-two"][second p-one="pv-one" p-two="pv-two"]"#
+            elems,
+            [
+                r#"[exampleSDID@32473 iut="3" path="[a\]"]"#,
+                r#"[examplePriority@32473 class="high"]"#,
+            ]
         );
-    }
 
-    #[derive(Debug)]
-    struct Parts<'a> {
-        prio: &'a str,
-        timestamp: &'a str,
-        hostname: &'a str,
-        app_name: &'a str,
-        proc_id: &'a str,
-        msg_id: &'a str,
-        data: &'a str,
-        msg: &'a str,
+        let mut elems = sd_elements(r#"[a@32473 x="\"]"] [b@32473]"#);
+        assert_eq!(elems.next(), Some(r#"[a@32473 x="\"]"]"#));
+        assert_eq!(elems.next(), None);
+        assert_eq!(elems.remainder(), " [b@32473]");
+
+        let mut elems = sd_elements(r#"[a@32473 x="unclosed]"#);
+        assert_eq!(elems.next(), None);
+        assert_eq!(elems.remainder(), r#"[a@32473 x="unclosed]"#);
+
+        assert_eq!(sd_elements("-").next(), None);
     }
 
     fn parse_syslog_message(buf: &[u8]) -> Parts<'_> {
@@ -1066,8 +10201,9 @@ mod tests {
         let (msg_id, s) = s.split_once(DELIM).unwrap();
 
         let (data, msg) = if s.starts_with('[') {
-            let index = s.rfind(']').expect("There should be a closing delimiter");
-            let (data, s) = s.split_at(index + 1);
+            let mut elems = sd_elements(s);
+            elems.by_ref().for_each(drop);
+            let (data, s) = s.split_at(s.len() - elems.remainder().len());
             let s = s.trim();
 
             (data, s.strip_prefix(UTF8_BOM).unwrap_or(s))
@@ -1088,6 +10224,110 @@ mod tests {
         }
     }
 
+    // See: <https://datatracker.ietf.org/doc/html/rfc5424#section-6.5>
+    #[test]
+    fn should_parse_rfc_examples_with_public_parser() {
+        let message = parse(b"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - 'su root' failed for lonvick on /dev/pts/8").unwrap();
+        assert_eq!(
+            message,
+            Message {
+                priority: Priority::new(Facility::Auth, Severity::Crit),
+                version: 1,
+                timestamp: Some("2003-10-11T22:14:15.003Z"),
+                hostname: Some("mymachine.example.com"),
+                app_name: Some("su"),
+                proc_id: None,
+                msg_id: Some("ID47"),
+                structured_data: None,
+                msg: Some(b"'su root' failed for lonvick on /dev/pts/8"),
+            }
+        );
+
+        let message = parse(b"<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts.").unwrap();
+        assert_eq!(
+            message,
+            Message {
+                priority: Priority::new(Facility::Local4, Severity::Notice),
+                version: 1,
+                timestamp: Some("2003-08-24T05:14:15.000003-07:00"),
+                hostname: Some("192.0.2.1"),
+                app_name: Some("myproc"),
+                proc_id: Some("8710"),
+                msg_id: None,
+                structured_data: None,
+                msg: Some(b"%% It's time to make the do-nuts."),
+            }
+        );
+
+        let message = parse(br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"] An application event log entry..."#).unwrap();
+        assert_eq!(
+            message.structured_data,
+            Some(r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#)
+        );
+        assert_eq!(message.msg_str(), Some("An application event log entry..."));
+
+        let message = parse(br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"]"#).unwrap();
+        assert_eq!(
+            message.sd_elements().collect::<Vec<_>>(),
+            [
+                r#"[exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]"#,
+                r#"[examplePriority@32473 class="high"]"#,
+            ]
+        );
+        assert_eq!(message.msg, None);
+    }
+
+    #[test]
+    fn should_parse_formatted_message_with_escaped_data() {
+        let fmt = Config {
+            hostname: Some("mymachine.example.com"),
+            ..Default::default()
+        }
+        .into_formatter();
+        let mut buf = vec![];
+        fmt.write_with_data(
+            &mut buf,
+            Severity::Info,
+            "-",
+            Msg::NonUnicodeBytes(b"\xFF raw"),
+            None,
+            [
+                ("a@32473", [("note", r#"a ] and a " in a value"#)]),
+                ("b@32473", [("n", "1")]),
+            ],
+        )
+        .unwrap();
+
+        let message = parse(&buf).unwrap();
+        assert_eq!(message.timestamp, None);
+        assert_eq!(message.hostname, Some("mymachine.example.com"));
+        assert_eq!(
+            message.sd_elements().collect::<Vec<_>>(),
+            [
+                r#"[a@32473 note="a \] and a \" in a value"]"#,
+                r#"[b@32473 n="1"]"#
+            ]
+        );
+        assert_eq!(message.msg, Some(&b"\xFF raw"[..]));
+        assert_eq!(message.msg_str(), None);
+
+        let error = |field| Err(ParseError { field });
+        assert_eq!(parse(b""), error(Field::Pri));
+        assert_eq!(parse(b"<192>1 - - - - - -"), error(Field::Pri));
+        assert_eq!(parse(b"<134>01 - - - - - -"), error(Field::Version));
+        assert_eq!(parse(b"<134>1 - - - - -"), error(Field::MsgId));
+        assert_eq!(parse(b"<134>1 -  - - - -"), error(Field::Hostname));
+        assert_eq!(
+            parse(b"<134>1 - - - - - [a@32473"),
+            error(Field::StructuredData)
+        );
+        assert_eq!(
+            parse(b"<134>1 - - - - - [a@32473]x"),
+            error(Field::StructuredData)
+        );
+        assert!(parse(b"<134>1 - - - - - -").is_ok());
+    }
+
     // See: <https://datatracker.ietf.org/doc/html/rfc5424#section-6.5>
     #[test]
     fn should_parse_example_1_with_no_structured_data() {
@@ -0,0 +1,183 @@
+//! Derive macros for [syslog_fmt](https://docs.rs/syslog_fmt).
+//!
+//! Use the macros through the `derive` feature of `syslog_fmt` rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Derive `syslog_fmt::v5424::IntoStructuredData` for a struct with named fields.
+///
+/// The struct is converted into an SD-ELEMENT with each field as an SD-PARAM.
+/// The value of a param is the `Display` output of the field.
+///
+/// - `#[sd(id = "...")]` on the struct sets the SD-ID, this attribute is required.
+/// - `#[sd(rename = "...")]` on a field sets the PARAM-NAME, the field name is used by default.
+/// - `#[sd(skip)]` on a field leaves the field out of the params.
+///
+/// The SD-ID and PARAM-NAMEs are validated at compile time.
+#[proc_macro_derive(IntoStructuredData, attributes(sd))]
+pub fn derive_into_structured_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "IntoStructuredData can only be derived for structs",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "IntoStructuredData can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut sd_id = None;
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("sd")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                let id: LitStr = meta.value()?.parse()?;
+                validate_sd_id(&id.value(), id.span())?;
+                sd_id = Some(id);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported sd attribute, expected `id`"))
+            }
+        })?;
+    }
+
+    let Some(sd_id) = sd_id else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[sd(id = \"...\")]` attribute",
+        ));
+    };
+
+    let mut param_names = vec![];
+    let mut field_idents = vec![];
+
+    for field in &fields.named {
+        let Some(ident) = &field.ident else {
+            continue;
+        };
+
+        let mut skip = false;
+        let mut rename = None;
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("sd")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    rename = Some(meta.value()?.parse::<LitStr>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported sd attribute, expected `rename` or `skip`"))
+                }
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        let param_name = rename.unwrap_or_else(|| {
+            let name = ident.to_string();
+            LitStr::new(name.trim_start_matches("r#"), ident.span())
+        });
+        validate_sd_name(&param_name.value(), param_name.span())?;
+
+        param_names.push(param_name);
+        field_idents.push(ident);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::syslog_fmt::v5424::IntoStructuredData for #name #ty_generics #where_clause {
+            fn sd_id(&self) -> &'static str {
+                #sd_id
+            }
+
+            fn sd_params(&self) -> ::std::vec::Vec<(&'static str, ::std::string::String)> {
+                ::std::vec![
+                    #((#param_names, ::std::string::ToString::to_string(&self.#field_idents)),)*
+                ]
+            }
+        }
+    })
+}
+
+/// Validate an SD-ID, which is an SD-NAME with at most one at-sign
+/// followed by a private enterprise number.
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6.3.2)
+fn validate_sd_id(id: &str, span: Span) -> syn::Result<()> {
+    validate_sd_name(id, span)?;
+
+    let Some((name, enterprise_id)) = id.split_once('@') else {
+        return Ok(());
+    };
+
+    // enterpriseId = 1*DIGIT *("." 1*DIGIT)
+    let is_enterprise_id = enterprise_id
+        .split('.')
+        .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+
+    if name.is_empty() || !is_enterprise_id {
+        return Err(syn::Error::new(
+            span,
+            "An SD-ID with an '@' must have the format `name@<private enterprise number>`",
+        ));
+    }
+
+    Ok(())
+}
+
+/// The maximum length of an SD-NAME
+const SD_NAME_MAX_LEN: usize = 32;
+
+/// Validate an SD-NAME, which is the format of both the SD-ID and PARAM-NAME.
+///
+/// SD-NAME = 1*32PRINTUSASCII ; except '=', SP, ']', %d34 (")
+///
+/// [spec](https://datatracker.ietf.org/doc/html/rfc5424#section-6)
+fn validate_sd_name(name: &str, span: Span) -> syn::Result<()> {
+    if name.is_empty() {
+        return Err(syn::Error::new(span, "The name is empty"));
+    }
+
+    if let Some((index, ch)) = name
+        .char_indices()
+        .find(|(_, ch)| !matches!(ch, '!'..='~') || matches!(ch, '=' | ' ' | ']' | '"'))
+    {
+        return Err(syn::Error::new(
+            span,
+            format!("The name contains the illegal char {ch:?} at {index}"),
+        ));
+    }
+
+    if name.len() > SD_NAME_MAX_LEN {
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "The name is {} bytes long, the maximum is {SD_NAME_MAX_LEN}",
+                name.len()
+            ),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use syslog_fmt::{
+    v5424::{Formatter, IntoStructuredData},
+    Severity,
+};
+
+#[derive(IntoStructuredData)]
+#[sd(id = "shipment@32473")]
+struct Shipment<'a> {
+    order_id: u32,
+    #[sd(rename = "carrierName")]
+    carrier: &'a str,
+    #[sd(skip)]
+    _internal: Vec<u8>,
+    weight: f32,
+}
+
+#[test]
+fn should_derive_params_from_fields() {
+    let shipment = Shipment {
+        order_id: 7,
+        carrier: "post",
+        _internal: vec![1, 2, 3],
+        weight: 1.5,
+    };
+
+    assert_eq!(shipment.sd_id(), "shipment@32473");
+    assert_eq!(
+        shipment.sd_params(),
+        [
+            ("order_id", "7".to_owned()),
+            ("carrierName", "post".to_owned()),
+            ("weight", "1.5".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn should_write_derived_element() {
+    let shipment = Shipment {
+        order_id: 7,
+        carrier: "post",
+        _internal: vec![],
+        weight: 1.5,
+    };
+
+    let mut buf = Vec::<u8>::new();
+    shipment.write_sd_element(&mut buf).unwrap();
+    assert_eq!(
+        buf,
+        br#" [shipment@32473 order_id="7" carrierName="post" weight="1.5"]"#
+    );
+
+    let params = shipment.sd_params();
+    let params = params.iter().map(|(name, value)| (*name, value.as_str()));
+    let mut msg = Vec::<u8>::new();
+    Formatter::default()
+        .write_with_data(
+            &mut msg,
+            Severity::Info,
+            "2003-10-11T22:14:15.003Z",
+            "shipped",
+            None,
+            [(shipment.sd_id(), params)],
+        )
+        .unwrap();
+
+    assert!(msg.ends_with(&[&buf[..], " \u{feff}shipped".as_bytes()].concat()));
+}
@@ -0,0 +1,67 @@
+use syslog_fmt::{
+    v5424::{self, Timestamp},
+    Facility, Severity,
+};
+
+/// The fixture contains the messages below, each followed by a LF
+const GOLDEN: &[u8] = include_bytes!("fixtures/golden.log");
+
+#[test]
+fn should_reproduce_golden_output() {
+    // a fixed timestamp makes the output independent of the clock
+    let timestamp = "2003-10-11T22:14:15.003Z";
+    let formatter = v5424::Config {
+        facility: Facility::Local4,
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("evntslog"),
+        proc_id: Some("8710"),
+        ..Default::default()
+    }
+    .into_formatter();
+
+    let mut buf = Vec::<u8>::new();
+
+    formatter
+        .write_without_data(
+            &mut buf,
+            Severity::Crit,
+            timestamp,
+            "'su root' failed for lonvick on /dev/pts/8",
+            Some("ID47"),
+        )
+        .unwrap();
+    buf.push(b'\n');
+
+    formatter
+        .write_with_data(
+            &mut buf,
+            Severity::Notice,
+            Timestamp::PreformattedStr(timestamp),
+            "An application event log entry...",
+            Some("ID47"),
+            [
+                (
+                    "exampleSDID@32473",
+                    vec![
+                        ("iut", "3"),
+                        ("eventSource", "Application"),
+                        ("eventID", "1011"),
+                    ],
+                ),
+                ("examplePriority@32473", vec![("class", "high")]),
+            ],
+        )
+        .unwrap();
+    buf.push(b'\n');
+
+    formatter
+        .write_without_data(&mut buf, Severity::Debug, Timestamp::None, "", None)
+        .unwrap();
+    buf.push(b'\n');
+
+    assert_eq!(
+        String::from_utf8_lossy(&buf),
+        String::from_utf8_lossy(GOLDEN)
+    );
+    assert_eq!(buf, GOLDEN, "the output should be byte for byte equal");
+}
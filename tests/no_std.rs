@@ -0,0 +1,55 @@
+//! Smoke test that the crate is usable from a `no_std` crate with only `alloc`.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+use syslog_fmt::{v5424::Config, Facility, Priority, Severity};
+
+#[test]
+fn should_use_priority_without_std() {
+    let priority = Priority::new(Facility::Local4, Severity::Notice);
+    assert_eq!(priority.value(), 165);
+    assert_eq!(priority.to_string(), "165");
+
+    assert_eq!(Facility::try_from(16_u8).unwrap(), Facility::Local0);
+    assert_eq!(
+        format!("{}", Severity::try_from(8_u8).unwrap_err()),
+        Severity::try_from(8_i32).unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn should_format_message_without_std() {
+    let fmt = Config {
+        facility: Facility::Local4,
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("evntslog"),
+        ..Default::default()
+    }
+    .into_formatter();
+
+    let mut s = String::new();
+    fmt.format_into(
+        &mut s,
+        Severity::Notice,
+        "2003-10-11T22:14:15.003Z",
+        "An application event log entry...",
+        Some("ID47"),
+        [(
+            "exampleSDID@32473",
+            [("iut", "3"), ("eventSource", "Application")],
+        )],
+    )
+    .unwrap();
+
+    assert_eq!(
+        s,
+        "<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 \
+         [exampleSDID@32473 iut=\"3\" eventSource=\"Application\"] \u{feff}An application event log entry..."
+    );
+}
@@ -0,0 +1,133 @@
+//! The examples of [RFC 5424 section 6.5](https://datatracker.ietf.org/doc/html/rfc5424#section-6.5)
+//! generated with the public API.
+//!
+//! The examples are byte for byte equal, the timestamps are preformatted as given in the RFC.
+use syslog_fmt::{
+    v5424::{self, Msg},
+    Facility, Severity,
+};
+
+const BOM: &str = "\u{feff}";
+
+#[test]
+fn should_generate_example_1_with_no_structured_data() {
+    let formatter = v5424::Config {
+        facility: Facility::Auth,
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("su"),
+        ..Default::default()
+    }
+    .into_formatter();
+
+    let mut buf = Vec::<u8>::new();
+    formatter
+        .write_without_data(
+            &mut buf,
+            Severity::Crit,
+            "2003-10-11T22:14:15.003Z",
+            "'su root' failed for lonvick on /dev/pts/8",
+            Some("ID47"),
+        )
+        .unwrap();
+
+    let expected = format!("<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - {BOM}'su root' failed for lonvick on /dev/pts/8");
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
+
+#[test]
+fn should_generate_example_2_with_no_structured_data() {
+    let formatter = v5424::Config {
+        facility: Facility::Local4,
+        hostname: Some("192.0.2.1"),
+        app_name: Some("myproc"),
+        proc_id: Some("8710"),
+        ..Default::default()
+    }
+    .into_formatter();
+
+    // the encoding of the MSG is unknown, so it has no BOM
+    let mut buf = Vec::<u8>::new();
+    formatter
+        .write_without_data(
+            &mut buf,
+            Severity::Notice,
+            "2003-08-24T05:14:15.000003-07:00",
+            Msg::NonUnicodeBytes(b"%% It's time to make the do-nuts."),
+            None,
+        )
+        .unwrap();
+
+    let expected = "<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts.";
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
+
+#[test]
+fn should_generate_example_3_with_structured_data() {
+    let formatter = v5424::Config {
+        facility: Facility::Local4,
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("evntslog"),
+        ..Default::default()
+    }
+    .into_formatter();
+
+    let mut buf = Vec::<u8>::new();
+    formatter
+        .write_with_data(
+            &mut buf,
+            Severity::Notice,
+            "2003-10-11T22:14:15.003Z",
+            "An application event log entry...",
+            Some("ID47"),
+            [(
+                "exampleSDID@32473",
+                [
+                    ("iut", "3"),
+                    ("eventSource", "Application"),
+                    ("eventID", "1011"),
+                ],
+            )],
+        )
+        .unwrap();
+
+    let expected = format!(
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"] {BOM}An application event log entry..."#
+    );
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
+
+#[test]
+fn should_generate_example_4_structured_data_only() {
+    let formatter = v5424::Config {
+        facility: Facility::Local4,
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("evntslog"),
+        ..Default::default()
+    }
+    .into_formatter();
+
+    let mut buf = Vec::<u8>::new();
+    formatter
+        .write_with_data(
+            &mut buf,
+            Severity::Notice,
+            "2003-10-11T22:14:15.003Z",
+            "",
+            Some("ID47"),
+            vec![
+                (
+                    "exampleSDID@32473",
+                    vec![
+                        ("iut", "3"),
+                        ("eventSource", "Application"),
+                        ("eventID", "1011"),
+                    ],
+                ),
+                ("examplePriority@32473", vec![("class", "high")]),
+            ],
+        )
+        .unwrap();
+
+    let expected = r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"]"#;
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
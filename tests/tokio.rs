@@ -0,0 +1,71 @@
+use syslog_fmt::{v5424, Facility, Severity};
+use tokio::io::AsyncReadExt;
+
+#[tokio::test]
+async fn should_write_same_bytes_as_sync_formatter() {
+    let formatter = v5424::Config {
+        facility: Facility::Local4,
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("evntslog"),
+        ..Default::default()
+    }
+    .into_formatter();
+    let timestamp = "2003-10-11T22:14:15.003Z";
+    let data = [("exampleSDID@32473", [("iut", "3"), ("eventID", "1011")])];
+
+    let mut expected = Vec::<u8>::new();
+    formatter
+        .write_with_data(
+            &mut expected,
+            Severity::Notice,
+            timestamp,
+            "An application event log entry...",
+            Some("ID47"),
+            data,
+        )
+        .unwrap();
+    formatter
+        .write_without_data(
+            &mut expected,
+            Severity::Crit,
+            timestamp,
+            "'su root' failed for lonvick on /dev/pts/8",
+            None,
+        )
+        .unwrap();
+
+    // a small duplex buffer applies backpressure, so the reader must run concurrently
+    let (mut client, mut server) = tokio::io::duplex(16);
+    let read = async {
+        let mut received = vec![];
+        server.read_to_end(&mut received).await.unwrap();
+        received
+    };
+    let write = async {
+        formatter
+            .write_with_data_async(
+                &mut client,
+                Severity::Notice,
+                timestamp,
+                "An application event log entry...",
+                Some("ID47"),
+                data,
+            )
+            .await
+            .unwrap();
+        formatter
+            .write_without_data_async(
+                &mut client,
+                Severity::Crit,
+                timestamp,
+                "'su root' failed for lonvick on /dev/pts/8",
+                None,
+            )
+            .await
+            .unwrap();
+        drop(client);
+    };
+
+    let (received, ()) = tokio::join!(read, write);
+    assert_eq!(received, expected);
+}
@@ -0,0 +1,157 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use syslog_fmt::{
+    tracing::SyslogLayer,
+    v5424::{self, Config},
+    Facility, Priority, Severity,
+};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// A writer sharing the written messages with the test
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl io::Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn should_map_level_to_severity() {
+    assert_eq!(Severity::from(tracing::Level::ERROR), Severity::Err);
+    assert_eq!(Severity::from(tracing::Level::WARN), Severity::Warning);
+    assert_eq!(Severity::from(tracing::Level::INFO), Severity::Info);
+    assert_eq!(Severity::from(tracing::Level::DEBUG), Severity::Debug);
+    assert_eq!(Severity::from(tracing::Level::TRACE), Severity::Debug);
+}
+
+#[test]
+fn should_write_events_as_syslog_messages() {
+    let messages = SharedBuf::default();
+    let formatter = Config {
+        hostname: Some("mymachine"),
+        app_name: Some("app"),
+        ..Default::default()
+    }
+    .into_formatter();
+    let layer = SyslogLayer::new(formatter, messages.clone(), "fields@32473", "span@32473");
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", id = 7, path = "/login");
+        let _guard = span.enter();
+        tracing::warn!(user = "bob", attempts = 3, "login failed for {}", "bob");
+        tracing::trace!("no fields");
+    });
+
+    let messages = messages.0.lock().unwrap();
+    assert_eq!(messages.len(), 2);
+
+    let msg = v5424::parse(&messages[0]).unwrap();
+    assert_eq!(
+        msg.priority,
+        Priority::new(Facility::Local0, Severity::Warning)
+    );
+    assert_eq!(msg.hostname, Some("mymachine"));
+    assert_eq!(msg.app_name, Some("app"));
+    assert_eq!(
+        msg.structured_data,
+        Some(r#"[fields@32473 user="bob" attempts="3"][span@32473 id="7" path="/login"]"#)
+    );
+    assert_eq!(msg.msg_str(), Some("login failed for bob"));
+
+    let msg = v5424::parse(&messages[1]).unwrap();
+    assert_eq!(
+        msg.priority,
+        Priority::new(Facility::Local0, Severity::Debug)
+    );
+    assert_eq!(
+        msg.structured_data,
+        Some(r#"[span@32473 id="7" path="/login"]"#)
+    );
+    assert_eq!(msg.msg_str(), Some("no fields"));
+}
+
+#[test]
+fn should_sanitize_field_names() {
+    let messages = SharedBuf::default();
+    let layer = SyslogLayer::new(
+        Config::default().into_formatter(),
+        messages.clone(),
+        "fields@32473",
+        "span@32473",
+    );
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", "request id" = 7);
+        let _guard = span.enter();
+        tracing::info!("user=name" = "bob", "a]\"b" = 3, "login");
+    });
+
+    let messages = messages.0.lock().unwrap();
+    assert_eq!(messages.len(), 1);
+
+    let msg = v5424::parse(&messages[0]).unwrap();
+    assert_eq!(
+        msg.structured_data,
+        Some(r#"[fields@32473 user_name="bob" a__b="3"][span@32473 request_id="7"]"#)
+    );
+    assert_eq!(msg.msg_str(), Some("login"));
+}
+
+#[test]
+fn should_pass_errors_to_error_handler() {
+    static ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+    struct FailingWriter;
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let layer = SyslogLayer::new(
+        Config::default().into_formatter(),
+        FailingWriter,
+        "fields@32473",
+        "span@32473",
+    );
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::with_default(subscriber, || tracing::info!("dropped"));
+    assert_eq!(ERRORS.load(Ordering::Relaxed), 0);
+
+    let layer = SyslogLayer::new(
+        Config::default().into_formatter(),
+        FailingWriter,
+        "fields@32473",
+        "span@32473",
+    )
+    .with_error_handler(|err| {
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        ERRORS.fetch_add(1, Ordering::Relaxed);
+    });
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!(user = "bob", "login");
+        tracing::info!("no fields");
+    });
+    assert_eq!(ERRORS.load(Ordering::Relaxed), 2);
+}